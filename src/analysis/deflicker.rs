@@ -8,6 +8,9 @@
 //! and the target is computed from the deflicker-free luminance
 //! `L0 = L_measured / 2^offset`. L0 is invariant under the correction
 //! itself, so re-running the command never re-smooths its own output.
+//!
+//! When `analyze scenes` found several shoots in the folder, each scene gets
+//! its own target, so no scene's brightness bleeds across a cut.
 
 use std::path::PathBuf;
 
@@ -118,6 +121,11 @@ pub fn run_deflicker(
         developed: true,
     };
 
+    let segments = match project.analysis.as_ref().and_then(|a| a.scenes.as_ref()) {
+        Some(scenes) => scenes.segments(n),
+        None => std::iter::once(0..n).collect(),
+    };
+
     let mut passes_run = 0;
     let mut converged = false;
 
@@ -136,10 +144,15 @@ pub fn run_deflicker(
             .collect();
 
         if layer.target.is_empty() {
-            layer.target = gaussian_smooth(&l0, opts.smoothing_frames as f32 / 4.0)
-                .into_iter()
-                .map(|v| v.max(LUMA_FLOOR))
-                .collect();
+            // Each scene gets its own target: smoothing across a cut would
+            // drag one shoot's brightness into the next.
+            for range in &segments {
+                layer.target.extend(
+                    gaussian_smooth(&l0[range.clone()], opts.smoothing_frames as f32 / 4.0)
+                        .into_iter()
+                        .map(|v| v.max(LUMA_FLOOR)),
+                );
+            }
         }
 
         let mut max_delta = 0.0f32;
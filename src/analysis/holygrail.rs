@@ -5,6 +5,15 @@
 //! cumulative inverse of those camera exposure steps, so the develop-side
 //! exposure cancels each jump and the remaining brightness ramp stays smooth
 //! and keyframeable.
+//!
+//! Exposure changes EXIF can't see (an ND filter swapped mid-shoot) enter as
+//! manual EV events, and on request camera exposure compensation is read
+//! from the ExposureBiasValue tag; both fold into the same cumulative
+//! compensation.
+//!
+//! Rotate and stretch are either auto-fit so the compensation ends at zero,
+//! or solved by least squares against the measured source luminance so the
+//! compensated clip follows a smooth trend of what the camera captured.
 
 use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
@@ -12,11 +21,15 @@ use std::sync::atomic::{AtomicUsize, Ordering};
 use rayon::prelude::*;
 use serde::{Deserialize, Serialize};
 
+use crate::analysis::deflicker::gaussian_smooth;
 use crate::analysis::{now_unix, source_fingerprint};
 use crate::error::{LapsifyError, Result};
-use crate::exif::{camera_ev, read_frame_exif};
+use crate::exif::{biased_camera_ev, camera_ev, read_frame_exif};
 use crate::progress::{ProgressEvent, ProgressReporter};
 
+/// Guard for log2 on near-black frames.
+const LUMA_FLOOR: f32 = 1e-6;
+
 #[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct HolyGrailLayer {
     /// Cumulative compensation in EV stops: raw[i] = EV_cam[i] − EV_cam[0].
@@ -30,6 +43,19 @@ pub struct HolyGrailLayer {
     /// Frames whose EXIF was missing (their step was carried forward).
     #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub frames_missing_exif: Vec<u32>,
+    /// Manual exposure changes folded into `raw`, kept so re-running the
+    /// analysis does not lose them.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ev_events: Vec<EvEvent>,
+    /// Whether camera exposure compensation (ExposureBiasValue) was folded
+    /// into `raw`.
+    #[serde(default)]
+    pub exposure_bias: bool,
+    /// Per-frame residual of a luminance fit in EV: compensated source
+    /// luminance minus the smooth trend it was fit to. Empty unless rotate
+    /// and stretch were fit to luminance.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fit_residuals: Vec<f32>,
     pub computed_at_unix: u64,
     pub source_fingerprint: String,
 }
@@ -51,11 +77,73 @@ impl HolyGrailLayer {
     }
 }
 
+/// A manual exposure change invisible to EXIF: from `frame` onward the
+/// capture is `ev` stops darker, so the compensation rises by `ev`
+/// ("+3 EV from frame 812" for a 3-stop ND filter put on at frame 812).
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct EvEvent {
+    pub frame: u32,
+    pub ev: f32,
+}
+
+/// How rotate (and, for a luminance fit, stretch) are chosen when not given.
+#[derive(Debug, Clone, Default)]
+pub enum RotateFit {
+    /// Tilt the baseline so the compensation ends at zero.
+    #[default]
+    End,
+    /// Solve rotate and stretch by least squares so the compensated source
+    /// luminance follows a Gaussian-smoothed trend of the captured one.
+    Luminance {
+        /// Mean linear source luminance per frame.
+        source_luma: Vec<f32>,
+        /// Low-pass window of the trend, in frames.
+        trend_frames: u32,
+    },
+}
+
+/// Result of fitting rotate and stretch against luminance.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LuminanceFit {
+    pub rotate: f32,
+    pub stretch: f32,
+    /// Per-frame residual in EV.
+    pub residuals: Vec<f32>,
+}
+
+impl LuminanceFit {
+    /// Root-mean-square residual in EV.
+    pub fn rms(&self) -> f32 {
+        let n = self.residuals.len().max(1) as f32;
+        (self.residuals.iter().map(|r| r * r).sum::<f32>() / n).sqrt()
+    }
+}
+
 pub struct HolyGrailOptions {
-    /// Manual rotate override. None = auto-fit so the compensation ends at 0.
+    /// Manual rotate override. None = fit according to `fit`.
     pub rotate: Option<f32>,
-    /// Manual stretch override. None = 1.0.
+    /// Manual stretch override. None = 1.0, or solved by a luminance fit.
     pub stretch: Option<f32>,
+    /// How to fit the values that were not overridden.
+    pub fit: RotateFit,
+    /// Manual EV events to fold into the compensation.
+    pub events: Vec<EvEvent>,
+    /// Fold camera exposure compensation (ExposureBiasValue) into the camera
+    /// EV. Off by default: in the auto modes the camera already expresses
+    /// the bias through shutter, aperture or ISO, so it would count twice.
+    pub exposure_bias: bool,
+}
+
+impl Default for HolyGrailOptions {
+    fn default() -> Self {
+        Self {
+            rotate: None,
+            stretch: None,
+            fit: RotateFit::End,
+            events: Vec::new(),
+            exposure_bias: false,
+        }
+    }
 }
 
 /// The pure staircase inversion: per-frame camera EVs (None = missing EXIF)
@@ -92,6 +180,137 @@ pub fn layer_from_evs(evs: &[Option<f32>]) -> Result<(Vec<f32>, Vec<u32>)> {
     Ok((raw, missing))
 }
 
+/// Add manual EV events on top of the staircase: each event raises the
+/// compensation of its frame and every frame after it.
+pub fn fold_ev_events(raw: &mut [f32], events: &[EvEvent]) -> Result<()> {
+    for event in events {
+        let start = event.frame as usize;
+        if start >= raw.len() {
+            return Err(LapsifyError::message(format!(
+                "EV event at frame {} is out of range (0-{})",
+                event.frame,
+                raw.len().saturating_sub(1)
+            )));
+        }
+        for value in &mut raw[start..] {
+            *value += event.ev;
+        }
+    }
+    Ok(())
+}
+
+/// Fit rotate and stretch so the compensated clip tracks a smooth trend.
+///
+/// With `y` the captured luminance in EV, `u` the normalized clip position
+/// and `T` the smoothed `y`, the compensated brightness is
+/// `y + stretch * (raw + rotate * u)`. Writing `k = stretch * rotate` makes
+/// that linear in `(stretch, k)`, so minimizing the squared distance to `T`
+/// is a 2x2 normal-equation solve. A `rotate` or `stretch` that is given is
+/// held fixed and only the other one is solved; the residuals are always
+/// those of the returned pair.
+pub fn fit_to_luminance(
+    raw: &[f32],
+    source_luma: &[f32],
+    trend_frames: u32,
+    rotate: Option<f32>,
+    stretch: Option<f32>,
+) -> Result<LuminanceFit> {
+    let n = raw.len();
+    if source_luma.len() != n {
+        return Err(LapsifyError::message(format!(
+            "Source luminance has {} frames but the sequence has {n}; re-run `lapsify analyze luminance`",
+            source_luma.len()
+        )));
+    }
+    if n < 2 {
+        return Err(LapsifyError::message(
+            "Fitting to luminance needs at least 2 frames",
+        ));
+    }
+
+    let y: Vec<f64> = source_luma
+        .iter()
+        .map(|l| l.max(LUMA_FLOOR).log2() as f64)
+        .collect();
+    let trend = gaussian_smooth(
+        &y.iter().map(|v| *v as f32).collect::<Vec<_>>(),
+        trend_frames as f32 / 4.0,
+    );
+    let u = |i: usize| i as f64 / (n - 1) as f64;
+
+    // Normal equations for residual = y - T + stretch * raw + k * u.
+    let (mut aa, mut ab, mut bb, mut ad, mut bd) = (0.0f64, 0.0, 0.0, 0.0, 0.0);
+    for i in 0..n {
+        let a = raw[i] as f64;
+        let b = u(i);
+        let d = trend[i] as f64 - y[i];
+        aa += a * a;
+        ab += a * b;
+        bb += b * b;
+        ad += a * d;
+        bd += b * d;
+    }
+    // The tilt that best goes with a given stretch.
+    let tilt_for = |stretch: f64| (bd - stretch * ab) / bb;
+
+    let held_stretch = stretch;
+    let (stretch, k) = match (stretch, rotate) {
+        (Some(stretch), Some(rotate)) => (stretch as f64, (stretch * rotate) as f64),
+        (Some(stretch), None) => (stretch as f64, tilt_for(stretch as f64)),
+        (None, Some(rotate)) => {
+            // residual = y - T + stretch * (raw + rotate * u)
+            let rotate = rotate as f64;
+            let (cc, cd) = (
+                aa + 2.0 * rotate * ab + rotate * rotate * bb,
+                ad + rotate * bd,
+            );
+            let stretch = if cc > 1e-12 { cd / cc } else { 1.0 };
+            (stretch, stretch * rotate)
+        }
+        (None, None) => {
+            let det = aa * bb - ab * ab;
+            if det.abs() > 1e-9 * (aa * bb).max(1e-12) {
+                ((ad * bb - bd * ab) / det, (aa * bd - ab * ad) / det)
+            } else {
+                // The staircase is flat (or a pure ramp): only the tilt is
+                // identifiable, so keep the compensation at full strength.
+                (1.0, tilt_for(1.0))
+            }
+        }
+    };
+    if held_stretch.is_none() && stretch < 1e-3 {
+        return Err(LapsifyError::message(
+            "Luminance fit found no usable compensation (stretch near or below 0); the exposure steps don't show in the measured luminance",
+        ));
+    }
+
+    let residuals = (0..n)
+        .map(|i| (y[i] - trend[i] as f64 + stretch * raw[i] as f64 + k * u(i)) as f32)
+        .collect();
+
+    Ok(LuminanceFit {
+        rotate: rotate.unwrap_or((k / stretch) as f32),
+        stretch: stretch as f32,
+        residuals,
+    })
+}
+
+/// Parse a "FRAME:EV" manual event, e.g. "812:+3".
+pub fn parse_ev_event(input: &str) -> Result<EvEvent> {
+    let invalid = || {
+        LapsifyError::message(format!(
+            "Invalid EV event '{input}' (expected FRAME:EV, e.g. 812:+3)"
+        ))
+    };
+    let (frame, ev) = input.split_once(':').ok_or_else(invalid)?;
+    let frame = frame.trim().parse::<u32>().map_err(|_| invalid())?;
+    let ev = ev.trim().parse::<f32>().map_err(|_| invalid())?;
+    if !ev.is_finite() {
+        return Err(invalid());
+    }
+    Ok(EvEvent { frame, ev })
+}
+
 /// Read EXIF across the sequence and build the compensation layer plus
 /// capture timestamps (missing timestamps are interpolated between
 /// neighbors).
@@ -100,6 +319,15 @@ pub fn compute_holy_grail(
     opts: &HolyGrailOptions,
     reporter: &ProgressReporter,
 ) -> Result<(HolyGrailLayer, Option<Vec<i64>>)> {
+    if opts
+        .stretch
+        .is_some_and(|stretch| !stretch.is_finite() || stretch < 0.0)
+    {
+        return Err(LapsifyError::InvalidParam {
+            field: "stretch",
+            reason: "must be 0 or more".into(),
+        });
+    }
     let total = image_files.len();
     let done = AtomicUsize::new(0);
 
@@ -117,21 +345,34 @@ pub fn compute_holy_grail(
         })
         .collect();
 
-    let evs: Vec<Option<f32>> = exifs.iter().map(camera_ev).collect();
-    let (raw, missing) = layer_from_evs(&evs)?;
+    let ev_of = if opts.exposure_bias {
+        biased_camera_ev
+    } else {
+        camera_ev
+    };
+    let evs: Vec<Option<f32>> = exifs.iter().map(ev_of).collect();
+    let (mut raw, missing) = layer_from_evs(&evs)?;
+    fold_ev_events(&mut raw, &opts.events)?;
 
-    let rotate = opts.rotate.unwrap_or_else(|| {
-        // Auto-fit: tilt the baseline so the compensation ends at zero and
-        // the natural day-night ramp stays in the keyframes' hands.
-        -raw.last().copied().unwrap_or(0.0)
-    });
-    let stretch = opts.stretch.unwrap_or(1.0);
+    let (rotate, stretch, fit) = settle_rotate_stretch(&raw, opts)?;
+    if let Some(fit) = &fit {
+        reporter.report(ProgressEvent::HolyGrailFit {
+            rotate: fit.rotate,
+            stretch: fit.stretch,
+            rms_ev: fit.rms(),
+            residuals_ev: fit.residuals.clone(),
+        });
+    }
+    let fit_residuals = fit.map(|fit| fit.residuals).unwrap_or_default();
 
     let layer = HolyGrailLayer {
         raw,
         rotate,
         stretch,
         frames_missing_exif: missing,
+        ev_events: opts.events.clone(),
+        exposure_bias: opts.exposure_bias,
+        fit_residuals,
         computed_at_unix: now_unix(),
         source_fingerprint: source_fingerprint(image_files)?,
     };
@@ -141,6 +382,32 @@ pub fn compute_holy_grail(
     Ok((layer, times))
 }
 
+/// Rotate and stretch for a `raw` layer: the given values, with the rest
+/// chosen by `opts.fit`. A luminance fit always runs, even when both values
+/// are held, so its residuals are reported.
+fn settle_rotate_stretch(
+    raw: &[f32],
+    opts: &HolyGrailOptions,
+) -> Result<(f32, f32, Option<LuminanceFit>)> {
+    Ok(match &opts.fit {
+        RotateFit::End => (
+            // Auto-fit: tilt the baseline so the compensation ends at zero
+            // and the natural day-night ramp stays in the keyframes' hands.
+            opts.rotate
+                .unwrap_or_else(|| -raw.last().copied().unwrap_or(0.0)),
+            opts.stretch.unwrap_or(1.0),
+            None,
+        ),
+        RotateFit::Luminance {
+            source_luma,
+            trend_frames,
+        } => {
+            let fit = fit_to_luminance(raw, source_luma, *trend_frames, opts.rotate, opts.stretch)?;
+            (fit.rotate, fit.stretch, Some(fit))
+        }
+    })
+}
+
 /// Fill missing capture timestamps by linear interpolation between known
 /// neighbors (edges extend the nearest known spacing). None if no frame has
 /// a timestamp.
@@ -212,6 +479,141 @@ mod tests {
         assert!(layer_from_evs(&[]).is_err());
     }
 
+    #[test]
+    fn ev_events_raise_compensation_from_their_frame() {
+        // Camera steps one stop at frame 2; a 3-stop ND goes on at frame 3.
+        let evs = vec![Some(10.0), Some(10.0), Some(9.0), Some(9.0), Some(9.0)];
+        let (mut raw, _) = layer_from_evs(&evs).unwrap();
+        fold_ev_events(&mut raw, &[EvEvent { frame: 3, ev: 3.0 }]).unwrap();
+        assert_eq!(raw, vec![0.0, 0.0, -1.0, 2.0, 2.0]);
+
+        assert!(fold_ev_events(&mut raw, &[EvEvent { frame: 5, ev: 1.0 }]).is_err());
+    }
+
+    #[test]
+    fn parse_ev_event_accepts_signed_values() {
+        assert_eq!(
+            parse_ev_event("812:+3").unwrap(),
+            EvEvent {
+                frame: 812,
+                ev: 3.0
+            }
+        );
+        assert_eq!(
+            parse_ev_event("10:-1.5").unwrap(),
+            EvEvent {
+                frame: 10,
+                ev: -1.5
+            }
+        );
+        assert!(parse_ev_event("812").is_err());
+        assert!(parse_ev_event("-1:3").is_err());
+        assert!(parse_ev_event("5:abc").is_err());
+    }
+
+    #[test]
+    fn luminance_fit_cancels_camera_sawtooth() {
+        // The scene darkens 4 EV over the clip and the camera opens up one
+        // stop at frames 10, 20 and 30, so the captured series is a
+        // sawtooth. The fit keeps most of the staircase and tilts the
+        // darkening scene back onto the captured trend.
+        let n = 40;
+        let raw: Vec<f32> = (0..n).map(|i| -((i / 10) as f32)).collect();
+        let luma: Vec<f32> = (0..n)
+            .map(|i| {
+                let scene_ev = -4.0 * i as f32 / (n - 1) as f32;
+                0.2 * 2.0_f32.powf(scene_ev - raw[i])
+            })
+            .collect();
+
+        let fit = fit_to_luminance(&raw, &luma, 40, None, None).unwrap();
+        assert!((0.5..1.2).contains(&fit.stretch), "{fit:?}");
+        assert!(fit.rotate > 0.0, "tilt should lift the night end: {fit:?}");
+        assert_eq!(fit.residuals.len(), n);
+        assert!(fit.rms() < 0.2, "rms {}", fit.rms());
+    }
+
+    #[test]
+    fn luminance_fit_needs_matching_lengths() {
+        assert!(fit_to_luminance(&[0.0, 1.0], &[0.1, 0.2, 0.3], 10, None, None).is_err());
+        assert!(fit_to_luminance(&[0.0], &[0.1], 10, None, None).is_err());
+    }
+
+    #[test]
+    fn flat_staircase_fits_only_the_tilt() {
+        // No camera steps: stretch stays 1 and the residuals shrink against
+        // a pure brightness ramp.
+        let raw = vec![0.0f32; 20];
+        let luma: Vec<f32> = (0..20)
+            .map(|i| 0.1 * 2.0_f32.powf(i as f32 / 10.0))
+            .collect();
+        let fit = fit_to_luminance(&raw, &luma, 8, None, None).unwrap();
+        assert_relative_eq!(fit.stretch, 1.0);
+        assert!(fit.rms() < 0.2);
+
+        // A staircase that climbs evenly with the clip is just as
+        // degenerate. At full strength the best tilt leaves residuals with
+        // no trend left along the clip.
+        let raw: Vec<f32> = (0..20).map(|i| -2.0 * i as f32 / 19.0).collect();
+        let fit = fit_to_luminance(&raw, &luma, 8, None, None).unwrap();
+        assert_relative_eq!(fit.stretch, 1.0);
+        let trend: f32 = (0..20).map(|i| fit.residuals[i] * i as f32 / 19.0).sum();
+        assert!(trend.abs() < 1e-3, "residuals still tilt by {trend}");
+    }
+
+    #[test]
+    fn held_values_stay_and_residuals_follow_them() {
+        let n = 40;
+        let raw: Vec<f32> = (0..n).map(|i| -((i / 10) as f32)).collect();
+        let luma: Vec<f32> = (0..n)
+            .map(|i| 0.2 * 2.0_f32.powf(-4.0 * i as f32 / (n - 1) as f32 - raw[i]))
+            .collect();
+        let free = fit_to_luminance(&raw, &luma, 40, None, None).unwrap();
+
+        let held = fit_to_luminance(&raw, &luma, 40, None, Some(0.5)).unwrap();
+        assert_relative_eq!(held.stretch, 0.5);
+        assert!(held.rms() >= free.rms());
+        let pinned = fit_to_luminance(&raw, &luma, 40, Some(held.rotate), Some(0.5)).unwrap();
+        for (a, b) in pinned.residuals.iter().zip(&held.residuals) {
+            assert_relative_eq!(a, b, epsilon = 1e-5);
+        }
+
+        let held = fit_to_luminance(&raw, &luma, 40, Some(0.0), None).unwrap();
+        assert_relative_eq!(held.rotate, 0.0);
+        assert!(held.rms() >= free.rms());
+    }
+
+    #[test]
+    fn luminance_fit_reports_residuals_for_held_values() {
+        let n = 40;
+        let raw: Vec<f32> = (0..n).map(|i| -((i / 10) as f32)).collect();
+        let luma: Vec<f32> = (0..n)
+            .map(|i| 0.2 * 2.0_f32.powf(-4.0 * i as f32 / (n - 1) as f32 - raw[i]))
+            .collect();
+        let opts = HolyGrailOptions {
+            rotate: Some(2.0),
+            stretch: Some(0.0),
+            fit: RotateFit::Luminance {
+                source_luma: luma,
+                trend_frames: 40,
+            },
+            ..Default::default()
+        };
+        let (rotate, stretch, fit) = settle_rotate_stretch(&raw, &opts).unwrap();
+        assert_relative_eq!(rotate, 2.0);
+        assert_relative_eq!(stretch, 0.0);
+        assert_eq!(fit.unwrap().residuals.len(), n);
+
+        let end = HolyGrailOptions {
+            rotate: Some(2.0),
+            stretch: Some(0.5),
+            ..Default::default()
+        };
+        let (rotate, stretch, fit) = settle_rotate_stretch(&raw, &end).unwrap();
+        assert_eq!((rotate, stretch), (2.0, 0.5));
+        assert!(fit.is_none());
+    }
+
     #[test]
     fn auto_fit_rotate_lands_the_end_at_zero() {
         let layer = HolyGrailLayer {
@@ -219,6 +621,9 @@ mod tests {
             rotate: 3.0, // = -raw.last()
             stretch: 1.0,
             frames_missing_exif: vec![],
+            ev_events: vec![],
+            exposure_bias: false,
+            fit_residuals: vec![],
             computed_at_unix: 0,
             source_fingerprint: String::new(),
         };
@@ -236,6 +641,9 @@ mod tests {
             rotate: 0.0,
             stretch: 1.0,
             frames_missing_exif: vec![],
+            ev_events: vec![],
+            exposure_bias: false,
+            fit_residuals: vec![],
             computed_at_unix: 0,
             source_fingerprint: String::new(),
         };
@@ -246,6 +654,9 @@ mod tests {
             rotate: 0.0,
             stretch: 1.0,
             frames_missing_exif: vec![],
+            ev_events: vec![],
+            exposure_bias: false,
+            fit_residuals: vec![],
             computed_at_unix: 0,
             source_fingerprint: String::new(),
         };
@@ -0,0 +1,269 @@
+//! Capture interval report: check the intervalometer before rendering.
+//!
+//! Reads the capture time of every frame and summarizes the spacing: a
+//! histogram of intervals around the nominal one, gaps that mean dropped or
+//! deleted frames, frames sharing a timestamp, and clock jumps (the clock
+//! going backwards, or a step far too long to be a few lost frames — a
+//! clock change or a break between shoots).
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::exif::read_frame_exif;
+use crate::progress::{ProgressEvent, ProgressReporter};
+
+/// A forward step at least this many nominal intervals long (and at least
+/// `JUMP_MIN_MS`) is a clock jump rather than dropped frames.
+const JUMP_INTERVALS: i64 = 50;
+const JUMP_MIN_MS: i64 = 10 * 60 * 1000;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IntervalReport {
+    pub frames: usize,
+    /// Frames with no readable capture time (left out of every check).
+    pub frames_without_time: Vec<u32>,
+    /// Median interval between consecutive timestamped frames.
+    pub nominal_ms: Option<i64>,
+    /// Timestamp resolution: 1000 when no frame has sub-second time.
+    pub resolution_ms: i64,
+    /// Interval counts, in bins of `bin_ms`, ordered by interval.
+    pub histogram: Vec<IntervalBin>,
+    pub bin_ms: i64,
+    pub gaps: Vec<IntervalGap>,
+    /// Frames whose timestamp equals the previous frame's. Only reported
+    /// when the timestamp resolution is finer than the interval.
+    pub duplicates: Vec<u32>,
+    pub clock_jumps: Vec<ClockJump>,
+}
+
+impl IntervalReport {
+    /// Whether anything needs attention.
+    pub fn is_clean(&self) -> bool {
+        self.frames_without_time.is_empty()
+            && self.gaps.is_empty()
+            && self.duplicates.is_empty()
+            && self.clock_jumps.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct IntervalBin {
+    pub interval_ms: i64,
+    pub count: usize,
+}
+
+/// A step longer than the nominal interval, ending at `frame`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct IntervalGap {
+    pub frame: u32,
+    pub interval_ms: i64,
+    /// Frames the gap would hold at the nominal interval.
+    pub missing_frames: u32,
+}
+
+/// A backwards or implausibly long step, ending at `frame`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ClockJump {
+    pub frame: u32,
+    pub delta_ms: i64,
+}
+
+pub struct IntervalOptions {
+    /// An interval longer than this many nominal intervals is a gap.
+    pub gap_factor: f32,
+}
+
+impl Default for IntervalOptions {
+    fn default() -> Self {
+        Self { gap_factor: 1.5 }
+    }
+}
+
+/// Read the capture time of every frame and build the report.
+pub fn capture_interval_report(
+    image_files: &[PathBuf],
+    opts: &IntervalOptions,
+    reporter: &ProgressReporter,
+) -> IntervalReport {
+    let total = image_files.len();
+    let done = AtomicUsize::new(0);
+    let times: Vec<Option<i64>> = image_files
+        .par_iter()
+        .enumerate()
+        .map(|(index, path)| {
+            let time = read_frame_exif(path).datetime_ms;
+            let current = done.fetch_add(1, Ordering::Relaxed) + 1;
+            reporter.report(ProgressEvent::Frame {
+                index,
+                done: current,
+                total,
+            });
+            time
+        })
+        .collect();
+    interval_report(&times, opts)
+}
+
+/// The report over per-frame capture times (None where a frame has none).
+pub fn interval_report(times: &[Option<i64>], opts: &IntervalOptions) -> IntervalReport {
+    let frames_without_time: Vec<u32> = (0..times.len())
+        .filter(|&i| times[i].is_none())
+        .map(|i| i as u32)
+        .collect();
+    let resolution_ms = if times.iter().flatten().all(|t| t % 1000 == 0) {
+        1000
+    } else {
+        1
+    };
+
+    // Steps between consecutive timestamped frames, keyed by the later one,
+    // with the number of frame intervals they span (more than one across
+    // frames without a time).
+    let known: Vec<(usize, i64)> = times
+        .iter()
+        .enumerate()
+        .filter_map(|(i, t)| Some((i, (*t)?)))
+        .collect();
+    let steps: Vec<(u32, i64, i64)> = known
+        .windows(2)
+        .map(|pair| {
+            let span = (pair[1].0 - pair[0].0) as i64;
+            (pair[1].0 as u32, pair[1].1 - pair[0].1, span)
+        })
+        .collect();
+
+    let mut positive: Vec<i64> = steps
+        .iter()
+        .filter(|s| s.2 == 1 && s.1 > 0)
+        .map(|s| s.1)
+        .collect();
+    positive.sort_unstable();
+    let nominal_ms = positive.get(positive.len() / 2).copied();
+
+    let mut report = IntervalReport {
+        frames: times.len(),
+        frames_without_time,
+        nominal_ms,
+        resolution_ms,
+        histogram: Vec::new(),
+        bin_ms: resolution_ms,
+        gaps: Vec::new(),
+        duplicates: Vec::new(),
+        clock_jumps: Vec::new(),
+    };
+    let Some(nominal) = nominal_ms else {
+        return report;
+    };
+    // A tenth of the nominal interval, at least the timestamp resolution.
+    report.bin_ms = if resolution_ms >= 1000 {
+        1000
+    } else {
+        ((nominal / 10 + 5) / 10 * 10).max(10)
+    };
+
+    let jump_ms = (nominal * JUMP_INTERVALS).max(JUMP_MIN_MS);
+    // Whole-second stamps put a one-second jitter on every interval.
+    let gap_ms = (nominal as f32 * opts.gap_factor) as i64 + resolution_ms.min(nominal);
+    let mut bins: Vec<IntervalBin> = Vec::new();
+    for &(frame, delta, span) in &steps {
+        if delta < 0 || delta >= jump_ms {
+            report.clock_jumps.push(ClockJump {
+                frame,
+                delta_ms: delta,
+            });
+            continue;
+        }
+        if delta == 0 && resolution_ms < nominal {
+            report.duplicates.push(frame);
+        }
+        if delta > gap_ms + nominal * (span - 1) {
+            report.gaps.push(IntervalGap {
+                frame,
+                interval_ms: delta,
+                missing_frames: ((delta as f64 / nominal as f64).round() as u32)
+                    .saturating_sub(span as u32),
+            });
+        }
+        if span > 1 {
+            continue;
+        }
+        let interval_ms = (delta + report.bin_ms / 2) / report.bin_ms * report.bin_ms;
+        match bins.iter_mut().find(|b| b.interval_ms == interval_ms) {
+            Some(bin) => bin.count += 1,
+            None => bins.push(IntervalBin {
+                interval_ms,
+                count: 1,
+            }),
+        }
+    }
+    bins.sort_by_key(|b| b.interval_ms);
+    report.histogram = bins;
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn finds_gaps_duplicates_and_jumps() {
+        // 2 s interval with sub-second stamps.
+        let mut times: Vec<Option<i64>> = (0..20).map(|i| Some(1_500 + i * 2_000)).collect();
+        // Frames 6 and 7 were dropped: frame 8 arrives 6 s after frame 5.
+        times.drain(6..8);
+        // A duplicate stamp and a frame without EXIF.
+        times.insert(10, times[9]);
+        times[12] = None;
+        // The clock set back an hour.
+        for t in times.iter_mut().skip(15).flatten() {
+            *t -= 3_600_000;
+        }
+
+        let report = interval_report(&times, &IntervalOptions::default());
+        assert_eq!(report.nominal_ms, Some(2_000));
+        assert_eq!(report.resolution_ms, 1);
+        assert_eq!(
+            report.gaps,
+            vec![IntervalGap {
+                frame: 6,
+                interval_ms: 6_000,
+                missing_frames: 2
+            }]
+        );
+        assert_eq!(report.duplicates, vec![10]);
+        assert_eq!(report.frames_without_time, vec![12]);
+        assert_eq!(report.clock_jumps.len(), 1);
+        assert_eq!(report.clock_jumps[0].frame, 15);
+        assert!(report.clock_jumps[0].delta_ms < 0);
+        let two_seconds = report
+            .histogram
+            .iter()
+            .find(|b| b.interval_ms == 2_000)
+            .unwrap();
+        assert!(two_seconds.count >= 10);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn whole_second_stamps_tolerate_rounding() {
+        // A 1.5 s interval stamped to the second: steps of 1 and 2 s, and
+        // equal stamps are expected rather than duplicates.
+        let times: Vec<Option<i64>> = (0..30).map(|i| Some(i * 1_500 / 1_000 * 1_000)).collect();
+        let report = interval_report(&times, &IntervalOptions::default());
+        assert_eq!(report.resolution_ms, 1_000);
+        assert!(report.gaps.is_empty(), "{:?}", report.gaps);
+        assert!(report.duplicates.is_empty());
+        assert!(report.is_clean());
+    }
+
+    #[test]
+    fn no_timestamps_yield_an_empty_report() {
+        let report = interval_report(&[None, None], &IntervalOptions::default());
+        assert_eq!(report.nominal_ms, None);
+        assert!(report.histogram.is_empty());
+        assert_eq!(report.frames_without_time, vec![0, 1]);
+    }
+}
@@ -1,16 +1,56 @@
-//! Keyframe placement suggestions from the luminance progression: denser
-//! where brightness moves fast, sparse where nothing happens.
+//! Keyframe placement suggestions from the luminance and chromaticity
+//! progression: denser where brightness or color moves fast, sparse where
+//! nothing happens.
 
 use crate::analysis::deflicker::gaussian_smooth;
 use crate::analysis::holygrail::HolyGrailLayer;
+use crate::analysis::whitebalance::{WhiteBalanceLayer, TEMPERATURE_STOPS, TINT_STOPS};
+use crate::curve::{Curve, Keyframe};
 use crate::error::{LapsifyError, Result};
+use crate::project::ColorGrade;
 
 const LUMA_FLOOR: f32 = 1e-6;
 
+/// Temperature/tint units that count as one step of travel, so the same
+/// density works for color as for luminance: a sunset drifts a few tens of
+/// units where brightness travels a few EV.
+const COLOR_UNITS_PER_STEP: f32 = 10.0;
+
+/// The color curve a suggestion is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SuggestParam {
+    Exposure,
+    Temperature,
+    Tint,
+}
+
+impl SuggestParam {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input {
+            "exposure" => Ok(Self::Exposure),
+            "temperature" => Ok(Self::Temperature),
+            "tint" => Ok(Self::Tint),
+            other => Err(LapsifyError::message(format!(
+                "Invalid param '{other}' (expected 'exposure', 'temperature' or 'tint')"
+            ))),
+        }
+    }
+
+    /// The `ColorGrade` curve suggestions for this parameter go into.
+    pub fn curve_mut<'a>(&self, color: &'a mut ColorGrade) -> &'a mut Curve {
+        match self {
+            Self::Exposure => &mut color.exposure,
+            Self::Temperature => &mut color.temperature,
+            Self::Tint => &mut color.tint,
+        }
+    }
+}
+
 pub struct SuggestOptions {
     /// Exact number of keyframes. None = derive from density.
     pub count: Option<usize>,
-    /// Keyframes per EV of total luminance travel (used when count is None).
+    /// Keyframes per EV of total luminance travel, or per 10 units of
+    /// temperature/tint drift (used when count is None).
     pub density: f32,
 }
 
@@ -56,7 +96,90 @@ pub fn suggest_keyframes(
         })
         .collect();
 
-    let smoothed = gaussian_smooth(&signal, (n as f32 * 0.02).max(1.0));
+    Ok(place_by_travel(&signal, opts))
+}
+
+/// Suggest temperature or tint keyframe positions from the chromaticity
+/// progression (`LumaSeries::chromaticity`).
+///
+/// The signal is the color drift projected onto the temperature or tint
+/// axis of `ColorParams`, in slider units. As with the exposure staircase,
+/// the white-balance compensation layer (when present) cancels the camera's
+/// own auto white balance jumps first.
+pub fn suggest_color_keyframes(
+    chromaticity: &[[f32; 2]],
+    white_balance: Option<&WhiteBalanceLayer>,
+    param: SuggestParam,
+    opts: &SuggestOptions,
+) -> Result<Vec<u32>> {
+    if chromaticity.len() < 2 {
+        return Err(LapsifyError::message(
+            "Keyframe suggestion needs at least 2 frames of chromaticity data",
+        ));
+    }
+    let temperature = match param {
+        SuggestParam::Temperature => true,
+        SuggestParam::Tint => false,
+        SuggestParam::Exposure => {
+            return Err(LapsifyError::message(
+                "Exposure keyframes are suggested from luminance, not chromaticity",
+            ))
+        }
+    };
+
+    let signal: Vec<f32> = chromaticity
+        .iter()
+        .enumerate()
+        .map(|(i, [rg, bg])| {
+            let rg = rg.max(LUMA_FLOOR).log2();
+            let bg = bg.max(LUMA_FLOOR).log2();
+            // Temperature moves R/G and B/G apart, tint moves both together
+            // (see `ColorParams::wb_gain`).
+            let units = if temperature {
+                100.0 * (rg - bg) / (2.0 * TEMPERATURE_STOPS)
+                    + white_balance.map_or(0.0, |wb| wb.temperature_at(i))
+            } else {
+                100.0 * (rg + bg) / (2.0 * TINT_STOPS)
+                    + white_balance.map_or(0.0, |wb| wb.tint_at(i))
+            };
+            units / COLOR_UNITS_PER_STEP
+        })
+        .collect();
+
+    Ok(place_by_travel(&signal, opts))
+}
+
+/// Insert keyframes at the curve's current sampled values: the render is
+/// unchanged, but an editor now has handles at the right places. Existing
+/// keyframes within 2 frames of a suggestion are kept as-is. Expression
+/// curves have nowhere to put keyframes and are an error.
+pub fn insert_handles(curve: &mut Curve, frames: &[u32]) -> Result<()> {
+    let mut keyframes: Vec<Keyframe> = match curve {
+        Curve::Keyframed(kfs) => kfs.clone(),
+        Curve::Constant(_) => Vec::new(),
+        Curve::Expression(expression) => {
+            return Err(LapsifyError::message(format!(
+                "The curve is the expression '{}'; replace it or bake it to keyframes first",
+                expression.source()
+            )))
+        }
+    };
+    let existing: Vec<u32> = keyframes.iter().map(|k| k.frame).collect();
+    for &frame in frames {
+        if existing.iter().any(|&e| e.abs_diff(frame) <= 2) {
+            continue;
+        }
+        keyframes.push(Keyframe::new(frame, curve.sample(frame)));
+    }
+    keyframes.sort_by_key(|k| k.frame);
+    *curve = Curve::Keyframed(keyframes);
+    Ok(())
+}
+
+/// Keyframes at equal steps of cumulative travel of the smoothed signal.
+fn place_by_travel(signal: &[f32], opts: &SuggestOptions) -> Vec<u32> {
+    let n = signal.len();
+    let smoothed = gaussian_smooth(signal, (n as f32 * 0.02).max(1.0));
 
     // Cumulative absolute travel of the smoothed signal.
     let mut travel = Vec::with_capacity(n);
@@ -91,7 +214,7 @@ pub fn suggest_keyframes(
     }
     frames.push((n - 1) as u32);
 
-    Ok(frames)
+    frames
 }
 
 #[cfg(test)]
@@ -161,6 +284,9 @@ mod tests {
             rotate: 0.0,
             stretch: 1.0,
             frames_missing_exif: vec![],
+            ev_events: vec![],
+            exposure_bias: false,
+            fit_residuals: vec![],
             computed_at_unix: 0,
             source_fingerprint: String::new(),
         };
@@ -186,6 +312,79 @@ mod tests {
         );
     }
 
+    #[test]
+    fn warming_drift_places_temperature_keyframes() {
+        // Neutral, then warming by 0.2 stops (50 temperature units) between
+        // frames 60 and 80, then stable.
+        let chroma: Vec<[f32; 2]> = (0..100)
+            .map(|i| {
+                let stops = ((i as f32 - 60.0) / 100.0).clamp(0.0, 0.2);
+                [2.0_f32.powf(stops), 2.0_f32.powf(-stops)]
+            })
+            .collect();
+        let opts = SuggestOptions::default();
+
+        let temperature =
+            suggest_color_keyframes(&chroma, None, SuggestParam::Temperature, &opts).unwrap();
+        let interior = &temperature[1..temperature.len() - 1];
+        assert!(!interior.is_empty());
+        assert!(
+            interior.iter().all(|&f| (55..=85).contains(&f)),
+            "keyframes should cluster in the drift: {interior:?}"
+        );
+
+        // Opposite R/G and B/G moves are pure temperature: no tint travel.
+        let tint = suggest_color_keyframes(&chroma, None, SuggestParam::Tint, &opts).unwrap();
+        assert_eq!(tint, vec![0, 99]);
+
+        // Exposure has no chromaticity signal.
+        assert!(suggest_color_keyframes(&chroma, None, SuggestParam::Exposure, &opts).is_err());
+    }
+
+    #[test]
+    fn white_balance_jump_is_not_a_color_change() {
+        // The camera's auto white balance warmed frames 25.. by a stop of
+        // R/G and B/G each (250 units); the compensation cools them back.
+        let mut chroma = vec![[1.0f32, 1.0]; 50];
+        let mut compensation = vec![0.0f32; 50];
+        for i in 25..50 {
+            chroma[i] = [2.0, 0.5];
+            compensation[i] = -250.0;
+        }
+        let wb = WhiteBalanceLayer {
+            temperature: compensation,
+            tint: vec![],
+            strength: crate::curve::Curve::Constant(1.0),
+            smoothing_frames: 0,
+            frames_missing_wb: vec![],
+            computed_at_unix: 0,
+            source_fingerprint: String::new(),
+        };
+        let opts = SuggestOptions {
+            count: Some(4),
+            density: 1.5,
+        };
+        let frames =
+            suggest_color_keyframes(&chroma, Some(&wb), SuggestParam::Temperature, &opts).unwrap();
+        assert_eq!(frames, vec![0, 49]);
+    }
+
+    #[test]
+    fn handles_keep_the_curve_and_existing_keyframes() {
+        let mut curve = Curve::Keyframed(vec![Keyframe::new(0, 0.0), Keyframe::new(100, 50.0)]);
+        insert_handles(&mut curve, &[0, 50, 99]).unwrap();
+        let Curve::Keyframed(kfs) = &curve else {
+            panic!("expected keyframes")
+        };
+        let frames: Vec<u32> = kfs.iter().map(|k| k.frame).collect();
+        assert_eq!(frames, vec![0, 50, 100]);
+        assert!((kfs[1].value - 25.0).abs() < 5.0, "{}", kfs[1].value);
+
+        let mut constant = Curve::Constant(12.0);
+        insert_handles(&mut constant, &[0, 40]).unwrap();
+        assert_eq!(constant.sample(40), 12.0);
+    }
+
     #[test]
     fn too_few_frames_error() {
         assert!(suggest_keyframes(&[0.5], None, &SuggestOptions::default()).is_err());
@@ -5,7 +5,7 @@ use image::RgbImage;
 use rayon::prelude::*;
 use xxhash_rust::xxh64::xxh64;
 
-use crate::analysis::{now_unix, source_fingerprint, LumaSeries};
+use crate::analysis::{bridge_excluded, now_unix, source_fingerprint, LumaSeries};
 use crate::color::{transfer, ColorParams, FrameColorOps, LUMA_B, LUMA_G, LUMA_R};
 use crate::crop::CropRect;
 use crate::error::{LapsifyError, Result};
@@ -48,12 +48,23 @@ pub fn measure_luminance(
     let cache_usable = std::fs::create_dir_all(&cache_dir).is_ok();
 
     let total = image_files.len();
+    let excluded: Vec<u32> = project
+        .excluded_frames
+        .iter()
+        .copied()
+        .filter(|&f| (f as usize) < total)
+        .collect();
+    let measured_total = total - excluded.len();
     let done = AtomicUsize::new(0);
 
-    let values: Vec<f32> = image_files
+    let measured: Vec<(f32, [f32; 2])> = image_files
         .par_iter()
         .enumerate()
-        .map(|(frame, path)| -> Result<f32> {
+        .map(|(frame, path)| -> Result<(f32, [f32; 2])> {
+            if project.is_excluded(frame as u32) {
+                // Bridged below.
+                return Ok((0.0, [1.0, 1.0]));
+            }
             let mut thumb = load_thumbnail(
                 path,
                 opts.measure_dim,
@@ -61,26 +72,34 @@ pub fn measure_luminance(
             )?;
 
             if opts.developed {
-                let params = ColorParams::at_frame(project, frame as u32);
+                let params = ColorParams::at_frame(project, frame as u32, total);
                 FrameColorOps::from_params(&params).apply(&mut thumb);
             }
 
-            let value = mean_linear_luma(&thumb, opts.region);
+            let rgb = mean_linear_rgb(&thumb, opts.region);
+            let value = LUMA_R * rgb[0] + LUMA_G * rgb[1] + LUMA_B * rgb[2];
 
             let current = done.fetch_add(1, Ordering::Relaxed) + 1;
             reporter.report(ProgressEvent::Luma {
                 frame,
                 value,
                 done: current,
-                total,
+                total: measured_total,
             });
 
-            Ok(value)
+            Ok((value, chromaticity(rgb)))
         })
-        .collect::<Result<Vec<f32>>>()?;
+        .collect::<Result<Vec<_>>>()?;
+    let (mut values, mut chromaticity): (Vec<f32>, Vec<[f32; 2]>) = measured.into_iter().unzip();
+    bridge_excluded(&mut values, &excluded, |a, b, t| a + (b - a) * t);
+    bridge_excluded(&mut chromaticity, &excluded, |a, b, t| {
+        [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t]
+    });
 
     Ok(LumaSeries {
         values,
+        chromaticity,
+        excluded_frames: excluded,
         region: opts.region,
         measure_dim: opts.measure_dim,
         computed_at_unix: now_unix(),
@@ -89,7 +108,11 @@ pub fn measure_luminance(
 }
 
 /// Load a downscaled frame, using the on-disk thumbnail cache when possible.
-fn load_thumbnail(path: &Path, measure_dim: u32, cache_dir: Option<&Path>) -> Result<RgbImage> {
+pub(crate) fn load_thumbnail(
+    path: &Path,
+    measure_dim: u32,
+    cache_dir: Option<&Path>,
+) -> Result<RgbImage> {
     let cache_path = cache_dir.map(|dir| {
         let meta = std::fs::metadata(path);
         let (len, mtime) = meta
@@ -131,8 +154,18 @@ fn load_thumbnail(path: &Path, measure_dim: u32, cache_dir: Option<&Path>) -> Re
     Ok(thumb)
 }
 
-/// Mean linear Rec.709 luminance over a normalized region of the image.
-fn mean_linear_luma(img: &RgbImage, region: Option<CropRect>) -> f32 {
+/// (R/G, B/G) of a linear mean color. A black frame has no color, so it
+/// reads as neutral instead of dividing by zero.
+fn chromaticity(rgb: [f32; 3]) -> [f32; 2] {
+    if rgb[1] <= 0.0 {
+        [1.0, 1.0]
+    } else {
+        [rgb[0] / rgb[1], rgb[2] / rgb[1]]
+    }
+}
+
+/// Mean linear RGB over a normalized region of the image.
+pub(crate) fn mean_linear_rgb(img: &RgbImage, region: Option<CropRect>) -> [f32; 3] {
     let (w, h) = img.dimensions();
     let (x0, y0, x1, y1) = match region {
         Some(r) => (
@@ -145,26 +178,32 @@ fn mean_linear_luma(img: &RgbImage, region: Option<CropRect>) -> f32 {
     };
 
     let decode = transfer::srgb_decode_table();
-    let mut sum = 0.0f64;
+    let mut sum = [0.0f64; 3];
     let mut count = 0u64;
     for y in y0..y1 {
         for x in x0..x1 {
-            let [r, g, b] = img.get_pixel(x, y).0;
-            let luma = LUMA_R * decode[r as usize]
-                + LUMA_G * decode[g as usize]
-                + LUMA_B * decode[b as usize];
-            sum += luma as f64;
+            let px = img.get_pixel(x, y).0;
+            for c in 0..3 {
+                sum[c] += decode[px[c] as usize] as f64;
+            }
             count += 1;
         }
     }
 
     if count == 0 {
-        0.0
+        [0.0; 3]
     } else {
-        (sum / count as f64) as f32
+        sum.map(|v| (v / count as f64) as f32)
     }
 }
 
+/// Mean linear Rec.709 luminance over a normalized region of the image.
+#[cfg(test)]
+fn mean_linear_luma(img: &RgbImage, region: Option<CropRect>) -> f32 {
+    let [r, g, b] = mean_linear_rgb(img, region);
+    LUMA_R * r + LUMA_G * g + LUMA_B * b
+}
+
 /// Parse a "X,Y,W,H" normalized region string.
 pub fn parse_region(input: &str) -> Result<CropRect> {
     let parts: Vec<f32> = input
@@ -246,6 +285,14 @@ mod tests {
         assert_relative_eq!(right, 1.0, epsilon = 1e-6);
     }
 
+    #[test]
+    fn chromaticity_of_warm_gray() {
+        let img = RgbImage::from_pixel(4, 4, Rgb([188, 128, 100]));
+        let [rg, bg] = chromaticity(mean_linear_rgb(&img, None));
+        assert!(rg > 1.0 && bg < 1.0, "{rg} {bg}");
+        assert_eq!(chromaticity([0.0; 3]), [1.0, 1.0]);
+    }
+
     #[test]
     fn parse_region_validates() {
         assert!(parse_region("0.1,0.1,0.5,0.5").is_ok());
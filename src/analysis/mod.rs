@@ -8,13 +8,22 @@
 
 pub mod deflicker;
 pub mod holygrail;
+pub mod intervals;
 pub mod keyframes;
 pub mod luminance;
+pub mod outliers;
+pub mod scenes;
+pub mod stabilize;
+pub mod whitebalance;
 
 pub use deflicker::DeflickerLayer;
 pub use holygrail::HolyGrailLayer;
+pub use outliers::OutlierLayer;
+pub use scenes::SceneLayer;
+pub use stabilize::StabilizeLayer;
+pub use whitebalance::WhiteBalanceLayer;
 
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 use xxhash_rust::xxh64::Xxh64;
@@ -35,12 +44,90 @@ pub struct Analysis {
     /// Exposure compensation for in-camera exposure changes, from EXIF.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub holy_grail: Option<HolyGrailLayer>,
+    /// Temperature and tint compensation for as-shot white balance jumps,
+    /// from RAW metadata.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub white_balance: Option<WhiteBalanceLayer>,
     /// Per-frame exposure corrections from visual deflicker.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub deflicker: Option<DeflickerLayer>,
+    /// Frames flagged as bad by `analyze outliers`, with the reason.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outliers: Option<OutlierLayer>,
+    /// Scenes found by `analyze scenes`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub scenes: Option<SceneLayer>,
+    /// Camera shake corrections from `analyze stabilize`, already written
+    /// into the crop track.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stabilize: Option<StabilizeLayer>,
     /// Capture timestamps in unix epoch milliseconds, one per frame.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub capture_times_ms: Option<Vec<i64>>,
+    /// The source frames every frame index in the project refers to, as of
+    /// the last analysis. `lapsify project rebase` uses them to remap the
+    /// project after the folder changes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frames: Option<Vec<FrameKey>>,
+}
+
+impl Analysis {
+    /// Record the frame list the analysis data lines up with. Once a list
+    /// is recorded it only changes through `project rebase`: overwriting it
+    /// here would lose the mapping from the old frames to the current ones.
+    pub fn record_frames(&mut self, image_files: &[PathBuf]) -> Result<()> {
+        let current = frame_keys(image_files)?;
+        match &self.frames {
+            None => self.frames = Some(current),
+            Some(recorded) if *recorded == current => {}
+            Some(_) => {
+                return Err(LapsifyError::message(
+                    "The source folder changed since the frame list was recorded; \
+                     run `lapsify project rebase` first",
+                ))
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Identity of one source frame: its filename, and the size and mtime that
+/// recognize it again after a rename.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct FrameKey {
+    pub name: String,
+    pub size: u64,
+    pub modified_unix: u64,
+}
+
+/// Frame keys of a frame list.
+pub fn frame_keys(image_files: &[PathBuf]) -> Result<Vec<FrameKey>> {
+    image_files
+        .iter()
+        .map(|path| {
+            let (size, modified_unix) = file_stamp(path)?;
+            Ok(FrameKey {
+                name: path
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+                size,
+                modified_unix,
+            })
+        })
+        .collect()
+}
+
+/// Size and mtime (unix seconds, 0 when unavailable) of a file.
+fn file_stamp(path: &Path) -> Result<(u64, u64)> {
+    let meta = std::fs::metadata(path).map_err(|e| LapsifyError::io(path, e))?;
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok((meta.len(), mtime))
 }
 
 /// A per-frame scalar luminance series.
@@ -48,33 +135,58 @@ pub struct Analysis {
 pub struct LumaSeries {
     /// Mean linear Rec.709 luminance per frame, one entry per source frame.
     pub values: Vec<f32>,
+    /// Linear (R/G, B/G) of the mean color per frame. Empty in series
+    /// measured before chromaticity was recorded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub chromaticity: Vec<[f32; 2]>,
     /// Normalized source-image region the measurement was restricted to.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub region: Option<CropRect>,
     /// Long-edge size the frames were downscaled to before measuring.
     pub measure_dim: u32,
+    /// Frames the project excluded at measurement time. They were not
+    /// measured: their values are interpolated from the nearest kept frames.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_frames: Vec<u32>,
     /// Unix timestamp (seconds) of the measurement.
     pub computed_at_unix: u64,
     /// Fingerprint of the source folder at measurement time.
     pub source_fingerprint: String,
 }
 
+/// Replace the values of excluded frames with a linear interpolation between
+/// the nearest kept frames (held at the ends), so what happened in an
+/// excluded frame doesn't leak into smoothing and fits. Leaves the values
+/// alone when every frame is excluded.
+pub fn bridge_excluded<T: Copy>(values: &mut [T], excluded: &[u32], lerp: impl Fn(T, T, f32) -> T) {
+    let is_excluded = |i: usize| excluded.binary_search(&(i as u32)).is_ok();
+    let kept: Vec<usize> = (0..values.len()).filter(|&i| !is_excluded(i)).collect();
+    if kept.is_empty() {
+        return;
+    }
+    for i in (0..values.len()).filter(|&i| is_excluded(i)) {
+        let after = kept.partition_point(|&k| k < i);
+        values[i] = match (after.checked_sub(1).map(|k| kept[k]), kept.get(after)) {
+            (Some(lo), Some(&hi)) => {
+                lerp(values[lo], values[hi], (i - lo) as f32 / (hi - lo) as f32)
+            }
+            (Some(lo), None) => values[lo],
+            (None, Some(&hi)) => values[hi],
+            (None, None) => unreachable!("kept is not empty"),
+        };
+    }
+}
+
 /// Fingerprint of a frame list: filename, size and mtime of every file.
 /// Changes when frames are added, removed, renamed or rewritten.
 pub fn source_fingerprint(image_files: &[PathBuf]) -> Result<String> {
     let mut hasher = Xxh64::new(0);
     for path in image_files {
-        let meta = std::fs::metadata(path).map_err(|e| LapsifyError::io(path, e))?;
+        let (size, mtime) = file_stamp(path)?;
         if let Some(name) = path.file_name() {
             hasher.update(name.to_string_lossy().as_bytes());
         }
-        hasher.update(&meta.len().to_le_bytes());
-        let mtime = meta
-            .modified()
-            .ok()
-            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
-            .map(|d| d.as_secs())
-            .unwrap_or(0);
+        hasher.update(&size.to_le_bytes());
         hasher.update(&mtime.to_le_bytes());
     }
     Ok(format!("{:016x}", hasher.digest()))
@@ -111,4 +223,27 @@ mod tests {
         let fp3 = source_fingerprint(&[b, a]).unwrap();
         assert_ne!(fp2, fp3);
     }
+
+    #[test]
+    fn recorded_frames_are_kept_until_rebase() {
+        let tmp = tempfile::tempdir().unwrap();
+        let a = tmp.path().join("a.jpg");
+        let b = tmp.path().join("b.jpg");
+        fs::write(&a, b"one").unwrap();
+        fs::write(&b, b"two").unwrap();
+
+        let mut analysis = Analysis::default();
+        analysis.record_frames(&[a.clone(), b.clone()]).unwrap();
+        analysis.record_frames(&[a.clone(), b.clone()]).unwrap();
+        assert!(analysis.record_frames(std::slice::from_ref(&a)).is_err());
+        assert_eq!(analysis.frames.as_ref().unwrap().len(), 2);
+    }
+
+    #[test]
+    fn excluded_values_are_bridged() {
+        let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+        let mut values = [0.0, 9.0, 9.0, 3.0, 9.0];
+        bridge_excluded(&mut values, &[1, 2, 4], lerp);
+        assert_eq!(values, [0.0, 1.0, 2.0, 3.0, 3.0]);
+    }
 }
@@ -0,0 +1,442 @@
+//! Bad-frame detection: flag frames that stand out from their neighbors.
+//!
+//! Every frame is reduced to a few statistics from the luminance thumbnail
+//! cache (mean luminance, high-frequency energy, clipped fractions, and a
+//! small grayscale copy for registration), then compared with the median of
+//! its neighbors. Comparing locally keeps slow changes — a sunset, a camera
+//! ramping its exposure, a motion-control pan — from being flagged, while
+//! one-frame events (a flash, a passer-by, a bumped tripod, a focus miss)
+//! stand out.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use image::{imageops, GrayImage, RgbImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::luminance::{load_thumbnail, mean_linear_rgb};
+use crate::analysis::{now_unix, source_fingerprint};
+use crate::color::{LUMA_B, LUMA_G, LUMA_R};
+use crate::error::Result;
+pub use crate::progress::{OutlierFlag, OutlierReason};
+use crate::progress::{ProgressEvent, ProgressReporter};
+use crate::project::Project;
+
+/// Neighbors on each side a frame is compared with.
+const NEIGHBORS: usize = 3;
+/// Long edge of the grayscale copy used for registration.
+const REGISTER_DIM: u32 = 64;
+/// Largest shift searched for, in registration pixels.
+const MAX_SEARCH: i32 = 6;
+/// sRGB levels (0..1) below/above which a pixel counts as black/white.
+const BLACK_LEVEL: f32 = 0.03;
+const WHITE_LEVEL: f32 = 0.97;
+/// Fraction of black/white pixels that makes a frame near-black/white.
+const CLIPPED_FRACTION: f32 = 0.9;
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct OutlierLayer {
+    /// Flagged frames in frame order; a frame can carry several reasons.
+    pub flags: Vec<OutlierFlag>,
+    pub spike_ev: f32,
+    pub blur_ratio: f32,
+    pub max_shift: f32,
+    pub measure_dim: u32,
+    pub computed_at_unix: u64,
+    pub source_fingerprint: String,
+}
+
+impl OutlierLayer {
+    /// The flagged frames, each once.
+    pub fn frames(&self) -> Vec<u32> {
+        let mut frames: Vec<u32> = self.flags.iter().map(|f| f.frame).collect();
+        frames.dedup();
+        frames
+    }
+}
+
+pub struct OutlierOptions {
+    /// Luminance deviation from the neighbors that counts as a spike, in EV.
+    pub spike_ev: f32,
+    /// Detail below this fraction of the neighbors' counts as blur.
+    pub blur_ratio: f32,
+    /// Jump against the previous frame (beyond the neighbors' usual motion)
+    /// that counts as displacement, as a fraction of the image width.
+    pub max_shift: f32,
+    /// Thumbnail size for measurement (shares the luminance cache).
+    pub measure_dim: u32,
+}
+
+impl Default for OutlierOptions {
+    fn default() -> Self {
+        Self {
+            spike_ev: 0.7,
+            blur_ratio: 0.5,
+            max_shift: 0.02,
+            measure_dim: 256,
+        }
+    }
+}
+
+/// Per-frame measurements the detectors compare.
+#[derive(Debug, Clone)]
+pub struct FrameStats {
+    /// Mean linear luminance.
+    pub luma: f32,
+    /// Mean absolute Laplacian of the sRGB gray image, relative to its mean
+    /// level so exposure changes don't read as sharpness changes.
+    pub detail: f32,
+    pub black: f32,
+    pub white: f32,
+    /// Shift against the previous frame, as a fraction of the width.
+    pub shift: f32,
+}
+
+/// Measure every frame and flag the outliers.
+pub fn detect_outliers(
+    project: &Project,
+    image_files: &[PathBuf],
+    opts: &OutlierOptions,
+    reporter: &ProgressReporter,
+) -> Result<OutlierLayer> {
+    let cache_dir = project.input.join(".lapsify").join("thumbs");
+    let cache_usable = std::fs::create_dir_all(&cache_dir).is_ok();
+    let total = image_files.len();
+    let done = AtomicUsize::new(0);
+
+    let measured: Vec<(FrameStats, GrayImage)> = image_files
+        .par_iter()
+        .enumerate()
+        .map(|(index, path)| -> Result<(FrameStats, GrayImage)> {
+            let thumb = load_thumbnail(
+                path,
+                opts.measure_dim,
+                cache_usable.then_some(cache_dir.as_path()),
+            )?;
+            let stats = frame_stats(&thumb);
+            let current = done.fetch_add(1, Ordering::Relaxed) + 1;
+            reporter.report(ProgressEvent::Frame {
+                index,
+                done: current,
+                total,
+            });
+            Ok(stats)
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let (mut stats, grays): (Vec<FrameStats>, Vec<GrayImage>) = measured.into_iter().unzip();
+    let shifts: Vec<f32> = (1..grays.len())
+        .into_par_iter()
+        .map(|i| shift_between(&grays[i - 1], &grays[i]))
+        .collect();
+    for (s, shift) in stats.iter_mut().skip(1).zip(shifts) {
+        s.shift = shift;
+    }
+
+    Ok(OutlierLayer {
+        flags: flag_outliers(&stats, opts),
+        spike_ev: opts.spike_ev,
+        blur_ratio: opts.blur_ratio,
+        max_shift: opts.max_shift,
+        measure_dim: opts.measure_dim,
+        computed_at_unix: now_unix(),
+        source_fingerprint: source_fingerprint(image_files)?,
+    })
+}
+
+/// The pure detector: compare each frame's statistics with the median of
+/// its neighbors. A near-black or near-white frame is flagged for that
+/// alone, since it would trip the other detectors for the same reason.
+pub fn flag_outliers(stats: &[FrameStats], opts: &OutlierOptions) -> Vec<OutlierFlag> {
+    let mut flags = Vec::new();
+    for (i, s) in stats.iter().enumerate() {
+        let frame = i as u32;
+        if is_clipped(s) {
+            let (reason, score) = if s.black >= s.white {
+                (OutlierReason::NearBlack, s.black)
+            } else {
+                (OutlierReason::NearWhite, s.white)
+            };
+            flags.push(OutlierFlag {
+                frame,
+                reason,
+                score,
+            });
+            continue;
+        }
+        let Some(neighbors) = neighbor_median(stats, i, |s| s.luma.max(1e-6).log2()) else {
+            continue;
+        };
+        let spike = s.luma.max(1e-6).log2() - neighbors;
+        if spike.abs() >= opts.spike_ev {
+            flags.push(OutlierFlag {
+                frame,
+                reason: OutlierReason::LuminanceSpike,
+                score: spike,
+            });
+        }
+        if let Some(detail) = neighbor_median(stats, i, |s| s.detail) {
+            if detail > 0.0 && s.detail / detail < opts.blur_ratio {
+                flags.push(OutlierFlag {
+                    frame,
+                    reason: OutlierReason::Blur,
+                    score: s.detail / detail,
+                });
+            }
+        }
+        if is_displaced(stats, i, opts.max_shift) {
+            flags.push(OutlierFlag {
+                frame,
+                reason: OutlierReason::Displacement,
+                score: s.shift,
+            });
+        }
+    }
+    flags
+}
+
+/// Whether frame `i` jumped against both neighbors. After a one-frame bump
+/// the next frame jumps back, but only against the bumped frame, so it is
+/// not flagged. A frame with one usable neighbor is flagged when that
+/// neighbor agrees with its own other neighbor.
+fn is_displaced(stats: &[FrameStats], i: usize, max_shift: f32) -> bool {
+    let usual = neighbor_median(stats, i, |s| s.shift).unwrap_or(0.0);
+    // Shift between frames j - 1 and j; registration against a clipped
+    // frame means nothing.
+    let jumped = |j: usize| {
+        (j > 0 && j < stats.len() && !is_clipped(&stats[j - 1]) && !is_clipped(&stats[j]))
+            .then(|| stats[j].shift - usual >= max_shift)
+    };
+    match (jumped(i), jumped(i + 1)) {
+        (Some(before), Some(after)) => before && after,
+        (Some(before), None) => before && jumped(i - 1) != Some(true),
+        (None, Some(after)) => after && jumped(i + 2) != Some(true),
+        (None, None) => false,
+    }
+}
+
+fn is_clipped(s: &FrameStats) -> bool {
+    s.black >= CLIPPED_FRACTION || s.white >= CLIPPED_FRACTION
+}
+
+/// Median of `value` over up to `NEIGHBORS` frames on each side of `i`,
+/// leaving out near-black/white frames. None without any neighbor.
+fn neighbor_median(
+    stats: &[FrameStats],
+    i: usize,
+    value: impl Fn(&FrameStats) -> f32,
+) -> Option<f32> {
+    let lo = i.saturating_sub(NEIGHBORS);
+    let hi = (i + NEIGHBORS + 1).min(stats.len());
+    let mut values: Vec<f32> = (lo..hi)
+        .filter(|&j| j != i)
+        .map(|j| &stats[j])
+        .filter(|s| !is_clipped(s))
+        .map(value)
+        .collect();
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f32::total_cmp);
+    let mid = values.len() / 2;
+    Some(if values.len().is_multiple_of(2) {
+        (values[mid - 1] + values[mid]) / 2.0
+    } else {
+        values[mid]
+    })
+}
+
+/// Statistics of one thumbnail, plus its registration copy (shift is
+/// filled in once the neighbors are known).
+fn frame_stats(thumb: &RgbImage) -> (FrameStats, GrayImage) {
+    let rgb = mean_linear_rgb(thumb, None);
+    let luma = LUMA_R * rgb[0] + LUMA_G * rgb[1] + LUMA_B * rgb[2];
+
+    let gray = imageops::grayscale(thumb);
+    let (w, h) = gray.dimensions();
+    let level = |x: u32, y: u32| gray.get_pixel(x, y).0[0] as f32 / 255.0;
+
+    let pixels = (w * h).max(1) as f32;
+    let black = gray
+        .pixels()
+        .filter(|p| (p.0[0] as f32 / 255.0) < BLACK_LEVEL)
+        .count() as f32
+        / pixels;
+    let white = gray
+        .pixels()
+        .filter(|p| (p.0[0] as f32 / 255.0) > WHITE_LEVEL)
+        .count() as f32
+        / pixels;
+
+    let mut laplacian = 0.0f32;
+    let mut mean = 0.0f32;
+    for y in 1..h.saturating_sub(1) {
+        for x in 1..w.saturating_sub(1) {
+            let c = level(x, y);
+            laplacian +=
+                (4.0 * c - level(x - 1, y) - level(x + 1, y) - level(x, y - 1) - level(x, y + 1))
+                    .abs();
+            mean += c;
+        }
+    }
+    let detail = if mean > 0.0 { laplacian / mean } else { 0.0 };
+
+    let small = if w.max(h) > REGISTER_DIM {
+        let scale = REGISTER_DIM as f32 / w.max(h) as f32;
+        imageops::resize(
+            &gray,
+            ((w as f32 * scale).round() as u32).max(1),
+            ((h as f32 * scale).round() as u32).max(1),
+            imageops::FilterType::Triangle,
+        )
+    } else {
+        gray
+    };
+
+    (
+        FrameStats {
+            luma,
+            detail,
+            black,
+            white,
+            shift: 0.0,
+        },
+        small,
+    )
+}
+
+/// Global translation between two registration images, as a fraction of
+/// the width: the shift within `MAX_SEARCH` that minimizes the mean
+/// absolute difference of the overlap, each image normalized by its mean
+/// level so an exposure change doesn't pull the match.
+fn shift_between(a: &GrayImage, b: &GrayImage) -> f32 {
+    if a.dimensions() != b.dimensions() {
+        return 0.0;
+    }
+    let (w, h) = (a.width() as i32, a.height() as i32);
+    let normalized = |img: &GrayImage| {
+        let mean = img.pixels().map(|p| p.0[0] as f32).sum::<f32>() / (w * h) as f32;
+        let scale = if mean > 0.0 { 1.0 / mean } else { 0.0 };
+        img.pixels()
+            .map(|p| p.0[0] as f32 * scale)
+            .collect::<Vec<f32>>()
+    };
+    let (a, b) = (normalized(a), normalized(b));
+    let margin = MAX_SEARCH.min(w / 4).min(h / 4);
+
+    let mut best = (f32::MAX, 0, 0);
+    for dy in -margin..=margin {
+        for dx in -margin..=margin {
+            let mut sum = 0.0f32;
+            for y in margin..h - margin {
+                let row_a = (y * w) as usize;
+                let row_b = ((y + dy) * w) as usize;
+                for x in margin..w - margin {
+                    sum += (a[row_a + x as usize] - b[row_b + (x + dx) as usize]).abs();
+                }
+            }
+            if sum < best.0 {
+                best = (sum, dx, dy);
+            }
+        }
+    }
+    ((best.1 * best.1 + best.2 * best.2) as f32).sqrt() / w as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgb;
+
+    fn calm(n: usize) -> Vec<FrameStats> {
+        (0..n)
+            .map(|i| FrameStats {
+                // A slow brightening that must not be flagged.
+                luma: 0.1 * 1.05f32.powi(i as i32),
+                detail: 0.3,
+                black: 0.0,
+                white: 0.0,
+                shift: 0.002,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn flags_each_kind_of_bad_frame() {
+        let mut stats = calm(20);
+        stats[3].luma *= 4.0; // flash
+        stats[8].detail = 0.05; // focus miss
+        stats[12].shift = 0.08; // bumped tripod...
+        stats[13].shift = 0.08; // ...and back
+        stats[16].black = 0.99; // lens cap
+        stats[17].shift = 0.3; // registered against the black frame
+
+        let flags = flag_outliers(&stats, &OutlierOptions::default());
+        let found: Vec<(u32, OutlierReason)> = flags.iter().map(|f| (f.frame, f.reason)).collect();
+        assert_eq!(
+            found,
+            vec![
+                (3, OutlierReason::LuminanceSpike),
+                (8, OutlierReason::Blur),
+                (12, OutlierReason::Displacement),
+                (16, OutlierReason::NearBlack),
+            ]
+        );
+        assert!((flags[0].score - 2.0).abs() < 0.1, "{}", flags[0].score);
+    }
+
+    #[test]
+    fn only_the_bumped_frame_is_displaced() {
+        let opts = OutlierOptions::default();
+        let displaced = |stats: &[FrameStats]| -> Vec<u32> {
+            flag_outliers(stats, &opts)
+                .iter()
+                .filter(|f| f.reason == OutlierReason::Displacement)
+                .map(|f| f.frame)
+                .collect()
+        };
+
+        // Bumped frames at both ends of the clip: their only neighbor
+        // jumps against them and nothing else.
+        let mut stats = calm(10);
+        stats[1].shift = 0.08;
+        stats[9].shift = 0.08;
+        assert_eq!(displaced(&stats), vec![0, 9]);
+
+        // Frame 8 bumped: the last frame jumps back against it and stays
+        // unflagged.
+        let mut stats = calm(10);
+        stats[8].shift = 0.08;
+        stats[9].shift = 0.08;
+        assert_eq!(displaced(&stats), vec![8]);
+
+        // A camera that moved for good is a cut, not a bad frame.
+        let mut stats = calm(10);
+        stats[5].shift = 0.08;
+        assert!(displaced(&stats).is_empty());
+    }
+
+    #[test]
+    fn registration_finds_a_translation() {
+        let pattern = |dx: i32| {
+            GrayImage::from_fn(48, 32, |x, y| {
+                let x = x as i32 - dx;
+                let hash = (x.wrapping_mul(73_856_093) ^ (y as i32).wrapping_mul(19_349_663))
+                    .rem_euclid(200);
+                image::Luma([(hash + 30) as u8])
+            })
+        };
+        let shift = shift_between(&pattern(0), &pattern(3));
+        assert!((shift - 3.0 / 48.0).abs() < 1e-4, "{shift}");
+        assert_eq!(shift_between(&pattern(0), &pattern(0)), 0.0);
+    }
+
+    #[test]
+    fn clipped_frames_are_measured() {
+        let black = RgbImage::from_pixel(16, 16, Rgb([2, 2, 2]));
+        let (stats, small) = frame_stats(&black);
+        assert!(stats.black > 0.99);
+        assert_eq!(small.dimensions(), (16, 16));
+    }
+}
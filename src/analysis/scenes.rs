@@ -0,0 +1,312 @@
+//! Scene-cut detection: find where one shoot ends and the next begins.
+//!
+//! A card often holds several shoots, and `list_images` sees them as one
+//! sequence. Two signals mark a boundary: a capture-time gap far longer than
+//! the shooting interval, and a frame that looks nothing like the one
+//! before it. The result is a list of contiguous scenes covering the whole
+//! folder; `--scene N` renders one of them and `project split` moves each
+//! into its own folder and project.
+
+use std::ops::Range;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use image::{imageops, GrayImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::luminance::load_thumbnail;
+use crate::analysis::{now_unix, source_fingerprint};
+use crate::error::Result;
+use crate::exif::read_frame_exif;
+use crate::progress::{ProgressEvent, ProgressReporter};
+use crate::project::Project;
+
+/// Long edge of the grayscale copy frames are compared on.
+const COMPARE_DIM: u32 = 32;
+/// A gap must also be this many median intervals long to count.
+const GAP_INTERVALS: f32 = 10.0;
+/// A dissimilar frame must also differ this many times more than its
+/// neighbors usually do.
+const DISSIMILAR_FACTOR: f32 = 3.0;
+/// Frame-to-frame differences on each side a cut is compared with.
+const NEIGHBORS: usize = 5;
+
+/// What starts a scene.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum SceneCut {
+    /// The capture clock jumped (forward or back) by this many seconds.
+    TimeGap { seconds: f32 },
+    /// The frame looks unlike the previous one (mean difference of the
+    /// exposure-normalized frames; 0 = identical).
+    Dissimilar { score: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct Scene {
+    /// First and last source frame (inclusive).
+    pub start: u32,
+    pub end: u32,
+    /// Why the scene starts here; None for the first scene.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cut: Option<SceneCut>,
+}
+
+impl Scene {
+    pub fn frames(&self) -> Range<usize> {
+        self.start as usize..self.end as usize + 1
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct SceneLayer {
+    /// Contiguous scenes covering every frame, in order.
+    pub scenes: Vec<Scene>,
+    pub min_gap_s: f32,
+    pub cut_threshold: f32,
+    pub measure_dim: u32,
+    pub computed_at_unix: u64,
+    pub source_fingerprint: String,
+}
+
+impl SceneLayer {
+    /// Frame ranges of the scenes, or the whole clip as one range when the
+    /// layer doesn't cover exactly `frames` frames (stale analysis).
+    pub fn segments(&self, frames: usize) -> Vec<Range<usize>> {
+        match self.scenes.last() {
+            Some(last) if last.end as usize + 1 == frames => {
+                self.scenes.iter().map(Scene::frames).collect()
+            }
+            _ => std::iter::once(0..frames).collect(),
+        }
+    }
+}
+
+pub struct SceneOptions {
+    /// Shortest capture-time gap that separates shoots, in seconds.
+    pub min_gap_s: f32,
+    /// Smallest frame-to-frame difference that counts as a cut.
+    pub cut_threshold: f32,
+    /// Thumbnail size for measurement (shares the luminance cache).
+    pub measure_dim: u32,
+}
+
+impl Default for SceneOptions {
+    fn default() -> Self {
+        Self {
+            min_gap_s: 300.0,
+            cut_threshold: 0.35,
+            measure_dim: 256,
+        }
+    }
+}
+
+/// Read capture times and thumbnails of every frame and split the sequence
+/// into scenes.
+pub fn detect_scenes(
+    project: &Project,
+    image_files: &[PathBuf],
+    opts: &SceneOptions,
+    reporter: &ProgressReporter,
+) -> Result<SceneLayer> {
+    let cache_dir = project.input.join(".lapsify").join("thumbs");
+    let cache_usable = std::fs::create_dir_all(&cache_dir).is_ok();
+    let total = image_files.len();
+    let done = AtomicUsize::new(0);
+
+    let measured: Vec<(Option<i64>, Vec<f32>)> = image_files
+        .par_iter()
+        .enumerate()
+        .map(|(index, path)| -> Result<(Option<i64>, Vec<f32>)> {
+            let time = read_frame_exif(path).datetime_ms;
+            let thumb = load_thumbnail(
+                path,
+                opts.measure_dim,
+                cache_usable.then_some(cache_dir.as_path()),
+            )?;
+            let gray = normalized_gray(&imageops::grayscale(&thumb));
+            let current = done.fetch_add(1, Ordering::Relaxed) + 1;
+            reporter.report(ProgressEvent::Frame {
+                index,
+                done: current,
+                total,
+            });
+            Ok((time, gray))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let (times, grays): (Vec<Option<i64>>, Vec<Vec<f32>>) = measured.into_iter().unzip();
+    let differences: Vec<f32> = grays
+        .windows(2)
+        .map(|pair| difference(&pair[0], &pair[1]))
+        .collect();
+
+    Ok(SceneLayer {
+        scenes: scenes_from_cuts(total, &find_cuts(&times, &differences, opts)),
+        min_gap_s: opts.min_gap_s,
+        cut_threshold: opts.cut_threshold,
+        measure_dim: opts.measure_dim,
+        computed_at_unix: now_unix(),
+        source_fingerprint: source_fingerprint(image_files)?,
+    })
+}
+
+/// Frames that start a new scene. `differences[i]` compares frame i and
+/// i + 1. A time gap must exceed both `min_gap_s` and `GAP_INTERVALS`
+/// median intervals; a visual cut must exceed `cut_threshold` and stand out
+/// from the neighboring differences, so a fast pan isn't split.
+pub fn find_cuts(
+    times: &[Option<i64>],
+    differences: &[f32],
+    opts: &SceneOptions,
+) -> Vec<(u32, SceneCut)> {
+    let gaps: Vec<Option<f32>> = times
+        .windows(2)
+        .map(|pair| Some((pair[1]? - pair[0]?) as f32 / 1000.0))
+        .collect();
+    let min_gap = median(gaps.iter().flatten().map(|g| g.abs()).collect())
+        .map_or(opts.min_gap_s, |interval| {
+            opts.min_gap_s.max(interval * GAP_INTERVALS)
+        });
+
+    let mut cuts = Vec::new();
+    for (i, gap) in gaps.iter().enumerate() {
+        let frame = i as u32 + 1;
+        if let Some(gap) = gap.filter(|g| g.abs() >= min_gap) {
+            cuts.push((frame, SceneCut::TimeGap { seconds: gap }));
+            continue;
+        }
+        let Some(&score) = differences.get(i) else {
+            continue;
+        };
+        let lo = i.saturating_sub(NEIGHBORS);
+        let hi = (i + NEIGHBORS + 1).min(differences.len());
+        let usual = median(
+            (lo..hi)
+                .filter(|&j| j != i)
+                .map(|j| differences[j])
+                .collect(),
+        )
+        .unwrap_or(0.0);
+        if score >= opts.cut_threshold && score >= usual * DISSIMILAR_FACTOR {
+            cuts.push((frame, SceneCut::Dissimilar { score }));
+        }
+    }
+    cuts
+}
+
+/// Contiguous scenes over `frames` frames, starting at each cut.
+pub fn scenes_from_cuts(frames: usize, cuts: &[(u32, SceneCut)]) -> Vec<Scene> {
+    if frames == 0 {
+        return Vec::new();
+    }
+    let mut scenes = vec![Scene {
+        start: 0,
+        end: frames as u32 - 1,
+        cut: None,
+    }];
+    for &(frame, cut) in cuts {
+        let last = scenes.last_mut().expect("starts non-empty");
+        if frame <= last.start || frame as usize >= frames {
+            continue;
+        }
+        last.end = frame - 1;
+        scenes.push(Scene {
+            start: frame,
+            end: frames as u32 - 1,
+            cut: Some(cut),
+        });
+    }
+    scenes
+}
+
+fn median(mut values: Vec<f32>) -> Option<f32> {
+    if values.is_empty() {
+        return None;
+    }
+    values.sort_by(f32::total_cmp);
+    Some(values[values.len() / 2])
+}
+
+/// A small copy of the frame divided by its mean level, so exposure ramps
+/// don't read as scene changes.
+fn normalized_gray(gray: &GrayImage) -> Vec<f32> {
+    let (w, h) = gray.dimensions();
+    let scale = COMPARE_DIM as f32 / w.max(h).max(1) as f32;
+    let small = imageops::resize(
+        gray,
+        ((w as f32 * scale).round() as u32).clamp(1, COMPARE_DIM),
+        ((h as f32 * scale).round() as u32).clamp(1, COMPARE_DIM),
+        imageops::FilterType::Triangle,
+    );
+    let values: Vec<f32> = small.pixels().map(|p| p.0[0] as f32).collect();
+    let mean = values.iter().sum::<f32>() / values.len() as f32;
+    let scale = if mean > 0.0 { 1.0 / mean } else { 0.0 };
+    values.into_iter().map(|v| v * scale).collect()
+}
+
+/// Mean absolute difference of two normalized images: a few hundredths
+/// between frames of one shot, around half or more between unrelated ones.
+fn difference(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 1.0;
+    }
+    a.iter().zip(b).map(|(x, y)| (x - y).abs()).sum::<f32>() / a.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_gaps_and_visual_cuts_split_scenes() {
+        // Two shoots 10 s apart per frame, an hour apart from each other,
+        // and a hard visual cut inside the second one.
+        let times: Vec<Option<i64>> = (0..20)
+            .map(|i| {
+                let base = if i < 8 { 0 } else { 3_600_000 };
+                Some(base + i * 10_000)
+            })
+            .collect();
+        let mut differences = vec![0.05; 19];
+        differences[14] = 0.6;
+        // A fast pan: large, but no larger than its neighbors.
+        for d in &mut differences[1..9] {
+            *d = 0.4;
+        }
+
+        let cuts = find_cuts(&times, &differences, &SceneOptions::default());
+        let scenes = scenes_from_cuts(20, &cuts);
+        let ranges: Vec<(u32, u32)> = scenes.iter().map(|s| (s.start, s.end)).collect();
+        assert_eq!(ranges, vec![(0, 7), (8, 14), (15, 19)]);
+        assert!(matches!(
+            scenes[1].cut,
+            Some(SceneCut::TimeGap { seconds }) if (seconds - 3610.0).abs() < 1e-3
+        ));
+        assert!(matches!(scenes[2].cut, Some(SceneCut::Dissimilar { .. })));
+    }
+
+    #[test]
+    fn missing_times_fall_back_to_visual_cuts() {
+        let times = vec![None; 6];
+        let cuts = find_cuts(&times, &[0.0; 5], &SceneOptions::default());
+        assert!(cuts.is_empty());
+        assert_eq!(scenes_from_cuts(6, &cuts).len(), 1);
+        assert!(scenes_from_cuts(0, &cuts).is_empty());
+    }
+
+    #[test]
+    fn stale_layers_segment_as_one_scene() {
+        let layer = SceneLayer {
+            scenes: scenes_from_cuts(10, &[(4, SceneCut::Dissimilar { score: 0.9 })]),
+            min_gap_s: 300.0,
+            cut_threshold: 0.35,
+            measure_dim: 256,
+            computed_at_unix: 0,
+            source_fingerprint: String::new(),
+        };
+        assert_eq!(layer.segments(10), vec![0..4, 4..10]);
+        assert_eq!(layer.segments(12), vec![0..12]);
+    }
+}
@@ -0,0 +1,468 @@
+//! Video stabilisation: measure how wind and thermal drift moved the camera
+//! from frame to frame, smooth that camera path, and write the difference
+//! into the crop track so the window follows the scene instead of the
+//! sensor.
+//!
+//! The motion between consecutive kept frames is a global fit to the block
+//! motion of their thumbnails (a shift, optionally with rotation and
+//! scale). Summed up it gives the camera path; the correction is the path
+//! minus its low-pass, and a uniform zoom keeps every corrected window
+//! inside the image.
+//!
+//! Idempotency: the track is rebuilt from the base window recorded in the
+//! layer (the project's static crop before the first run, or the full
+//! frame), so re-running never stabilises its own output.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use image::{imageops, RgbImage};
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::deflicker::gaussian_smooth;
+use crate::analysis::luminance::load_thumbnail;
+use crate::analysis::{bridge_excluded, now_unix, source_fingerprint};
+use crate::crop::{rotated_half_extents, CropRect, CropTrack};
+use crate::curve::{Curve, Easing, Keyframe};
+use crate::error::{LapsifyError, Result};
+use crate::flow::{global_motion, GlobalMotion, MotionModel};
+use crate::progress::{ProgressEvent, ProgressReporter};
+use crate::project::Project;
+use crate::source::scan_dimensions;
+
+/// Smallest zoom accepted before the motion is called too large to hide.
+const MIN_ZOOM: f32 = 0.5;
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct StabilizeLayer {
+    /// Per-frame move of the window centre, in normalized source-image
+    /// coordinates: where the scene went minus where the smoothed camera
+    /// path says it should be.
+    pub shift: Vec<[f32; 2]>,
+    /// Per-frame window size factor cancelling zoom jitter. Empty unless
+    /// scale was estimated.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub scale: Vec<f32>,
+    /// Per-frame roll of the scene against the smoothed path, in radians
+    /// clockwise on screen. Empty unless rotation was estimated.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rotation: Vec<f32>,
+    /// Uniform zoom (< 1 shrinks the window) that keeps every corrected
+    /// window inside the image.
+    pub zoom: f32,
+    /// The crop the stabilised track is built from; None = the full frame.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<CropRect>,
+    pub smoothing_frames: u32,
+    pub measure_dim: u32,
+    pub computed_at_unix: u64,
+    pub source_fingerprint: String,
+}
+
+impl StabilizeLayer {
+    /// The stabilised crop: the base window zoomed about its centre,
+    /// moved, resized and turned per frame, one linear keyframe per frame.
+    pub fn crop_track(&self) -> CropTrack {
+        let base = self.base.unwrap_or(FULL_FRAME);
+        let channel = |value: &dyn Fn(usize) -> f32| {
+            Curve::Keyframed(
+                (0..self.shift.len())
+                    .map(|i| Keyframe {
+                        easing: Easing::Linear,
+                        ..Keyframe::new(i as u32, value(i))
+                    })
+                    .collect(),
+            )
+        };
+        let size = |i: usize, base_size: f32| {
+            base_size * self.zoom * self.scale.get(i).copied().unwrap_or(1.0)
+        };
+        let origin = |i: usize, axis: usize, base_origin: f32, base_size: f32| {
+            let extent = size(i, base_size);
+            let centre = base_origin + base_size / 2.0 + self.shift[i][axis];
+            (centre - extent / 2.0).min(1.0 - extent).max(0.0)
+        };
+        let width = if self.scale.is_empty() {
+            Curve::Constant(base.width * self.zoom)
+        } else {
+            channel(&|i| size(i, base.width))
+        };
+        let height = if self.scale.is_empty() {
+            Curve::Constant(base.height * self.zoom)
+        } else {
+            channel(&|i| size(i, base.height))
+        };
+        // The window turns with the scene: a clockwise roll is a negative
+        // (counterclockwise) crop angle.
+        let rotation = if self.rotation.is_empty() {
+            Curve::Constant(0.0)
+        } else {
+            channel(&|i| -self.rotation[i].to_degrees())
+        };
+        CropTrack {
+            x: channel(&|i| origin(i, 0, base.x, base.width)),
+            y: channel(&|i| origin(i, 1, base.y, base.height)),
+            width,
+            height,
+            rotation,
+            aspect: None,
+        }
+    }
+
+    /// Largest window move in normalized coordinates.
+    pub fn max_shift(&self) -> f32 {
+        self.shift
+            .iter()
+            .map(|s| s[0].abs().max(s[1].abs()))
+            .fold(0.0, f32::max)
+    }
+}
+
+const FULL_FRAME: CropRect = CropRect {
+    x: 0.0,
+    y: 0.0,
+    width: 1.0,
+    height: 1.0,
+};
+
+pub struct StabilizeOptions {
+    /// Low-pass window in frames of the camera path that is kept; shorter
+    /// motion is removed.
+    pub smoothing_frames: u32,
+    /// Also estimate and cancel roll, with the crop's rotation channel.
+    pub rotation: bool,
+    /// Also estimate and cancel zoom (focus breathing, thermal focus drift).
+    pub scale: bool,
+    /// Thumbnail size motion is estimated on (shares the luminance cache).
+    pub measure_dim: u32,
+}
+
+impl Default for StabilizeOptions {
+    fn default() -> Self {
+        Self {
+            smoothing_frames: 60,
+            rotation: false,
+            scale: false,
+            measure_dim: 512,
+        }
+    }
+}
+
+/// The window stabilisation starts from: the one a previous run recorded,
+/// else the project's crop, which must not be animated. `src_aspect` sizes
+/// an aspect-locked crop.
+pub fn base_window(project: &Project, src_aspect: f32) -> Result<Option<CropRect>> {
+    if let Some(layer) = project.analysis.as_ref().and_then(|a| a.stabilize.as_ref()) {
+        return Ok(layer.base);
+    }
+    let Some(crop) = &project.crop else {
+        return Ok(None);
+    };
+    let constant = |curve: &Curve| matches!(curve, Curve::Constant(_));
+    let height_fixed = crop.aspect.is_some() || constant(&crop.height);
+    if constant(&crop.x) && constant(&crop.y) && constant(&crop.width) && height_fixed {
+        Ok(Some(crop.rect_at(0, src_aspect)))
+    } else {
+        Err(LapsifyError::message(
+            "Stabilisation needs a static crop to start from; the project's crop is keyframed",
+        ))
+    }
+}
+
+/// Measure the camera path over every frame and build the layer.
+pub fn stabilize(
+    project: &Project,
+    image_files: &[PathBuf],
+    opts: &StabilizeOptions,
+    reporter: &ProgressReporter,
+) -> Result<StabilizeLayer> {
+    let n = image_files.len();
+    let (src_w, src_h) = scan_dimensions(image_files)?;
+    let base = base_window(project, src_w as f32 / src_h as f32)?;
+    let cache_dir = project.input.join(".lapsify").join("thumbs");
+    let cache_dir = std::fs::create_dir_all(&cache_dir)
+        .is_ok()
+        .then_some(cache_dir.as_path());
+    let model = MotionModel {
+        rotation: opts.rotation,
+        scale: opts.scale,
+    };
+
+    let excluded = &project.excluded_frames;
+    let kept: Vec<usize> = (0..n)
+        .filter(|&i| excluded.binary_search(&(i as u32)).is_err())
+        .collect();
+    let segments = match project.analysis.as_ref().and_then(|a| a.scenes.as_ref()) {
+        Some(scenes) => scenes.segments(n),
+        None => std::iter::once(0..n).collect(),
+    };
+    // Motion is measured between consecutive kept frames of one scene;
+    // across a cut there is no camera path to follow.
+    let pairs: Vec<(usize, usize)> = kept
+        .windows(2)
+        .map(|w| (w[0], w[1]))
+        .filter(|&(a, b)| segments.iter().any(|s| s.contains(&a) && s.contains(&b)))
+        .collect();
+
+    // Motion is measured on the frames as the crop sees them: after the
+    // perspective correction, which runs before it.
+    let corrected = |img: RgbImage, frame: usize| match &project.perspective {
+        Some(perspective) if !perspective.is_identity_at(frame as u32) => {
+            perspective.warp(&img, frame as u32)
+        }
+        _ => img,
+    };
+    let done = AtomicUsize::new(0);
+    let motions: Vec<((usize, usize), Step)> = pairs
+        .par_iter()
+        .map(|&(a, b)| -> Result<_> {
+            let first = load_thumbnail(&image_files[a], opts.measure_dim, cache_dir)?;
+            let mut second = load_thumbnail(&image_files[b], opts.measure_dim, cache_dir)?;
+            let (w, h) = first.dimensions();
+            if second.dimensions() != (w, h) {
+                second = imageops::resize(&second, w, h, imageops::FilterType::Triangle);
+            }
+            let (first, second) = (corrected(first, a), corrected(second, b));
+            let step = Step::of(&global_motion(&first, &second, model), w, h);
+            let current = done.fetch_add(1, Ordering::Relaxed) + 1;
+            reporter.report(ProgressEvent::Frame {
+                index: b,
+                done: current,
+                total: pairs.len(),
+            });
+            Ok(((a, b), step))
+        })
+        .collect::<Result<_>>()?;
+
+    let mut path = vec![Step::default(); n];
+    for ((a, b), step) in motions {
+        path[b] = path[a].then(step);
+    }
+    bridge_excluded(&mut path, excluded, Step::lerp);
+
+    let sigma = opts.smoothing_frames as f32 / 4.0;
+    let mut corrections = Vec::with_capacity(n);
+    for range in &segments {
+        let channel = |f: fn(&Step) -> f32| {
+            let raw: Vec<f32> = path[range.clone()].iter().map(f).collect();
+            let smooth = gaussian_smooth(&raw, sigma);
+            raw.iter()
+                .zip(smooth)
+                .map(|(r, s)| r - s)
+                .collect::<Vec<_>>()
+        };
+        let (x, y) = (channel(|s| s.x), channel(|s| s.y));
+        let (roll, log_scale) = (channel(|s| s.roll), channel(|s| s.log_scale));
+        corrections.extend((0..range.len()).map(|i| Step {
+            x: x[i],
+            y: y[i],
+            roll: roll[i],
+            log_scale: log_scale[i],
+        }));
+    }
+
+    let shift: Vec<[f32; 2]> = corrections.iter().map(|c| [c.x, c.y]).collect();
+    let scale: Vec<f32> = if opts.scale {
+        corrections.iter().map(|c| c.log_scale.exp()).collect()
+    } else {
+        Vec::new()
+    };
+    let rotation: Vec<f32> = if opts.rotation {
+        corrections.iter().map(|c| c.roll).collect()
+    } else {
+        Vec::new()
+    };
+    // Turning is isotropic in pixels, not in normalized coordinates.
+    let aspect = match kept.first() {
+        Some(&i) => {
+            let thumb = load_thumbnail(&image_files[i], opts.measure_dim, cache_dir)?;
+            thumb.width() as f32 / thumb.height() as f32
+        }
+        None => 1.0,
+    };
+    let zoom = fit_zoom(
+        base.unwrap_or(FULL_FRAME),
+        &shift,
+        &scale,
+        &rotation,
+        aspect,
+    );
+    if zoom < MIN_ZOOM {
+        return Err(LapsifyError::message(format!(
+            "The camera moves too far to stabilise within the image (zoom {zoom:.2}); lower --smoothing"
+        )));
+    }
+
+    Ok(StabilizeLayer {
+        shift,
+        scale,
+        rotation,
+        zoom,
+        base,
+        smoothing_frames: opts.smoothing_frames,
+        measure_dim: opts.measure_dim,
+        computed_at_unix: now_unix(),
+        source_fingerprint: source_fingerprint(image_files)?,
+    })
+}
+
+/// Camera motion in normalized coordinates: the shift of the frame centre,
+/// roll in radians and the log of the scale.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+struct Step {
+    x: f32,
+    y: f32,
+    roll: f32,
+    log_scale: f32,
+}
+
+impl Step {
+    /// The motion from one `w`x`h` frame to the next, taken at the centre.
+    fn of(motion: &GlobalMotion, w: u32, h: u32) -> Self {
+        let centre = (w as f32 / 2.0, h as f32 / 2.0);
+        let (x, y) = motion.apply(centre);
+        Self {
+            x: (x - centre.0) / w as f32,
+            y: (y - centre.1) / h as f32,
+            roll: motion.rotation(),
+            log_scale: motion.scale().ln(),
+        }
+    }
+
+    fn then(self, next: Self) -> Self {
+        Self {
+            x: self.x + next.x,
+            y: self.y + next.y,
+            roll: self.roll + next.roll,
+            log_scale: self.log_scale + next.log_scale,
+        }
+    }
+
+    fn lerp(a: Self, b: Self, t: f32) -> Self {
+        let mix = |p: f32, q: f32| p + (q - p) * t;
+        Self {
+            x: mix(a.x, b.x),
+            y: mix(a.y, b.y),
+            roll: mix(a.roll, b.roll),
+            log_scale: mix(a.log_scale, b.log_scale),
+        }
+    }
+}
+
+/// The largest zoom (at most 1) keeping every moved, resized and turned
+/// window inside an image `aspect` wide.
+fn fit_zoom(
+    base: CropRect,
+    shift: &[[f32; 2]],
+    scale: &[f32],
+    rotation: &[f32],
+    aspect: f32,
+) -> f32 {
+    let mut zoom = 1.0f32;
+    for (i, s) in shift.iter().enumerate() {
+        let factor = scale.get(i).copied().unwrap_or(1.0);
+        let window = CropRect {
+            width: base.width * factor,
+            height: base.height * factor,
+            ..base
+        };
+        let degrees = rotation.get(i).map_or(0.0, |roll| -roll.to_degrees());
+        let (half_w, half_h) = rotated_half_extents(window, degrees, aspect);
+        let centre_x = base.x + base.width / 2.0 + s[0];
+        let centre_y = base.y + base.height / 2.0 + s[1];
+        zoom = zoom
+            .min(centre_x.min(1.0 - centre_x) / half_w)
+            .min(centre_y.min(1.0 - centre_y) / half_h);
+    }
+    zoom.max(0.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn zoom_keeps_every_window_inside() {
+        let shift = [[0.0, 0.0], [0.05, -0.02], [-0.03, 0.01]];
+        let zoom = fit_zoom(FULL_FRAME, &shift, &[], &[], 1.5);
+        assert_relative_eq!(zoom, 0.9);
+
+        let layer = StabilizeLayer {
+            shift: shift.to_vec(),
+            scale: Vec::new(),
+            rotation: Vec::new(),
+            zoom,
+            base: None,
+            smoothing_frames: 30,
+            measure_dim: 512,
+            computed_at_unix: 0,
+            source_fingerprint: String::new(),
+        };
+        let track = layer.crop_track();
+        assert!(track.validate_over(3, 150, 100, None).is_ok());
+        let rect = track.rect_at(1, 1.5);
+        assert_relative_eq!(rect.x + rect.width / 2.0, 0.55, epsilon = 1e-6);
+        assert_relative_eq!(rect.y + rect.height / 2.0, 0.48, epsilon = 1e-6);
+        assert_relative_eq!(rect.width, 0.9);
+    }
+
+    #[test]
+    fn scale_correction_resizes_within_the_base() {
+        let base = CropRect {
+            x: 0.2,
+            y: 0.2,
+            width: 0.6,
+            height: 0.6,
+        };
+        // The window grows by 10 % on frame 1: its margin is what limits zoom.
+        let zoom = fit_zoom(base, &[[0.0, 0.0], [0.0, 0.0]], &[1.0, 1.1], &[], 1.5);
+        assert_relative_eq!(zoom, 1.0);
+        let zoom = fit_zoom(
+            FULL_FRAME,
+            &[[0.0, 0.0], [0.0, 0.0]],
+            &[1.0, 1.25],
+            &[],
+            1.5,
+        );
+        assert_relative_eq!(zoom, 0.8);
+    }
+
+    #[test]
+    fn roll_correction_turns_a_window_that_fits() {
+        let shift = [[0.0, 0.0], [0.0, 0.0]];
+        let rotation = [0.0, 0.02];
+        let zoom = fit_zoom(FULL_FRAME, &shift, &[], &rotation, 1.5);
+        assert!(zoom < 1.0 && zoom > 0.9, "{zoom}");
+
+        let layer = StabilizeLayer {
+            shift: shift.to_vec(),
+            scale: Vec::new(),
+            rotation: rotation.to_vec(),
+            zoom,
+            base: None,
+            smoothing_frames: 30,
+            measure_dim: 512,
+            computed_at_unix: 0,
+            source_fingerprint: String::new(),
+        };
+        let track = layer.crop_track();
+        assert_relative_eq!(track.rotation_at(1), -0.02f32.to_degrees());
+        assert!(track.validate_over(2, 150, 100, None).is_ok());
+    }
+
+    #[test]
+    fn steps_compose_into_a_path() {
+        let shift = GlobalMotion {
+            to: (4.0, -2.0),
+            ..GlobalMotion::IDENTITY
+        };
+        let step = Step::of(&shift, 200, 100);
+        assert_relative_eq!(step.x, 0.02);
+        assert_relative_eq!(step.y, -0.02);
+        let path = step.then(step);
+        assert_relative_eq!(path.x, 0.04);
+        assert_relative_eq!(Step::lerp(step, path, 0.5).x, 0.03);
+    }
+}
@@ -0,0 +1,328 @@
+//! White-balance holy grail: cancel auto white balance jumps in RAW frames.
+//!
+//! `raw::decode_raw` develops each frame with its own as-shot white balance,
+//! so when the camera's auto white balance hunts through a sunset, the
+//! developed colors jump from frame to frame. This layer reads the as-shot
+//! multipliers of every frame and converts each frame's deviation from a
+//! reference into temperature and tint compensation in the same units as
+//! the `ColorGrade` curves, so the compensation adds onto them. With
+//! smoothing, the reference follows a low-passed version of the as-shot
+//! white balance instead of frame 0: the jumps go, the slow drift stays.
+//! The layer's keyframable `strength` scales the compensation, so it can be
+//! eased in or out along the clip, and survives re-running the analysis.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::deflicker::gaussian_smooth;
+use crate::analysis::{now_unix, source_fingerprint};
+use crate::curve::Curve;
+use crate::error::{LapsifyError, Result};
+use crate::progress::{ProgressEvent, ProgressReporter};
+use crate::timeline::Timeline;
+
+/// Stops of red (and inverse blue) gain per 100 temperature units, and of
+/// inverse green gain per 100 tint units. Must match `ColorParams::wb_gain`.
+pub(crate) const TEMPERATURE_STOPS: f32 = 0.4;
+pub(crate) const TINT_STOPS: f32 = 0.3;
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct WhiteBalanceLayer {
+    /// Temperature compensation per frame, in `ColorGrade::temperature` units.
+    pub temperature: Vec<f32>,
+    /// Tint compensation per frame, in `ColorGrade::tint` units.
+    pub tint: Vec<f32>,
+    /// How much of the compensation applies, 0-1, keyframable on the
+    /// project timeline: 1 cancels the jumps fully, 0 keeps the as-shot
+    /// color.
+    #[serde(default = "full_strength")]
+    pub strength: Curve,
+    /// Low-pass window of the reference in frames. 0 = cancel every frame
+    /// back to frame 0's white balance.
+    pub smoothing_frames: u32,
+    /// Frames with no as-shot white balance (their value was carried
+    /// forward).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub frames_missing_wb: Vec<u32>,
+    pub computed_at_unix: u64,
+    pub source_fingerprint: String,
+}
+
+fn full_strength() -> Curve {
+    Curve::Constant(1.0)
+}
+
+impl WhiteBalanceLayer {
+    /// Temperature compensation at a frame, as computed (before strength).
+    pub fn temperature_at(&self, frame: usize) -> f32 {
+        sample_clamped(&self.temperature, frame)
+    }
+
+    /// Tint compensation at a frame, as computed (before strength).
+    pub fn tint_at(&self, frame: usize) -> f32 {
+        sample_clamped(&self.tint, frame)
+    }
+
+    /// Temperature compensation applied at a frame of `timeline`.
+    pub fn temperature_on(&self, frame: u32, timeline: &Timeline) -> f32 {
+        self.strength.sample_on(frame, timeline) * self.temperature_at(frame as usize)
+    }
+
+    /// Tint compensation applied at a frame of `timeline`.
+    pub fn tint_on(&self, frame: u32, timeline: &Timeline) -> f32 {
+        self.strength.sample_on(frame, timeline) * self.tint_at(frame as usize)
+    }
+}
+
+fn sample_clamped(values: &[f32], frame: usize) -> f32 {
+    if values.is_empty() {
+        0.0
+    } else {
+        values[frame.min(values.len() - 1)]
+    }
+}
+
+#[derive(Default)]
+pub struct WhiteBalanceOptions {
+    /// Low-pass window of the reference in frames. 0 = fixed reference.
+    pub smoothing_frames: u32,
+    /// Frames the project excludes: treated as having no as-shot white
+    /// balance, so a flash frame can't drag the smoothed reference.
+    pub excluded_frames: Vec<u32>,
+}
+
+/// Temperature and tint that turn white balance `from` into `to`, both as
+/// (R, G, B) multipliers. Only the R/G and B/G ratios matter; overall gain
+/// is exposure, not color.
+pub fn wb_shift(from: [f32; 3], to: [f32; 3]) -> (f32, f32) {
+    let r = ((to[0] / to[1]) / (from[0] / from[1])).log2();
+    let b = ((to[2] / to[1]) / (from[2] / from[1])).log2();
+    // R/G = 0.4 T + 0.3 tint and B/G = -0.4 T + 0.3 tint, in stops per 100.
+    let temperature = 100.0 * (r - b) / (2.0 * TEMPERATURE_STOPS);
+    let tint = 100.0 * (r + b) / (2.0 * TINT_STOPS);
+    (temperature, tint)
+}
+
+/// The pure cancellation: per-frame as-shot multipliers (None = missing) to
+/// per-frame temperature and tint compensation, with carry-forward for gaps.
+pub fn layer_from_wb(
+    wbs: &[Option<[f32; 3]>],
+    smoothing_frames: u32,
+) -> Result<(Vec<f32>, Vec<f32>, Vec<u32>)> {
+    let usable = wbs.iter().flatten().count();
+    if usable < 2 {
+        return Err(LapsifyError::message(
+            "No usable as-shot white balance; white balance analysis needs RAW frames that record it in at least 2 frames",
+        ));
+    }
+
+    // Log-ratio space is where white balance is additive, so both carrying
+    // forward and smoothing happen there.
+    let mut missing = Vec::new();
+    let mut log_rg = Vec::with_capacity(wbs.len());
+    let mut log_bg = Vec::with_capacity(wbs.len());
+    let first = wbs.iter().flatten().next().copied().unwrap();
+    let mut previous = first;
+    for (i, wb) in wbs.iter().enumerate() {
+        let wb = match wb {
+            Some(wb) => *wb,
+            None => {
+                missing.push(i as u32);
+                previous
+            }
+        };
+        log_rg.push((wb[0] / wb[1]).log2());
+        log_bg.push((wb[2] / wb[1]).log2());
+        previous = wb;
+    }
+
+    let (ref_rg, ref_bg) = if smoothing_frames > 0 {
+        let sigma = smoothing_frames as f32 / 4.0;
+        (
+            gaussian_smooth(&log_rg, sigma),
+            gaussian_smooth(&log_bg, sigma),
+        )
+    } else {
+        (vec![log_rg[0]; wbs.len()], vec![log_bg[0]; wbs.len()])
+    };
+
+    let mut temperature = Vec::with_capacity(wbs.len());
+    let mut tint = Vec::with_capacity(wbs.len());
+    for i in 0..wbs.len() {
+        let from = [2.0_f32.powf(log_rg[i]), 1.0, 2.0_f32.powf(log_bg[i])];
+        let to = [2.0_f32.powf(ref_rg[i]), 1.0, 2.0_f32.powf(ref_bg[i])];
+        let (t, g) = wb_shift(from, to);
+        temperature.push(t);
+        tint.push(g);
+    }
+
+    Ok((temperature, tint, missing))
+}
+
+/// Read the as-shot white balance across the sequence and build the layer.
+pub fn compute_white_balance(
+    image_files: &[PathBuf],
+    opts: &WhiteBalanceOptions,
+    reporter: &ProgressReporter,
+) -> Result<WhiteBalanceLayer> {
+    let total = image_files.len();
+    let done = AtomicUsize::new(0);
+
+    let wbs: Vec<Option<[f32; 3]>> = image_files
+        .par_iter()
+        .enumerate()
+        .map(|(i, path)| {
+            let wb = if opts.excluded_frames.binary_search(&(i as u32)).is_ok() {
+                None
+            } else {
+                as_shot_wb(path)
+            };
+            let current = done.fetch_add(1, Ordering::Relaxed) + 1;
+            reporter.report(ProgressEvent::Frame {
+                index: current - 1,
+                done: current,
+                total,
+            });
+            wb
+        })
+        .collect();
+
+    let (temperature, tint, missing) = layer_from_wb(&wbs, opts.smoothing_frames)?;
+
+    Ok(WhiteBalanceLayer {
+        temperature,
+        tint,
+        strength: full_strength(),
+        smoothing_frames: opts.smoothing_frames,
+        frames_missing_wb: missing,
+        computed_at_unix: now_unix(),
+        source_fingerprint: source_fingerprint(image_files)?,
+    })
+}
+
+/// As-shot white balance of a RAW frame (always None without the `raw`
+/// feature, and for non-RAW frames, which carry no multipliers).
+fn as_shot_wb(path: &Path) -> Option<[f32; 3]> {
+    #[cfg(feature = "raw")]
+    if crate::source::is_raw_path(path) {
+        return crate::raw::as_shot_wb(path);
+    }
+    #[cfg(not(feature = "raw"))]
+    let _ = path;
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::{transfer, ColorParams};
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn shift_round_trips_through_the_color_gains() {
+        // Developed with a warmer as-shot WB than the reference: the
+        // compensation must cool it back by exactly the gain difference.
+        let reference = [2.0, 1.0, 1.5];
+        let warmer = [2.4, 1.0, 1.3];
+        let (t, g) = wb_shift(warmer, reference);
+        assert!(t < 0.0, "should cool: {t}");
+
+        let params = ColorParams {
+            exposure: 0.0,
+            temperature: t,
+            tint: g,
+            brightness: 0.0,
+            contrast: 1.0,
+            highlights: 0.0,
+            shadows: 0.0,
+            whites: 0.0,
+            blacks: 0.0,
+            gamma: 1.0,
+            saturation: 1.0,
+            vibrance: 0.0,
+            tone_curve: None,
+        };
+        // Gains relative to green must turn `warmer` into `reference`.
+        let gain = |c: usize| {
+            let linear = 0.2f32;
+            let out = params.tonal_chain(c, transfer::linear_to_srgb(linear));
+            transfer::srgb_to_linear(out) / linear
+        };
+        assert_relative_eq!(gain(0) / gain(1), (2.0 / 1.0) / (2.4 / 1.0), epsilon = 1e-3);
+        assert_relative_eq!(gain(2) / gain(1), 1.5 / 1.3, epsilon = 1e-3);
+    }
+
+    #[test]
+    fn fixed_reference_cancels_jumps() {
+        let wbs = vec![
+            Some([2.0, 1.0, 1.5]),
+            Some([2.0, 1.0, 1.5]),
+            Some([2.4, 1.0, 1.3]),
+            None,
+        ];
+        let (temperature, tint, missing) = layer_from_wb(&wbs, 0).unwrap();
+        assert_relative_eq!(temperature[0], 0.0);
+        assert_relative_eq!(tint[1], 0.0);
+        assert!(temperature[2] < 0.0);
+        // The missing frame carries the previous white balance forward.
+        assert_relative_eq!(temperature[3], temperature[2]);
+        assert_eq!(missing, vec![3]);
+    }
+
+    #[test]
+    fn smoothing_keeps_drift_and_removes_jumps() {
+        // Slow warming drift with a one-frame jump at frame 10.
+        let wbs: Vec<Option<[f32; 3]>> = (0..20)
+            .map(|i| {
+                let r = 2.0 + i as f32 * 0.01 + if i == 10 { 0.4 } else { 0.0 };
+                Some([r, 1.0, 1.5])
+            })
+            .collect();
+        let (temperature, _, _) = layer_from_wb(&wbs, 8).unwrap();
+        // The jump frame gets a strong correction, its neighbors little.
+        assert!(temperature[10] < -10.0, "{}", temperature[10]);
+        assert!(temperature[5].abs() < 5.0, "{}", temperature[5]);
+    }
+
+    #[test]
+    fn too_little_wb_is_an_error() {
+        assert!(layer_from_wb(&[None, Some([2.0, 1.0, 1.5])], 0).is_err());
+    }
+
+    #[test]
+    fn layer_samples_clamp() {
+        let layer = WhiteBalanceLayer {
+            temperature: vec![1.0, 2.0],
+            tint: vec![],
+            strength: full_strength(),
+            smoothing_frames: 0,
+            frames_missing_wb: vec![],
+            computed_at_unix: 0,
+            source_fingerprint: String::new(),
+        };
+        assert_relative_eq!(layer.temperature_at(9), 2.0);
+        assert_relative_eq!(layer.tint_at(0), 0.0);
+    }
+
+    #[test]
+    fn strength_scales_the_compensation_along_the_clip() {
+        use crate::curve::Keyframe;
+
+        let layer = WhiteBalanceLayer {
+            temperature: vec![-40.0; 5],
+            tint: vec![10.0; 5],
+            strength: Curve::Keyframed(vec![Keyframe::new(0, 1.0), Keyframe::new(4, 0.0)]),
+            smoothing_frames: 0,
+            frames_missing_wb: vec![],
+            computed_at_unix: 0,
+            source_fingerprint: String::new(),
+        };
+        let timeline = Timeline::indices(5);
+        assert_relative_eq!(layer.temperature_on(0, &timeline), -40.0);
+        assert_relative_eq!(layer.temperature_on(2, &timeline), -20.0);
+        assert_relative_eq!(layer.tint_on(4, &timeline), 0.0);
+    }
+}
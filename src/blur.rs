@@ -0,0 +1,137 @@
+//! Motion blur by integrating source frames.
+//!
+//! An output frame sits at a (possibly fractional) source position. Its
+//! exposure window is centered there, and each source frame contributes
+//! the part of the weighting kernel that falls on its own slot, one frame
+//! wide. Box weighting over a whole number of frames is a plain average;
+//! fractional windows weight their edge frames partially, so a keyframed
+//! window changes smoothly.
+
+use crate::project::{BlurUnit, BlurWeighting, MotionBlur, Project};
+use crate::timeline::Timeline;
+
+/// Integration steps per frame slot.
+const SLOT_SAMPLES: usize = 16;
+
+/// Length of the window in source frames for the output frame at source
+/// frame `frame`.
+pub fn window_frames(blur: &MotionBlur, project: &Project, frame: u32, timeline: &Timeline) -> f32 {
+    let value = blur.window.sample_on(frame, timeline);
+    match blur.unit {
+        BlurUnit::Frames => value,
+        BlurUnit::ShutterAngle => value / 360.0 * output_step(project, frame, timeline),
+    }
+}
+
+/// Source frames between consecutive output frames around `frame`.
+fn output_step(project: &Project, frame: u32, timeline: &Timeline) -> f32 {
+    let speed = project
+        .export
+        .speed
+        .as_ref()
+        .map_or(1.0, |speed| speed.sample_on(frame, timeline));
+    (speed as f64 / project.export.rate_factor()) as f32
+}
+
+/// Normalized weights of the source frames (out of `frames`) in a window
+/// `window` frames long centered on `position`. Frames `usable` rejects
+/// (excluded frames) are left out and the rest renormalized. A window
+/// under one frame holds no blur and yields nothing.
+pub fn blur_weights(
+    position: f64,
+    window: f32,
+    weighting: BlurWeighting,
+    frames: usize,
+    usable: impl Fn(usize) -> bool,
+) -> Vec<(usize, f32)> {
+    if window < 1.0 || frames == 0 {
+        return Vec::new();
+    }
+    let half = window as f64 / 2.0;
+    let first = (position - half - 0.5).floor().max(0.0) as usize;
+    let last = ((position + half + 0.5).ceil() as usize).min(frames - 1);
+
+    let mut weights: Vec<(usize, f32)> = (first..=last)
+        .filter(|&i| usable(i))
+        .filter_map(|i| {
+            // Midpoint rule over the frame's slot [i - 0.5, i + 0.5].
+            let weight: f64 = (0..SLOT_SAMPLES)
+                .map(|s| {
+                    let x = i as f64 - 0.5 + (s as f64 + 0.5) / SLOT_SAMPLES as f64;
+                    kernel(weighting, (x - position) / half)
+                })
+                .sum();
+            (weight > 0.0).then_some((i, weight as f32))
+        })
+        .collect();
+    let total: f32 = weights.iter().map(|w| w.1).sum();
+    if total <= 0.0 {
+        return Vec::new();
+    }
+    for w in &mut weights {
+        w.1 /= total;
+    }
+    weights
+}
+
+/// The weighting at `u` half-windows from the center (zero beyond one).
+fn kernel(weighting: BlurWeighting, u: f64) -> f64 {
+    let u = u.abs();
+    if u > 1.0 {
+        return 0.0;
+    }
+    match weighting {
+        BlurWeighting::Box => 1.0,
+        BlurWeighting::Triangle => 1.0 - u,
+        // Two standard deviations to the window edge.
+        BlurWeighting::Gaussian => (-2.0 * u * u).exp(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weights(position: f64, window: f32, weighting: BlurWeighting) -> Vec<(usize, f32)> {
+        blur_weights(position, window, weighting, 100, |_| true)
+    }
+
+    #[test]
+    fn box_windows_average_whole_frames() {
+        let three = weights(50.0, 3.0, BlurWeighting::Box);
+        assert_eq!(
+            three.iter().map(|w| w.0).collect::<Vec<_>>(),
+            vec![49, 50, 51]
+        );
+        assert!(three.iter().all(|w| (w.1 - 1.0 / 3.0).abs() < 1e-4));
+
+        // Two frames centered on a frame reach half of each neighbor.
+        let two = weights(50.0, 2.0, BlurWeighting::Box);
+        let expected = [0.25, 0.5, 0.25];
+        for (w, e) in two.iter().zip(expected) {
+            assert!((w.1 - e).abs() < 1e-4, "{two:?}");
+        }
+
+        assert!(weights(50.0, 0.5, BlurWeighting::Box).is_empty());
+    }
+
+    #[test]
+    fn shaped_windows_favor_the_center() {
+        for weighting in [BlurWeighting::Triangle, BlurWeighting::Gaussian] {
+            let w = weights(50.0, 5.0, weighting);
+            let total: f32 = w.iter().map(|w| w.1).sum();
+            assert!((total - 1.0).abs() < 1e-4);
+            let center = w.iter().find(|w| w.0 == 50).unwrap().1;
+            assert!(w.iter().all(|w| w.1 <= center));
+            assert!(w.iter().find(|w| w.0 == 48).unwrap().1 < center / 2.0);
+        }
+    }
+
+    #[test]
+    fn excluded_frames_and_edges_are_left_out() {
+        let w = blur_weights(1.0, 5.0, BlurWeighting::Box, 100, |i| i != 2);
+        assert_eq!(w.iter().map(|w| w.0).collect::<Vec<_>>(), vec![0, 1, 3]);
+        let total: f32 = w.iter().map(|w| w.1).sum();
+        assert!((total - 1.0).abs() < 1e-4);
+    }
+}
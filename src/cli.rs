@@ -2,16 +2,20 @@ use std::path::{Path, PathBuf};
 use std::time::Instant;
 
 use clap::parser::ValueSource;
-use clap::{Arg, ArgMatches, Command};
+use clap::{Arg, ArgAction, ArgMatches, Command};
 use colored::*;
 
 use crate::crop::{legacy_crop_to_track, parse_crop_dims};
 use crate::curve::{curve_from_legacy_array, parse_value_array, Curve};
 use crate::error::{LapsifyError, Result};
-use crate::export::images::render_to_images;
-use crate::export::video::render_to_video;
+use crate::export::render_project;
+use crate::perspective::Perspective;
 use crate::progress::ProgressReporter;
-use crate::project::{Codec, ColorGrade, ExportSettings, Project, PROJECT_VERSION};
+use crate::project::{
+    Accumulate, AccumulateMode, Aspect, BlurUnit, BlurWeighting, Codec, ColorGrade, ExportSettings,
+    FrameInterpolation, Framing, MotionBlur, Project, Retime, Stack, StackAlign, StackMode,
+    PROJECT_VERSION,
+};
 use crate::source::{list_images, scan_dimensions};
 
 fn build_command() -> Command {
@@ -61,7 +65,43 @@ fn build_command() -> Command {
             )))
             .subcommand(Command::new("schema").about(
                 "Print the JSON Schema of the project file format (for editor validation)",
-            )),
+            ))
+            .subcommand(
+                Command::new("rebase")
+                    .about("Remap keyframes and analysis after frames were inserted, deleted or renamed")
+                    .arg(
+                        Arg::new("project")
+                            .short('p')
+                            .long("project")
+                            .value_name("FILE")
+                            .help("Project file to remap")
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::new("dry-run")
+                            .long("dry-run")
+                            .num_args(0)
+                            .help("Report what would change without writing the project"),
+                    ),
+            )
+            .subcommand(
+                Command::new("split")
+                    .about("Move each scene found by `analyze scenes` into its own folder and project file")
+                    .arg(
+                        Arg::new("project")
+                            .short('p')
+                            .long("project")
+                            .value_name("FILE")
+                            .help("Project file to split; scene projects are written next to it")
+                            .required(true),
+                    )
+                    .arg(
+                        Arg::new("dry-run")
+                            .long("dry-run")
+                            .num_args(0)
+                            .help("Report the split without moving frames or writing projects"),
+                    ),
+            ),
     )
     .subcommand(
         render_args(Command::new("deflicker").about(
@@ -120,7 +160,31 @@ fn build_command() -> Command {
             .subcommand_required(true)
             .subcommand(render_args(Command::new("dump").about(
                 "Print every layer curve sampled per frame as one JSON document",
-            ))),
+            )))
+            .subcommand(
+                render_args(Command::new("bake").about(
+                    "Turn a per-frame analysis layer into the fewest editable keyframes that reproduce it",
+                ))
+                .arg(
+                    Arg::new("layer")
+                        .long("layer")
+                        .value_name("LAYER")
+                        .help("What to bake: 'holy-grail', 'deflicker' or 'effective' (both, into the exposure curve), or 'white-balance' (into the temperature and tint curves). The layer is summed with the user curve it adds onto")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("tolerance")
+                        .long("tolerance")
+                        .value_name("VALUE")
+                        .help("Largest allowed difference at any frame, in curve units. Default: 0.02 EV for exposure, 0.5 for temperature/tint"),
+                )
+                .arg(
+                    Arg::new("clear")
+                        .long("clear")
+                        .num_args(0)
+                        .help("Write the baked keyframes into the project file and remove the source layer(s), so the result can be hand-tuned. Without it the keyframes are only printed"),
+                ),
+            ),
     )
     .subcommand(
         Command::new("keyframes")
@@ -128,7 +192,7 @@ fn build_command() -> Command {
             .subcommand_required(true)
             .subcommand(
                 render_args(Command::new("suggest").about(
-                    "Suggest keyframe positions from the luminance progression (denser where brightness changes fast)",
+                    "Suggest keyframe positions from the luminance or color progression (denser where it changes fast)",
                 ))
                 .arg(
                     Arg::new("count")
@@ -139,15 +203,22 @@ fn build_command() -> Command {
                 .arg(
                     Arg::new("density")
                         .long("density")
-                        .value_name("PER_EV")
-                        .help("Keyframes per EV of total luminance travel (when --count is not given)")
+                        .value_name("PER_STEP")
+                        .help("Keyframes per EV of total luminance travel, or per 10 units of temperature/tint drift (when --count is not given)")
                         .default_value("1.5"),
                 )
                 .arg(
                     Arg::new("apply")
                         .long("apply")
                         .num_args(0)
-                        .help("Insert the suggested keyframes into the exposure curve at their current values (visually a no-op that gives an editor handles to grab)"),
+                        .help("Insert the suggested keyframes into the matching color curve at their current values (visually a no-op that gives an editor handles to grab)"),
+                )
+                .arg(
+                    Arg::new("param")
+                        .long("param")
+                        .value_name("PARAM")
+                        .help("Curve to suggest keyframes for: 'exposure' (from luminance travel), 'temperature' or 'tint' (from chromaticity drift)")
+                        .default_value("exposure"),
                 ),
             ),
     )
@@ -195,7 +266,20 @@ fn build_command() -> Command {
                         .allow_hyphen_values(true)
                         .long("rotate")
                         .value_name("EV")
-                        .help("Linear baseline tilt over the clip, in EV. Default: auto-fit so the compensation ends at 0"),
+                        .help("Linear baseline tilt over the clip, in EV. Default: fit according to --fit"),
+                )
+                .arg(
+                    Arg::new("fit")
+                        .long("fit")
+                        .value_name("MODE")
+                        .help("How rotate is chosen: 'end' (compensation ends at 0) or 'luminance' (rotate and stretch solved by least squares so the compensated source luminance follows a smooth trend; needs `analyze luminance`)")
+                        .default_value("end"),
+                )
+                .arg(
+                    Arg::new("trend")
+                        .long("trend")
+                        .value_name("FRAMES")
+                        .help("Low-pass window in frames of the trend a luminance fit follows. Default: a quarter of the clip"),
                 )
                 .arg(
                     Arg::new("stretch")
@@ -205,6 +289,169 @@ fn build_command() -> Command {
                         .help("Scale of the whole compensation")
                         .default_value("1.0"),
                 )
+                .arg(
+                    Arg::new("ev-event")
+                        .long("ev-event")
+                        .value_name("FRAME:EV")
+                        .action(ArgAction::Append)
+                        .help("Manual exposure change EXIF can't see, e.g. '812:+3' for a 3-stop ND filter added at frame 812 (repeatable). Replaces the events stored by a previous run; without it they are kept"),
+                )
+                .arg(
+                    Arg::new("exposure-bias")
+                        .long("exposure-bias")
+                        .num_args(0)
+                        .help("Fold camera exposure compensation (ExposureBiasValue) into the compensation, for cameras that record the bias without applying it through shutter, aperture or ISO"),
+                )
+                .arg(
+                    Arg::new("no-write")
+                        .long("no-write")
+                        .num_args(0)
+                        .help("Compute and emit events without writing the result into the project file"),
+                ),
+            )
+            .subcommand(
+                render_args(Command::new("whitebalance").about(
+                    "Compute temperature/tint compensation for as-shot white balance jumps in RAW frames",
+                ))
+                .arg(
+                    Arg::new("smoothing")
+                        .long("smoothing")
+                        .value_name("FRAMES")
+                        .help("Low-pass window of the reference white balance. 0 cancels every frame back to frame 0; larger values keep slow drift and remove only jumps")
+                        .default_value("0"),
+                )
+                .arg(
+                    Arg::new("no-write")
+                        .long("no-write")
+                        .num_args(0)
+                        .help("Compute and emit events without writing the result into the project file"),
+                ),
+            )
+            .subcommand(
+                render_args(Command::new("intervals").about(
+                    "Report capture intervals: histogram, gaps from dropped frames, duplicate timestamps and clock jumps",
+                ))
+                .arg(
+                    Arg::new("gap-factor")
+                        .long("gap-factor")
+                        .value_name("FACTOR")
+                        .help("Report intervals longer than this many nominal intervals as gaps")
+                        .default_value("1.5"),
+                ),
+            )
+            .subcommand(
+                render_args(Command::new("scenes").about(
+                    "Find scene boundaries (separate shoots on one card) from capture-time gaps and visual cuts",
+                ))
+                .arg(
+                    Arg::new("min-gap")
+                        .long("min-gap")
+                        .value_name("SECONDS")
+                        .help("Shortest capture-time gap that separates shoots (gaps must also be 10x the shooting interval)")
+                        .default_value("300"),
+                )
+                .arg(
+                    Arg::new("cut-threshold")
+                        .long("cut-threshold")
+                        .value_name("SCORE")
+                        .help("Smallest frame-to-frame difference that counts as a visual cut (mean difference of exposure-normalized frames; a few hundredths within a shot)")
+                        .default_value("0.35"),
+                )
+                .arg(
+                    Arg::new("measure-dim")
+                        .long("measure-dim")
+                        .value_name("PIXELS")
+                        .help("Downscale the long edge to this size before measuring (shares the luminance thumbnail cache)")
+                        .default_value("256"),
+                )
+                .arg(
+                    Arg::new("no-write")
+                        .long("no-write")
+                        .num_args(0)
+                        .help("Compute and emit events without writing the result into the project file"),
+                ),
+            )
+            .subcommand(
+                render_args(Command::new("outliers").about(
+                    "Flag bad frames: luminance spikes, blur, sudden displacement, near-black or near-white content",
+                ))
+                .arg(
+                    Arg::new("spike-ev")
+                        .long("spike-ev")
+                        .value_name("EV")
+                        .help("Brightness difference from the neighboring frames that counts as a spike")
+                        .default_value("0.7"),
+                )
+                .arg(
+                    Arg::new("blur-ratio")
+                        .long("blur-ratio")
+                        .value_name("RATIO")
+                        .help("Flag frames with less than this fraction of the neighboring frames' fine detail")
+                        .default_value("0.5"),
+                )
+                .arg(
+                    Arg::new("max-shift")
+                        .long("max-shift")
+                        .value_name("FRACTION")
+                        .help("Jump against the previous frame, beyond the usual frame-to-frame motion, that counts as displacement (fraction of the image width)")
+                        .default_value("0.02"),
+                )
+                .arg(
+                    Arg::new("measure-dim")
+                        .long("measure-dim")
+                        .value_name("PIXELS")
+                        .help("Downscale the long edge to this size before measuring (shares the luminance thumbnail cache)")
+                        .default_value("256"),
+                )
+                .arg(
+                    Arg::new("apply")
+                        .long("apply")
+                        .num_args(0)
+                        .help("Also add the flagged frames to the project's excluded frames"),
+                )
+                .arg(
+                    Arg::new("no-write")
+                        .long("no-write")
+                        .num_args(0)
+                        .help("Compute and emit events without writing the result into the project file"),
+                ),
+            )
+            .subcommand(
+                render_args(Command::new("stabilize").about(
+                    "Remove camera shake and drift: smooth the camera path and write the compensating motion into the crop track",
+                ))
+                .arg(
+                    Arg::new("smoothing")
+                        .long("smoothing")
+                        .value_name("FRAMES")
+                        .help("Low-pass window of the camera path that is kept; faster motion is removed")
+                        .default_value("60"),
+                )
+                .arg(
+                    Arg::new("rotation")
+                        .long("rotation")
+                        .num_args(0)
+                        .help("Also estimate and cancel roll, through the crop's rotation channel"),
+                )
+                .arg(
+                    Arg::new("scale")
+                        .long("scale")
+                        .num_args(0)
+                        .help("Also estimate and cancel zoom (focus breathing)"),
+                )
+                .arg(
+                    Arg::new("measure-dim")
+                        .long("measure-dim")
+                        .value_name("PIXELS")
+                        .help("Downscale the long edge to this size before estimating motion (shares the luminance thumbnail cache)")
+                        .default_value("512"),
+                )
+                .arg(
+                    Arg::new("reset")
+                        .long("reset")
+                        .num_args(0)
+                        .help("Put back the crop from before stabilisation, drop the layer and exit"),
+                )
                 .arg(
                     Arg::new("no-write")
                         .long("no-write")
@@ -380,7 +627,86 @@ fn render_args(cmd: Command) -> Command {
             Arg::new("motion-blur")
                 .long("motion-blur")
                 .value_name("FRAMES")
-                .help("Motion blur as frame blending: average this many neighboring frames per output frame (2-128, video only)"),
+                .help("Motion blur: each output frame integrates a window this many source frames long (up to 128; fractional allowed). Single value or comma-separated array"),
+        )
+        .arg(
+            Arg::new("shutter-angle")
+                .long("shutter-angle")
+                .value_name("DEGREES")
+                .conflicts_with("motion-blur")
+                .help("Motion blur as a shutter angle of the step between output frames (360 = everything up to the next frame; follows --speed and --output-fps). Single value or comma-separated array"),
+        )
+        .arg(
+            Arg::new("blur-weighting")
+                .long("blur-weighting")
+                .value_name("KERNEL")
+                .help("Motion-blur weighting: box (default), triangle or gaussian"),
+        )
+        .arg(
+            Arg::new("retime")
+                .long("retime")
+                .value_name("SECONDS|auto")
+                .help("Pace output frames at a constant real-time rate from capture times: one output frame per SECONDS of capture time, or 'auto' for the median interval"),
+        )
+        .arg(
+            Arg::new("retime-blend")
+                .long("retime-blend")
+                .num_args(0)
+                .help("Blend the two nearest source frames when retiming instead of repeating or dropping frames (implies --retime auto if not given)"),
+        )
+        .arg(
+            Arg::new("accumulate")
+                .long("accumulate")
+                .value_name("MODE")
+                .help("Accumulate frames into trails: lighten (star trails), darken, average or median. Also writes the final combination as a still"),
+        )
+        .arg(
+            Arg::new("accumulate-window")
+                .long("accumulate-window")
+                .value_name("FRAMES")
+                .help("Combine only the last FRAMES frames instead of every frame so far (2-64, each held in memory; required for median)"),
+        )
+        .arg(
+            Arg::new("trail-decay")
+                .long("trail-decay")
+                .value_name("AMOUNT")
+                .help("Fade older frames by this much per frame (0-1, e.g. 0.05 for comet-style trails)"),
+        )
+        .arg(
+            Arg::new("stack")
+                .long("stack")
+                .value_name("FRAMES")
+                .help("Average groups of FRAMES aligned source frames (2-64) in linear light to reduce noise; divides the frame count"),
+        )
+        .arg(
+            Arg::new("stack-sliding")
+                .long("stack-sliding")
+                .num_args(0)
+                .help("Stack a sliding window of frames around every output frame, keeping the frame count"),
+        )
+        .arg(
+            Arg::new("stack-align")
+                .long("stack-align")
+                .value_name("MODE")
+                .help("How stacked frames are aligned: none, translation (default) or rotation (for a sky turning around the pole)"),
+        )
+        .arg(
+            Arg::new("speed")
+                .long("speed")
+                .value_name("FACTOR")
+                .help("Speed ramp: source frames per output frame (0.25 = 4x slower, 4 = 4x faster). Single value or comma-separated array, e.g. '4,0.5,4' to linger on the middle"),
+        )
+        .arg(
+            Arg::new("frame-interpolation")
+                .long("frame-interpolation")
+                .value_name("MODE")
+                .help("How in-between frames are made: 'blend' (mix the neighbors) or 'flow' (motion-compensated, no double images; slower)"),
+        )
+        .arg(
+            Arg::new("output-fps")
+                .long("output-fps")
+                .value_name("RATE")
+                .help("Encode at this frame rate, generating in-between frames so the clip keeps its length at --fps (e.g. --fps 24 --output-fps 60)"),
         )
         .arg(
             Arg::new("jpeg-quality")
@@ -400,7 +726,25 @@ fn render_args(cmd: Command) -> Command {
             Arg::new("resolution")
                 .long("resolution")
                 .value_name("WIDTHxHEIGHT")
-                .help("Output video resolution (e.g., 1920x1080, 4K, HD). Default: original size"),
+                .help("Output resolution (e.g., 1920x1080, 4K, HD). Default: the size of the first frame's crop window, or the original size"),
+        )
+        .arg(
+            Arg::new("aspect")
+                .long("aspect")
+                .value_name("W:H")
+                .help("Output aspect ratio without a resolution (e.g. 16:9, 9:16, 1:1): the largest frame of that shape inside the first frame's picture"),
+        )
+        .arg(
+            Arg::new("framing")
+                .long("framing")
+                .value_name("MODE")
+                .help("How pictures are placed in an output of another shape: 'fit' (scale inside without enlarging, black bars), 'fill' (scale to cover, trim the overhang) or 'letterbox' (scale inside, black bars). Never stretches"),
+        )
+        .arg(
+            Arg::new("upscale")
+                .long("upscale")
+                .num_args(0)
+                .help("Let 'fit' scale the picture up to a larger --resolution (by default it only shrinks, leaving bars)"),
         )
         .arg(
             Arg::new("threads")
@@ -422,12 +766,44 @@ fn render_args(cmd: Command) -> Command {
                 .value_name("INDEX")
                 .help("End frame index (0-based, inclusive). Default: last frame"),
         )
+        .arg(
+            Arg::new("scene")
+                .long("scene")
+                .value_name("N")
+                .conflicts_with_all(["start-frame", "end-frame"])
+                .help("Render only scene N (1-based) of those found by `analyze scenes`"),
+        )
+        .arg(
+            Arg::new("exclude")
+                .long("exclude")
+                .value_name("FRAMES")
+                .help("Source frames to leave out, e.g. \"12,40-45\" (0-based, replaces the project's list)"),
+        )
+        .arg(
+            Arg::new("fill-excluded")
+                .long("fill-excluded")
+                .num_args(0)
+                .help("Replace excluded frames with a blend of their neighbors instead of dropping them"),
+        )
         .arg(
             Arg::new("crop")
                 .long("crop")
                 .value_name("WIDTH:HEIGHT:X:Y")
                 .help("Crop parameters in FFmpeg format (e.g., '1000:800:100:50' or '50%:50%:10%:10%')"),
         )
+        .arg(
+            Arg::new("crop-aspect")
+                .long("crop-aspect")
+                .value_name("W:H")
+                .help("Lock the crop window to this aspect ratio: its height follows its width, so zooms keep the shape"),
+        )
+        .arg(
+            Arg::new("keystone")
+                .allow_hyphen_values(true)
+                .long("keystone")
+                .value_name("AMOUNT")
+                .help("Vertical keystone correction: move the top corners apart by this fraction of the width (negative moves them together and needs a crop), straightening verticals shot looking up. Single value or comma-separated array. Applied before the crop"),
+        )
         .arg(
             Arg::new("offset-x")
                 .allow_hyphen_values(true)
@@ -447,6 +823,12 @@ fn render_args(cmd: Command) -> Command {
                 .num_args(0)
                 .help("Ignore the deflicker correction layer for this run (A/B comparison)"),
         )
+        .arg(
+            Arg::new("no-white-balance")
+                .long("no-white-balance")
+                .num_args(0)
+                .help("Ignore the white-balance compensation layer for this run (A/B comparison)"),
+        )
         .arg(
             Arg::new("offset-y")
                 .allow_hyphen_values(true)
@@ -474,6 +856,11 @@ fn build_project(matches: &ArgMatches) -> Result<Project> {
             analysis.deflicker = None;
         }
     }
+    if matches.get_flag("no-white-balance") {
+        if let Some(ref mut analysis) = project.analysis {
+            analysis.white_balance = None;
+        }
+    }
     Ok(project)
 }
 
@@ -498,10 +885,13 @@ fn build_project_inner(matches: &ArgMatches) -> Result<Project> {
                 version: PROJECT_VERSION,
                 input: PathBuf::from(input),
                 frame_range: None,
+                excluded_frames: Vec::new(),
                 interpolation: Default::default(),
                 color: ColorGrade::default(),
+                perspective: None,
                 crop: None,
                 export: ExportSettings::new(PathBuf::from(output)),
+                outputs: Vec::new(),
                 analysis: None,
             }
         }
@@ -537,6 +927,15 @@ fn build_project_inner(matches: &ArgMatches) -> Result<Project> {
     if is_explicit(matches, "resolution") {
         project.export.resolution = matches.get_one::<String>("resolution").cloned();
     }
+    if let Some(aspect) = matches.get_one::<String>("aspect") {
+        project.export.aspect = Some(aspect.parse::<Aspect>()?);
+    }
+    if let Some(framing) = matches.get_one::<String>("framing") {
+        project.export.framing = framing.parse::<Framing>()?;
+    }
+    if is_explicit(matches, "upscale") {
+        project.export.upscale = true;
+    }
     if overrides("codec") {
         project.export.codec = matches
             .get_one::<String>("codec")
@@ -546,13 +945,149 @@ fn build_project_inner(matches: &ArgMatches) -> Result<Project> {
     if is_explicit(matches, "ten-bit") {
         project.export.ten_bit = true;
     }
-    if is_explicit(matches, "motion-blur") {
-        project.export.motion_blur = Some(
-            matches
-                .get_one::<String>("motion-blur")
-                .unwrap()
-                .parse::<u32>()
-                .map_err(|_| LapsifyError::message("Invalid motion-blur value"))?,
+    for (name, unit) in [
+        ("motion-blur", BlurUnit::Frames),
+        ("shutter-angle", BlurUnit::ShutterAngle),
+    ] {
+        let Some(raw) = matches.get_one::<String>(name) else {
+            continue;
+        };
+        let values = parse_value_array(raw)?;
+        if values.is_empty() {
+            return Err(LapsifyError::message(format!(
+                "Empty value array for {name}"
+            )));
+        }
+        let total_frames = list_images(&project.input)?.len();
+        let weighting = project
+            .export
+            .motion_blur
+            .as_ref()
+            .map_or(BlurWeighting::Box, |blur| blur.weighting);
+        project.export.motion_blur = Some(MotionBlur {
+            window: curve_from_legacy_array(&values, total_frames),
+            unit,
+            weighting,
+        });
+    }
+    if let Some(kernel) = matches.get_one::<String>("blur-weighting") {
+        let weighting = kernel.parse::<BlurWeighting>()?;
+        match project.export.motion_blur.as_mut() {
+            Some(blur) => blur.weighting = weighting,
+            None => {
+                return Err(LapsifyError::message(
+                    "--blur-weighting needs motion blur (--motion-blur or --shutter-angle)",
+                ))
+            }
+        }
+    }
+    if let Some(value) = matches.get_one::<String>("retime") {
+        let seconds_per_frame = match value.as_str() {
+            "auto" => None,
+            seconds => Some(
+                seconds
+                    .parse::<f32>()
+                    .map_err(|_| LapsifyError::message("Invalid retime value"))?,
+            ),
+        };
+        let retime = project.export.retime.get_or_insert_with(Retime::default);
+        retime.seconds_per_frame = seconds_per_frame;
+    }
+    if is_explicit(matches, "retime-blend") {
+        project
+            .export
+            .retime
+            .get_or_insert_with(Retime::default)
+            .blend = true;
+    }
+    if let Some(mode) = matches.get_one::<String>("accumulate") {
+        let mode = mode.parse::<AccumulateMode>()?;
+        match project.export.accumulate.as_mut() {
+            Some(accumulate) => accumulate.mode = mode,
+            None => {
+                project.export.accumulate = Some(Accumulate {
+                    mode,
+                    window: None,
+                    decay: 0.0,
+                })
+            }
+        }
+    }
+    for name in ["accumulate-window", "trail-decay"] {
+        let Some(value) = matches.get_one::<String>(name) else {
+            continue;
+        };
+        let accumulate = project
+            .export
+            .accumulate
+            .as_mut()
+            .ok_or_else(|| LapsifyError::message(format!("--{name} needs --accumulate")))?;
+        let invalid = || LapsifyError::message(format!("Invalid {name} value"));
+        if name == "accumulate-window" {
+            accumulate.window = Some(value.parse::<u32>().map_err(|_| invalid())?);
+        } else {
+            accumulate.decay = value.parse::<f32>().map_err(|_| invalid())?;
+        }
+    }
+    if let Some(raw) = matches.get_one::<String>("keystone") {
+        let values = parse_value_array(raw)?;
+        let total_frames = list_images(&project.input)?.len();
+        let half = |sign: f32| {
+            let values: Vec<f32> = values.iter().map(|v| sign * v / 2.0).collect();
+            curve_from_legacy_array(&values, total_frames)
+        };
+        let perspective = project.perspective.get_or_insert_with(Perspective::default);
+        perspective.top_left.x = half(-1.0);
+        perspective.top_right.x = half(1.0);
+    }
+    if let Some(frames) = matches.get_one::<String>("stack") {
+        let frames = frames
+            .parse::<u32>()
+            .map_err(|_| LapsifyError::message("Invalid stack value"))?;
+        match project.export.stack.as_mut() {
+            Some(stack) => stack.frames = frames,
+            None => {
+                project.export.stack = Some(Stack {
+                    frames,
+                    mode: StackMode::default(),
+                    align: StackAlign::default(),
+                })
+            }
+        }
+    }
+    for name in ["stack-sliding", "stack-align"] {
+        if !is_explicit(matches, name) {
+            continue;
+        }
+        let stack = project
+            .export
+            .stack
+            .as_mut()
+            .ok_or_else(|| LapsifyError::message(format!("--{name} needs --stack")))?;
+        if name == "stack-align" {
+            stack.align = matches
+                .get_one::<String>(name)
+                .expect("--stack-align takes a value")
+                .parse()?;
+        } else {
+            stack.mode = StackMode::Sliding;
+        }
+    }
+    if let Some(raw) = matches.get_one::<String>("speed") {
+        let values = parse_value_array(raw)?;
+        if values.is_empty() {
+            return Err(LapsifyError::message("Empty value array for speed"));
+        }
+        let total_frames = list_images(&project.input)?.len();
+        project.export.speed = Some(curve_from_legacy_array(&values, total_frames));
+    }
+    if let Some(mode) = matches.get_one::<String>("frame-interpolation") {
+        project.export.frame_interpolation = mode.parse::<FrameInterpolation>()?;
+    }
+    if let Some(fps) = matches.get_one::<String>("output-fps") {
+        project.export.output_fps = Some(
+            fps.parse::<u32>()
+                .map_err(|_| LapsifyError::message("Invalid output-fps value"))?,
         );
     }
     if overrides("jpeg-quality") {
@@ -579,6 +1114,40 @@ fn build_project_inner(matches: &ArgMatches) -> Result<Project> {
         let end = end_frame.unwrap_or(image_count.saturating_sub(1));
         project.frame_range = Some((start, end));
     }
+    if let Some(scene) = matches.get_one::<String>("scene") {
+        let index = scene
+            .parse::<usize>()
+            .ok()
+            .filter(|&n| n > 0)
+            .ok_or_else(|| LapsifyError::message("Invalid scene value"))?;
+        let scenes = project
+            .analysis
+            .as_ref()
+            .and_then(|a| a.scenes.as_ref())
+            .ok_or_else(|| {
+                LapsifyError::message("--scene needs scenes; run `lapsify analyze scenes` first")
+            })?;
+        if scenes.source_fingerprint
+            != crate::analysis::source_fingerprint(&list_images(&project.input)?)?
+        {
+            return Err(LapsifyError::message(
+                "The source folder changed since `analyze scenes`; run it again before rendering a scene",
+            ));
+        }
+        let scene = scenes.scenes.get(index - 1).ok_or_else(|| {
+            LapsifyError::message(format!(
+                "Scene {index} does not exist (the project has {})",
+                scenes.scenes.len()
+            ))
+        })?;
+        project.frame_range = Some((scene.start as usize, scene.end as usize));
+    }
+    if let Some(list) = matches.get_one::<String>("exclude") {
+        project.excluded_frames = parse_frame_list(list)?;
+    }
+    if is_explicit(matches, "fill-excluded") {
+        project.export.fill_excluded = true;
+    }
 
     // Legacy comma-array flags are anchored to evenly spaced keyframes over
     // the full sequence, so conversion needs the frame count.
@@ -661,6 +1230,17 @@ fn build_project_inner(matches: &ArgMatches) -> Result<Project> {
         }
     }
 
+    if let Some(aspect) = matches.get_one::<String>("crop-aspect") {
+        let aspect = aspect.parse::<Aspect>()?;
+        project
+            .crop
+            .as_mut()
+            .ok_or_else(|| {
+                LapsifyError::message("--crop-aspect needs a crop (--crop or the project's)")
+            })?
+            .aspect = Some(aspect);
+    }
+
     Ok(project)
 }
 
@@ -675,6 +1255,9 @@ fn print_curve(name: &str, curve: &Curve, unit: &str) {
                 .join(", ");
             eprintln!("  {}: [{}]", name.green(), values_str);
         }
+        Curve::Expression(expression) => {
+            eprintln!("  {}: {}{}", name.green(), expression.source(), unit)
+        }
     }
 }
 
@@ -696,6 +1279,8 @@ pub fn run() -> Result<()> {
                 );
                 Ok(())
             }
+            Some(("rebase", rebase)) => run_project_rebase(rebase),
+            Some(("split", split)) => run_project_split(split),
             _ => unreachable!("subcommand_required"),
         },
         Some(("deflicker", sub)) => run_deflicker_cmd(sub),
@@ -705,11 +1290,17 @@ pub fn run() -> Result<()> {
         },
         Some(("curves", sub)) => match sub.subcommand() {
             Some(("dump", dump)) => run_curves_dump(dump),
+            Some(("bake", bake)) => run_curves_bake(bake),
             _ => unreachable!("subcommand_required"),
         },
         Some(("analyze", sub)) => match sub.subcommand() {
             Some(("luminance", lum)) => run_analyze_luminance(lum),
             Some(("holygrail", hg)) => run_analyze_holygrail(hg),
+            Some(("whitebalance", wb)) => run_analyze_white_balance(wb),
+            Some(("outliers", out)) => run_analyze_outliers(out),
+            Some(("scenes", sc)) => run_analyze_scenes(sc),
+            Some(("stabilize", st)) => run_analyze_stabilize(st),
+            Some(("intervals", iv)) => run_analyze_intervals(iv),
             _ => unreachable!("subcommand_required"),
         },
         Some(_) => unreachable!("unknown subcommand"),
@@ -763,6 +1354,7 @@ fn run_analyze_luminance(matches: &ArgMatches) -> Result<()> {
     let written = match (&project_path, matches.get_flag("no-write")) {
         (Some(path), false) => {
             let analysis = project.analysis.get_or_insert_with(Analysis::default);
+            analysis.record_frames(&image_files)?;
             if opts.developed {
                 analysis.developed_luminance = Some(series);
             } else {
@@ -799,11 +1391,11 @@ fn run_curves_dump(matches: &ArgMatches) -> Result<()> {
     project.validate()?;
 
     let n = list_images(&project.input)?.len();
-    let timeline = Timeline::of(&project);
+    let timeline = Timeline::of(&project, n);
     let analysis = project.analysis.as_ref();
 
     let user_exposure: Vec<f32> = (0..n as u32)
-        .map(|f| project.color.exposure.sample_mapped(f, |x| timeline.x(x)))
+        .map(|f| project.color.exposure.sample_on(f, &timeline))
         .collect();
     let holy_grail: Option<Vec<f32>> = analysis
         .and_then(|a| a.holy_grail.as_ref())
@@ -827,7 +1419,17 @@ fn run_curves_dump(matches: &ArgMatches) -> Result<()> {
             "developed_luminance": analysis.and_then(|a| a.developed_luminance.as_ref()).map(|s| &s.values),
             "deflicker_target": analysis.and_then(|a| a.deflicker.as_ref()).map(|d| &d.target),
             "holy_grail_ev": holy_grail,
+            "holy_grail_fit_residual_ev": analysis
+                .and_then(|a| a.holy_grail.as_ref())
+                .map(|hg| &hg.fit_residuals)
+                .filter(|r| !r.is_empty()),
             "deflicker_ev": deflicker,
+            "white_balance_temperature": analysis
+                .and_then(|a| a.white_balance.as_ref())
+                .map(|wb| (0..n as u32).map(|f| wb.temperature_on(f, &timeline)).collect::<Vec<_>>()),
+            "white_balance_tint": analysis
+                .and_then(|a| a.white_balance.as_ref())
+                .map(|wb| (0..n as u32).map(|f| wb.tint_on(f, &timeline)).collect::<Vec<_>>()),
             "user_exposure_ev": user_exposure,
             "effective_exposure_ev": effective,
         },
@@ -841,45 +1443,199 @@ fn run_curves_dump(matches: &ArgMatches) -> Result<()> {
     Ok(())
 }
 
-fn run_keyframes_suggest(matches: &ArgMatches) -> Result<()> {
-    use crate::analysis::keyframes::{suggest_keyframes, SuggestOptions};
-    use crate::curve::Keyframe;
-    use crate::progress::ProgressEvent;
+/// Bake machine layers into the user curves they add onto: the new curve
+/// samples (within tolerance) to the user curve plus the layer, so with
+/// --clear the render stays the same while the layer becomes editable.
+fn run_curves_bake(matches: &ArgMatches) -> Result<()> {
+    use crate::curve::bake_samples;
+    use crate::timeline::Timeline;
 
     let mut project = build_project(matches)?;
     project.validate()?;
 
-    let source_luma = project
-        .analysis
-        .as_ref()
-        .and_then(|a| a.source_luminance.as_ref())
-        .map(|s| s.values.clone())
-        .ok_or_else(|| {
-            LapsifyError::message(
-                "Keyframe suggestion needs source luminance; run `lapsify analyze luminance --project <FILE>` first",
-            )
-        })?;
-    let holy_grail = project.analysis.as_ref().and_then(|a| a.holy_grail.clone());
-
-    let opts = SuggestOptions {
-        count: matches
-            .get_one::<String>("count")
-            .map(|s| s.parse::<usize>())
-            .transpose()
-            .map_err(|_| LapsifyError::message("Invalid count value"))?,
-        density: matches
-            .get_one::<String>("density")
-            .unwrap()
+    let layer = matches.get_one::<String>("layer").unwrap().as_str();
+    let (holy_grail, deflicker, white_balance) = match layer {
+        "holy-grail" => (true, false, false),
+        "deflicker" => (false, true, false),
+        "effective" => (true, true, false),
+        "white-balance" => (false, false, true),
+        other => {
+            return Err(LapsifyError::message(format!(
+                "Invalid layer '{other}' (expected 'holy-grail', 'deflicker', 'effective' or 'white-balance')"
+            )))
+        }
+    };
+    let tolerance = match matches.get_one::<String>("tolerance") {
+        Some(s) => s
             .parse::<f32>()
-            .map_err(|_| LapsifyError::message("Invalid density value"))?,
+            .ok()
+            .filter(|t| *t > 0.0)
+            .ok_or_else(|| LapsifyError::message("Invalid tolerance value"))?,
+        None if white_balance => 0.5,
+        None => 0.02,
     };
 
-    let reporter = match matches.get_one::<String>("progress").unwrap().as_str() {
+    let n = list_images(&project.input)?.len();
+    let analysis = project.analysis.clone().unwrap_or_default();
+    let missing = |name: &str, command: &str| {
+        LapsifyError::message(format!(
+            "The project has no {name} layer; run `lapsify {command} --project <FILE>` first"
+        ))
+    };
+    let hg = match (holy_grail, &analysis.holy_grail) {
+        (true, None) if layer != "effective" => {
+            return Err(missing("holy grail", "analyze holygrail"))
+        }
+        (true, hg) => hg.as_ref(),
+        (false, _) => None,
+    };
+    let df = match (deflicker, &analysis.deflicker) {
+        (true, None) if layer != "effective" => return Err(missing("deflicker", "deflicker")),
+        (true, df) => df.as_ref(),
+        (false, _) => None,
+    };
+    let wb = match (white_balance, &analysis.white_balance) {
+        (true, None) => return Err(missing("white balance", "analyze whitebalance")),
+        (true, wb) => wb.as_ref(),
+        (false, _) => None,
+    };
+
+    let timeline = Timeline::of(&project, n);
+    let sampled = |curve: &Curve, layer: &dyn Fn(usize) -> f32| -> Vec<f32> {
+        (0..n)
+            .map(|f| curve.sample_on(f as u32, &timeline) + layer(f))
+            .collect()
+    };
+    let bake = |values: &[f32]| {
+        let curve = bake_samples(values, tolerance, |x| timeline.x(x));
+        let max_error = values
+            .iter()
+            .enumerate()
+            .map(|(f, v)| (curve.sample_on(f as u32, &timeline) - v).abs())
+            .fold(0.0f32, f32::max);
+        (curve, max_error)
+    };
+
+    let mut baked: Vec<(&str, Curve, f32)> = Vec::new();
+    if let Some(wb) = wb {
+        let (curve, error) = bake(&sampled(&project.color.temperature, &|f| {
+            wb.temperature_on(f as u32, &timeline)
+        }));
+        baked.push(("temperature", curve, error));
+        let (curve, error) = bake(&sampled(&project.color.tint, &|f| {
+            wb.tint_on(f as u32, &timeline)
+        }));
+        baked.push(("tint", curve, error));
+    } else {
+        let (curve, error) = bake(&sampled(&project.color.exposure, &|f| {
+            hg.map_or(0.0, |hg| hg.effective(f)) + df.map_or(0.0, |d| d.offset(f))
+        }));
+        baked.push(("exposure", curve, error));
+    }
+
+    let mut document = serde_json::Map::new();
+    for (name, curve, max_error) in &baked {
+        document.insert(
+            name.to_string(),
+            serde_json::json!({ "curve": curve, "max_error": max_error }),
+        );
+    }
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&document)
+            .map_err(|e| LapsifyError::message(format!("Failed to serialize curves: {e}")))?
+    );
+
+    if matches.get_flag("clear") {
+        let project_path = matches.get_one::<String>("project").ok_or_else(|| {
+            LapsifyError::message("--clear writes into the project file; pass --project <FILE>")
+        })?;
+        for (name, curve, _) in baked {
+            match name {
+                "temperature" => project.color.temperature = curve,
+                "tint" => project.color.tint = curve,
+                _ => project.color.exposure = curve,
+            }
+        }
+        if let Some(ref mut analysis) = project.analysis {
+            if holy_grail {
+                analysis.holy_grail = None;
+            }
+            if deflicker {
+                analysis.deflicker = None;
+            }
+            if white_balance {
+                analysis.white_balance = None;
+            }
+        }
+        // Layers may go beyond the user slider ranges (a day-to-night holy
+        // grail easily spans 10 EV); such a bake must not be written.
+        project.validate().map_err(|e| {
+            LapsifyError::message(format!(
+                "The baked curves are not a valid project ({e}); keep the layer instead"
+            ))
+        })?;
+        project.save_atomic(Path::new(project_path))?;
+        eprintln!("Baked {layer} into {project_path}");
+    }
+    Ok(())
+}
+
+fn run_keyframes_suggest(matches: &ArgMatches) -> Result<()> {
+    use crate::analysis::keyframes::{
+        insert_handles, suggest_color_keyframes, suggest_keyframes, SuggestOptions, SuggestParam,
+    };
+    use crate::progress::ProgressEvent;
+
+    let mut project = build_project(matches)?;
+    project.validate()?;
+
+    let param = SuggestParam::parse(matches.get_one::<String>("param").unwrap())?;
+    let analysis = project.analysis.as_ref();
+    let source = analysis
+        .and_then(|a| a.source_luminance.as_ref())
+        .ok_or_else(|| {
+            LapsifyError::message(
+                "Keyframe suggestion needs source luminance; run `lapsify analyze luminance --project <FILE>` first",
+            )
+        })?;
+    if param != SuggestParam::Exposure && source.chromaticity.is_empty() {
+        return Err(LapsifyError::message(
+            "The stored source luminance has no chromaticity; re-run `lapsify analyze luminance --project <FILE>`",
+        ));
+    }
+
+    let opts = SuggestOptions {
+        count: matches
+            .get_one::<String>("count")
+            .map(|s| s.parse::<usize>())
+            .transpose()
+            .map_err(|_| LapsifyError::message("Invalid count value"))?,
+        density: matches
+            .get_one::<String>("density")
+            .unwrap()
+            .parse::<f32>()
+            .map_err(|_| LapsifyError::message("Invalid density value"))?,
+    };
+
+    let reporter = match matches.get_one::<String>("progress").unwrap().as_str() {
         "json" => ProgressReporter::json(),
         _ => ProgressReporter::human(),
     };
 
-    let frames = suggest_keyframes(&source_luma, holy_grail.as_ref(), &opts)?;
+    let frames = match param {
+        SuggestParam::Exposure => suggest_keyframes(
+            &source.values,
+            analysis.and_then(|a| a.holy_grail.as_ref()),
+            &opts,
+        )?,
+        _ => suggest_color_keyframes(
+            &source.chromaticity,
+            analysis.and_then(|a| a.white_balance.as_ref()),
+            param,
+            &opts,
+        )?,
+    };
     reporter.report(ProgressEvent::KeyframeSuggestion {
         frames: frames.clone(),
     });
@@ -889,25 +1645,7 @@ fn run_keyframes_suggest(matches: &ArgMatches) -> Result<()> {
             LapsifyError::message("--apply writes into the project file; pass --project <FILE>")
         })?;
 
-        // Insert keyframes at the curve's current sampled values: the render
-        // is unchanged, but an editor now has handles at the right places.
-        // Existing keyframes within 2 frames of a suggestion are kept as-is.
-        let existing: Vec<u32> = match &project.color.exposure {
-            Curve::Keyframed(kfs) => kfs.iter().map(|k| k.frame).collect(),
-            Curve::Constant(_) => Vec::new(),
-        };
-        let mut keyframes: Vec<Keyframe> = match &project.color.exposure {
-            Curve::Keyframed(kfs) => kfs.clone(),
-            Curve::Constant(_) => Vec::new(),
-        };
-        for &frame in &frames {
-            if existing.iter().any(|&e| e.abs_diff(frame) <= 2) {
-                continue;
-            }
-            keyframes.push(Keyframe::new(frame, project.color.exposure.sample(frame)));
-        }
-        keyframes.sort_by_key(|k| k.frame);
-        project.color.exposure = Curve::Keyframed(keyframes);
+        insert_handles(param.curve_mut(&mut project.color), &frames)?;
 
         project.save_atomic(Path::new(project_path))?;
         reporter.report(ProgressEvent::Done {
@@ -1005,6 +1743,7 @@ fn run_deflicker_cmd(matches: &ArgMatches) -> Result<()> {
     }
 
     let analysis = project.analysis.get_or_insert_with(Analysis::default);
+    analysis.record_frames(&image_files)?;
     analysis.deflicker = Some(layer);
     project.save_atomic(&project_path)?;
 
@@ -1016,13 +1755,65 @@ fn run_deflicker_cmd(matches: &ArgMatches) -> Result<()> {
 }
 
 fn run_analyze_holygrail(matches: &ArgMatches) -> Result<()> {
-    use crate::analysis::holygrail::{compute_holy_grail, HolyGrailOptions};
+    use crate::analysis::holygrail::{
+        compute_holy_grail, parse_ev_event, HolyGrailOptions, RotateFit,
+    };
     use crate::analysis::Analysis;
     use crate::progress::ProgressEvent;
 
     let mut project = build_project(matches)?;
     project.validate()?;
 
+    // Manual events are user input rather than derived data: a re-run
+    // without --ev-event keeps the ones stored in the existing layer.
+    let events = match matches.get_many::<String>("ev-event") {
+        Some(values) => values
+            .map(|s| parse_ev_event(s))
+            .collect::<Result<Vec<_>>>()?,
+        None => project
+            .analysis
+            .as_ref()
+            .and_then(|a| a.holy_grail.as_ref())
+            .map(|hg| hg.ev_events.clone())
+            .unwrap_or_default(),
+    };
+
+    let fit = match matches.get_one::<String>("fit").unwrap().as_str() {
+        "end" => RotateFit::End,
+        "luminance" => {
+            let series = project
+                .analysis
+                .as_ref()
+                .and_then(|a| a.source_luminance.as_ref())
+                .ok_or_else(|| {
+                    LapsifyError::message(
+                        "--fit luminance needs source luminance; run `lapsify analyze luminance --project <FILE>` first",
+                    )
+                })?;
+            let trend_frames = match matches.get_one::<String>("trend") {
+                Some(s) => s
+                    .parse::<u32>()
+                    .map_err(|_| LapsifyError::message("Invalid trend value"))?,
+                None => (series.values.len() as u32 / 4).max(1),
+            };
+            RotateFit::Luminance {
+                source_luma: series.values.clone(),
+                trend_frames,
+            }
+        }
+        other => {
+            return Err(LapsifyError::message(format!(
+                "Invalid fit mode '{other}' (expected 'end' or 'luminance')"
+            )))
+        }
+    };
+    // A luminance fit solves stretch too, so the flag's default must not
+    // pin it; an explicit --stretch still wins.
+    let stretch_given = match fit {
+        RotateFit::End => true,
+        RotateFit::Luminance { .. } => is_explicit(matches, "stretch"),
+    };
+
     let opts = HolyGrailOptions {
         rotate: matches
             .get_one::<String>("rotate")
@@ -1031,9 +1822,13 @@ fn run_analyze_holygrail(matches: &ArgMatches) -> Result<()> {
             .map_err(|_| LapsifyError::message("Invalid rotate value"))?,
         stretch: matches
             .get_one::<String>("stretch")
+            .filter(|_| stretch_given)
             .map(|s| s.parse::<f32>())
             .transpose()
             .map_err(|_| LapsifyError::message("Invalid stretch value"))?,
+        fit,
+        events,
+        exposure_bias: matches.get_flag("exposure-bias"),
     };
 
     let reporter = match matches.get_one::<String>("progress").unwrap().as_str() {
@@ -1064,9 +1859,11 @@ fn run_analyze_holygrail(matches: &ArgMatches) -> Result<()> {
     let written = match (&project_path, matches.get_flag("no-write")) {
         (Some(path), false) => {
             let analysis = project.analysis.get_or_insert_with(Analysis::default);
+            analysis.record_frames(&image_files)?;
             analysis.holy_grail = Some(layer);
             if capture_times.is_some() {
                 analysis.capture_times_ms = capture_times;
+                project.resolve_anchors();
             }
             project.save_atomic(path)?;
             path.clone()
@@ -1089,6 +1886,381 @@ fn run_analyze_holygrail(matches: &ArgMatches) -> Result<()> {
     Ok(())
 }
 
+fn run_analyze_white_balance(matches: &ArgMatches) -> Result<()> {
+    use crate::analysis::whitebalance::{compute_white_balance, WhiteBalanceOptions};
+    use crate::analysis::Analysis;
+    use crate::progress::ProgressEvent;
+
+    let mut project = build_project(matches)?;
+    project.validate()?;
+
+    let opts = WhiteBalanceOptions {
+        smoothing_frames: matches
+            .get_one::<String>("smoothing")
+            .unwrap()
+            .parse::<u32>()
+            .map_err(|_| LapsifyError::message("Invalid smoothing value"))?,
+        excluded_frames: project.excluded_frames.clone(),
+    };
+
+    let reporter = match matches.get_one::<String>("progress").unwrap().as_str() {
+        "json" => ProgressReporter::json(),
+        _ => ProgressReporter::human(),
+    };
+
+    let image_files = list_images(&project.input)?;
+    reporter.report(ProgressEvent::Start {
+        total_frames: image_files.len(),
+        width: 0,
+        height: 0,
+    });
+
+    let start = Instant::now();
+    let mut layer = compute_white_balance(&image_files, &opts, &reporter)?;
+    // The strength is the user's: keep it across re-runs.
+    if let Some(previous) = project
+        .analysis
+        .as_ref()
+        .and_then(|a| a.white_balance.as_ref())
+    {
+        layer.strength = previous.strength.clone();
+    }
+
+    if !layer.frames_missing_wb.is_empty() {
+        reporter.report(ProgressEvent::Warning {
+            message: format!(
+                "{} frame(s) had no as-shot white balance; their compensation was carried forward",
+                layer.frames_missing_wb.len()
+            ),
+        });
+    }
+
+    let project_path = matches.get_one::<String>("project").map(PathBuf::from);
+    let written = match (&project_path, matches.get_flag("no-write")) {
+        (Some(path), false) => {
+            let analysis = project.analysis.get_or_insert_with(Analysis::default);
+            analysis.record_frames(&image_files)?;
+            analysis.white_balance = Some(layer);
+            project.save_atomic(path)?;
+            path.clone()
+        }
+        _ => {
+            if project_path.is_none() {
+                reporter.report(ProgressEvent::Warning {
+                    message: "no project file given; results were not persisted (use --project)"
+                        .to_string(),
+                });
+            }
+            PathBuf::new()
+        }
+    };
+
+    reporter.report(ProgressEvent::Done {
+        output: written,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    });
+    Ok(())
+}
+
+fn run_analyze_outliers(matches: &ArgMatches) -> Result<()> {
+    use crate::analysis::outliers::{detect_outliers, OutlierOptions};
+    use crate::analysis::Analysis;
+    use crate::progress::ProgressEvent;
+
+    let mut project = build_project(matches)?;
+    project.validate()?;
+
+    let parse = |name: &str| {
+        matches
+            .get_one::<String>(name)
+            .unwrap()
+            .parse::<f32>()
+            .map_err(|_| LapsifyError::message(format!("Invalid {name} value")))
+    };
+    let opts = OutlierOptions {
+        spike_ev: parse("spike-ev")?,
+        blur_ratio: parse("blur-ratio")?,
+        max_shift: parse("max-shift")?,
+        measure_dim: matches
+            .get_one::<String>("measure-dim")
+            .unwrap()
+            .parse::<u32>()
+            .map_err(|_| LapsifyError::message("Invalid measure-dim value"))?,
+    };
+
+    let reporter = match matches.get_one::<String>("progress").unwrap().as_str() {
+        "json" => ProgressReporter::json(),
+        _ => ProgressReporter::human(),
+    };
+
+    let image_files = list_images(&project.input)?;
+    reporter.report(ProgressEvent::Start {
+        total_frames: image_files.len(),
+        width: 0,
+        height: 0,
+    });
+
+    let start = Instant::now();
+    let layer = detect_outliers(&project, &image_files, &opts, &reporter)?;
+    reporter.report(ProgressEvent::Outliers {
+        flags: layer.flags.clone(),
+    });
+
+    let project_path = matches.get_one::<String>("project").map(PathBuf::from);
+    let written = match (&project_path, matches.get_flag("no-write")) {
+        (Some(path), false) => {
+            if matches.get_flag("apply") {
+                project.exclude_frames(&layer.frames());
+            }
+            let analysis = project.analysis.get_or_insert_with(Analysis::default);
+            analysis.record_frames(&image_files)?;
+            analysis.outliers = Some(layer);
+            project.save_atomic(path)?;
+            path.clone()
+        }
+        _ => {
+            if project_path.is_none() {
+                reporter.report(ProgressEvent::Warning {
+                    message: "no project file given; results were not persisted (use --project)"
+                        .to_string(),
+                });
+            }
+            PathBuf::new()
+        }
+    };
+
+    reporter.report(ProgressEvent::Done {
+        output: written,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    });
+    Ok(())
+}
+
+fn run_analyze_intervals(matches: &ArgMatches) -> Result<()> {
+    use crate::analysis::intervals::{capture_interval_report, IntervalOptions};
+    use crate::progress::ProgressEvent;
+
+    let project = build_project(matches)?;
+    let opts = IntervalOptions {
+        gap_factor: matches
+            .get_one::<String>("gap-factor")
+            .unwrap()
+            .parse::<f32>()
+            .ok()
+            .filter(|f| *f > 1.0)
+            .ok_or_else(|| LapsifyError::message("Invalid gap-factor value (must exceed 1)"))?,
+    };
+
+    let reporter = match matches.get_one::<String>("progress").unwrap().as_str() {
+        "json" => ProgressReporter::json(),
+        _ => ProgressReporter::human(),
+    };
+
+    let image_files = list_images(&project.input)?;
+    reporter.report(ProgressEvent::Start {
+        total_frames: image_files.len(),
+        width: 0,
+        height: 0,
+    });
+
+    let start = Instant::now();
+    let report = capture_interval_report(&image_files, &opts, &reporter);
+    reporter.report(ProgressEvent::Intervals { report });
+    reporter.report(ProgressEvent::Done {
+        output: PathBuf::new(),
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    });
+    Ok(())
+}
+
+fn run_analyze_scenes(matches: &ArgMatches) -> Result<()> {
+    use crate::analysis::scenes::{detect_scenes, SceneOptions};
+    use crate::analysis::Analysis;
+    use crate::progress::ProgressEvent;
+
+    let mut project = build_project(matches)?;
+    project.validate()?;
+
+    let parse = |name: &str| {
+        matches
+            .get_one::<String>(name)
+            .unwrap()
+            .parse::<f32>()
+            .map_err(|_| LapsifyError::message(format!("Invalid {name} value")))
+    };
+    let opts = SceneOptions {
+        min_gap_s: parse("min-gap")?,
+        cut_threshold: parse("cut-threshold")?,
+        measure_dim: matches
+            .get_one::<String>("measure-dim")
+            .unwrap()
+            .parse::<u32>()
+            .map_err(|_| LapsifyError::message("Invalid measure-dim value"))?,
+    };
+
+    let reporter = match matches.get_one::<String>("progress").unwrap().as_str() {
+        "json" => ProgressReporter::json(),
+        _ => ProgressReporter::human(),
+    };
+
+    let image_files = list_images(&project.input)?;
+    reporter.report(ProgressEvent::Start {
+        total_frames: image_files.len(),
+        width: 0,
+        height: 0,
+    });
+
+    let start = Instant::now();
+    let layer = detect_scenes(&project, &image_files, &opts, &reporter)?;
+    reporter.report(ProgressEvent::Scenes {
+        scenes: layer.scenes.clone(),
+    });
+
+    let project_path = matches.get_one::<String>("project").map(PathBuf::from);
+    let written = match (&project_path, matches.get_flag("no-write")) {
+        (Some(path), false) => {
+            let analysis = project.analysis.get_or_insert_with(Analysis::default);
+            analysis.record_frames(&image_files)?;
+            analysis.scenes = Some(layer);
+            project.save_atomic(path)?;
+            path.clone()
+        }
+        _ => {
+            if project_path.is_none() {
+                reporter.report(ProgressEvent::Warning {
+                    message: "no project file given; results were not persisted (use --project)"
+                        .to_string(),
+                });
+            }
+            PathBuf::new()
+        }
+    };
+
+    reporter.report(ProgressEvent::Done {
+        output: written,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    });
+    Ok(())
+}
+
+fn run_analyze_stabilize(matches: &ArgMatches) -> Result<()> {
+    use crate::analysis::stabilize::{stabilize, StabilizeOptions};
+    use crate::analysis::Analysis;
+    use crate::crop::CropTrack;
+    use crate::progress::ProgressEvent;
+
+    let project_path = matches
+        .get_one::<String>("project")
+        .map(PathBuf::from)
+        .ok_or_else(|| {
+            LapsifyError::message(
+                "stabilize writes its corrections into the project's crop; pass --project <FILE>",
+            )
+        })?;
+
+    // Load the file directly (not via flag overrides) so what is written
+    // back is exactly the stored project plus the stabilised crop.
+    let mut project = Project::from_json_file(&project_path)?;
+    project.validate()?;
+
+    let reporter = match matches.get_one::<String>("progress").unwrap().as_str() {
+        "json" => ProgressReporter::json(),
+        _ => ProgressReporter::human(),
+    };
+
+    if matches.get_flag("reset") {
+        if let Some(layer) = project.analysis.as_mut().and_then(|a| a.stabilize.take()) {
+            project.crop = layer.base.map(CropTrack::from_rect);
+        }
+        project.save_atomic(&project_path)?;
+        reporter.report(ProgressEvent::Done {
+            output: project_path,
+            elapsed_ms: 0,
+        });
+        return Ok(());
+    }
+
+    let opts = StabilizeOptions {
+        smoothing_frames: matches
+            .get_one::<String>("smoothing")
+            .unwrap()
+            .parse::<u32>()
+            .map_err(|_| LapsifyError::message("Invalid smoothing value"))?,
+        rotation: matches.get_flag("rotation"),
+        scale: matches.get_flag("scale"),
+        measure_dim: matches
+            .get_one::<String>("measure-dim")
+            .unwrap()
+            .parse::<u32>()
+            .map_err(|_| LapsifyError::message("Invalid measure-dim value"))?,
+    };
+
+    let image_files = list_images(&project.input)?;
+    let (width, height) = scan_dimensions(&image_files)?;
+    reporter.report(ProgressEvent::Start {
+        total_frames: image_files.len(),
+        width,
+        height,
+    });
+
+    let start = Instant::now();
+    let layer = stabilize(&project, &image_files, &opts, &reporter)?;
+    reporter.report(ProgressEvent::Stabilized {
+        zoom: layer.zoom,
+        max_shift: layer.max_shift(),
+    });
+
+    let written = if matches.get_flag("no-write") {
+        PathBuf::new()
+    } else {
+        let crop = layer.crop_track();
+        crop.validate_over(
+            image_files.len(),
+            width,
+            height,
+            project.perspective.as_ref(),
+        )?;
+        project.crop = Some(crop);
+        let analysis = project.analysis.get_or_insert_with(Analysis::default);
+        analysis.record_frames(&image_files)?;
+        analysis.stabilize = Some(layer);
+        project.save_atomic(&project_path)?;
+        project_path
+    };
+
+    reporter.report(ProgressEvent::Done {
+        output: written,
+        elapsed_ms: start.elapsed().as_millis() as u64,
+    });
+    Ok(())
+}
+
+/// Parse "12,40-45" into a sorted, deduplicated frame list.
+fn parse_frame_list(input: &str) -> Result<Vec<u32>> {
+    let invalid = || {
+        LapsifyError::message(format!(
+            "Invalid frame list '{input}' (expected e.g. 12,40-45)"
+        ))
+    };
+    let mut frames = Vec::new();
+    for part in input.split(',').map(str::trim).filter(|p| !p.is_empty()) {
+        match part.split_once('-') {
+            Some((a, b)) => {
+                let a: u32 = a.trim().parse().map_err(|_| invalid())?;
+                let b: u32 = b.trim().parse().map_err(|_| invalid())?;
+                if a > b {
+                    return Err(invalid());
+                }
+                frames.extend(a..=b);
+            }
+            None => frames.push(part.parse().map_err(|_| invalid())?),
+        }
+    }
+    frames.sort_unstable();
+    frames.dedup();
+    Ok(frames)
+}
+
 fn run_project_dump(matches: &ArgMatches) -> Result<()> {
     let project = build_project(matches)?;
     project.validate()?;
@@ -1096,6 +2268,193 @@ fn run_project_dump(matches: &ArgMatches) -> Result<()> {
     Ok(())
 }
 
+fn run_project_rebase(matches: &ArgMatches) -> Result<()> {
+    use crate::analysis::Analysis;
+    use crate::rebase::rebase;
+
+    let project_path = PathBuf::from(matches.get_one::<String>("project").unwrap());
+    let mut project = Project::from_json_file(&project_path)?;
+    let image_files = list_images(&project.input)?;
+    let dry_run = matches.get_flag("dry-run");
+
+    let recorded = project
+        .analysis
+        .as_ref()
+        .is_some_and(|a| a.frames.is_some());
+    if !recorded {
+        // Nothing to remap from yet: key the project to the folder as it is,
+        // so the next change can be rebased.
+        if !dry_run {
+            project
+                .analysis
+                .get_or_insert_with(Analysis::default)
+                .record_frames(&image_files)?;
+            project.save_atomic(&project_path)?;
+        }
+        eprintln!(
+            "No frame list was recorded; {} {} frame(s) as the baseline",
+            if dry_run { "would record" } else { "recorded" },
+            image_files.len()
+        );
+        return Ok(());
+    }
+
+    let report = rebase(&mut project, &image_files)?;
+    if report.is_unchanged() {
+        eprintln!("Source folder unchanged; nothing to rebase");
+        return Ok(());
+    }
+    eprintln!(
+        "{} {} kept ({} renamed), {} deleted, {} inserted",
+        "Frames:".green(),
+        report.kept,
+        report.renamed,
+        report.deleted,
+        report.inserted.len()
+    );
+    if report.merged_keyframes > 0 {
+        eprintln!(
+            "{} {} keyframe(s) on deleted frames landed on an existing keyframe and were dropped",
+            "Warning:".yellow(),
+            report.merged_keyframes
+        );
+    }
+    if !report.inserted.is_empty() {
+        eprintln!(
+            "{} analysis values for inserted frames are interpolated; re-run the analysis for measured ones",
+            "Note:".yellow()
+        );
+    }
+    project.validate()?;
+    if dry_run {
+        return Ok(());
+    }
+    project.save_atomic(&project_path)?;
+    eprintln!("Rebased {}", project_path.display());
+    Ok(())
+}
+
+fn run_project_split(matches: &ArgMatches) -> Result<()> {
+    use crate::analysis::{source_fingerprint, Analysis};
+    use crate::rebase::extract_scene;
+
+    let project_path = PathBuf::from(matches.get_one::<String>("project").unwrap());
+    let mut project = Project::from_json_file(&project_path)?;
+    let image_files = list_images(&project.input)?;
+    let dry_run = matches.get_flag("dry-run");
+
+    let scenes = project
+        .analysis
+        .as_ref()
+        .and_then(|a| a.scenes.clone())
+        .ok_or_else(|| {
+            LapsifyError::message("The project has no scenes; run `lapsify analyze scenes` first")
+        })?;
+    if scenes.source_fingerprint != source_fingerprint(&image_files)? {
+        return Err(LapsifyError::message(
+            "The source folder changed since `analyze scenes`; run it again before splitting",
+        ));
+    }
+    if scenes.scenes.len() < 2 {
+        eprintln!("The folder holds a single scene; nothing to split");
+        return Ok(());
+    }
+    let analysis = project.analysis.get_or_insert_with(Analysis::default);
+    if analysis.frames.is_none() {
+        analysis.record_frames(&image_files)?;
+    }
+
+    // Build and check every scene project before touching any file, so a
+    // failure leaves the folder as it was.
+    let stem = project_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "project".to_string());
+    let mut plan = Vec::new();
+    for (i, scene) in scenes.scenes.iter().enumerate() {
+        let suffix = format!("scene-{:02}", i + 1);
+        let folder = project.input.join(&suffix);
+        if folder.exists() {
+            return Err(LapsifyError::message(format!(
+                "{} already exists; move it away before splitting",
+                folder.display()
+            )));
+        }
+        let files = &image_files[scene.frames()];
+        let mut scene_project = extract_scene(&project, scene, files)?;
+        scene_project.input = folder.clone();
+        let scene_folder = |output: &Path| {
+            let extension = output
+                .extension()
+                .map(|e| format!(".{}", e.to_string_lossy()))
+                .unwrap_or_default();
+            let output_stem = output
+                .file_stem()
+                .map(|s| s.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            output.with_file_name(format!("{output_stem}-{suffix}{extension}"))
+        };
+        scene_project.export.output = scene_folder(&project.export.output);
+        for output in &mut scene_project.outputs {
+            output.export.output = scene_folder(&output.export.output);
+        }
+        scene_project.validate()?;
+        let scene_path = project_path.with_file_name(format!("{stem}-{suffix}.json"));
+        eprintln!(
+            "{} frames {}-{} -> {} ({})",
+            format!("Scene {}:", i + 1).green(),
+            scene.start,
+            scene.end,
+            folder.display(),
+            scene_path.display()
+        );
+        plan.push((files, folder, scene_path, scene_project));
+    }
+    if dry_run {
+        return Ok(());
+    }
+
+    // Everything done so far is undone when a step fails, so a failed
+    // split also leaves the folder as it was.
+    let mut moved: Vec<(PathBuf, PathBuf)> = Vec::new();
+    let mut created: Vec<PathBuf> = Vec::new();
+    let mut saved: Vec<PathBuf> = Vec::new();
+    let mut split = || -> Result<()> {
+        for (files, folder, _, _) in &plan {
+            std::fs::create_dir(folder).map_err(|e| LapsifyError::io(folder, e))?;
+            created.push(folder.clone());
+            for file in *files {
+                let target = folder.join(file.file_name().expect("listed files have names"));
+                std::fs::rename(file, &target).map_err(|e| LapsifyError::io(file, e))?;
+                moved.push((file.clone(), target));
+            }
+        }
+        for (_, _, scene_path, scene_project) in &plan {
+            scene_project.save_atomic(scene_path)?;
+            saved.push(scene_path.clone());
+        }
+        Ok(())
+    };
+    if let Err(e) = split() {
+        for (from, to) in moved.iter().rev() {
+            let _ = std::fs::rename(to, from);
+        }
+        for folder in &created {
+            let _ = std::fs::remove_dir(folder);
+        }
+        for path in &saved {
+            let _ = std::fs::remove_file(path);
+        }
+        return Err(e);
+    }
+    eprintln!(
+        "Split into {} projects; {} is left as it was",
+        scenes.scenes.len(),
+        project_path.display()
+    );
+    Ok(())
+}
+
 fn run_preview(matches: &ArgMatches) -> Result<()> {
     let project = build_project(matches)?;
     project.validate()?;
@@ -1179,9 +2538,18 @@ fn run_render(matches: &ArgMatches) -> Result<()> {
     // Scan every frame's header up front: catches mixed frame sizes before
     // any processing, and validates the crop window against every frame.
     let image_files = list_images(&project.input)?;
-    scan_dimensions(&image_files)?;
-    if let Some(ref crop) = project.crop {
-        crop.validate_over(image_files.len())?;
+    let (src_w, src_h) = scan_dimensions(&image_files)?;
+    if let Some(ref perspective) = project.perspective {
+        perspective.validate_over(image_files.len())?;
+    }
+    for render in project.renders() {
+        match (&render.crop, &project.perspective) {
+            (Some(crop), perspective) => {
+                crop.validate_over(image_files.len(), src_w, src_h, perspective.as_ref())?
+            }
+            (None, Some(perspective)) => perspective.validate_coverage(image_files.len())?,
+            (None, None) => {}
+        }
     }
 
     let reporter = match matches.get_one::<String>("progress").unwrap().as_str() {
@@ -1209,6 +2577,14 @@ fn run_render(matches: &ArgMatches) -> Result<()> {
             print_curve("  y", &crop.y, "");
             print_curve("  width", &crop.width, "");
             print_curve("  height", &crop.height, "");
+            print_curve("  rotation", &crop.rotation, "°");
+            if let Some(aspect) = crop.aspect {
+                eprintln!(
+                    "  {}: {} (height follows width)",
+                    "  aspect".green(),
+                    aspect
+                );
+            }
         }
 
         if project.is_video_output() {
@@ -1220,8 +2596,13 @@ fn run_render(matches: &ArgMatches) -> Result<()> {
                 project.export.codec,
                 project.export.quality
             );
-            if let Some(ref res) = project.export.resolution {
-                eprintln!("  {}: {}", "Resolution".yellow(), res);
+            if let Some(fps) = project.export.output_fps {
+                eprintln!(
+                    "  {}: {} fps ({:?} in-between frames)",
+                    "Encoded at".yellow(),
+                    fps,
+                    project.export.frame_interpolation
+                );
             }
         } else {
             eprintln!(
@@ -1230,6 +2611,19 @@ fn run_render(matches: &ArgMatches) -> Result<()> {
                 project.export.format
             );
         }
+        let framing = match (&project.export.resolution, project.export.aspect) {
+            (Some(res), _) => Some(res.clone()),
+            (None, Some(aspect)) => Some(aspect.to_string()),
+            (None, None) => None,
+        };
+        if let Some(size) = framing {
+            eprintln!(
+                "  {}: {} ({:?})",
+                "Resolution".yellow(),
+                size,
+                project.export.framing
+            );
+        }
 
         if let Some((start, end)) = project.frame_range {
             eprintln!(
@@ -1240,15 +2634,19 @@ fn run_render(matches: &ArgMatches) -> Result<()> {
                 end.saturating_sub(start) + 1
             );
         }
+        for output in &project.outputs {
+            eprintln!(
+                "  {}: {} {} -> {}",
+                "Also rendering".yellow(),
+                output.name,
+                output.export.format,
+                output.export.output.display()
+            );
+        }
     }
 
     let start_time = Instant::now();
-
-    if project.is_video_output() {
-        render_to_video(&project, &reporter, start_time)?;
-    } else {
-        render_to_images(&project, &reporter, start_time)?;
-    }
+    render_project(&project, &reporter, start_time)?;
 
     Ok(())
 }
@@ -63,10 +63,12 @@ fn smoothstep(edge0: f32, edge1: f32, x: f32) -> f32 {
 }
 
 impl<'a> ColorParams<'a> {
-    pub fn at_frame(project: &'a Project, frame: u32) -> Self {
+    /// Parameters at `frame` of a sequence of `frames` source frames (the
+    /// length gives expression curves their clip position).
+    pub fn at_frame(project: &'a Project, frame: u32, frames: usize) -> Self {
         let color = &project.color;
-        let timeline = crate::timeline::Timeline::of(project);
-        let sample = |curve: &crate::curve::Curve| curve.sample_mapped(frame, |f| timeline.x(f));
+        let timeline = crate::timeline::Timeline::of(project, frames);
+        let sample = |curve: &crate::curve::Curve| curve.sample_on(frame, &timeline);
 
         // Effective exposure is the sum of independent layers: the
         // user-keyframed curve plus machine-generated compensation. Each
@@ -81,10 +83,23 @@ impl<'a> ColorParams<'a> {
             }
         }
 
+        // White balance works the same way: the keyframed temperature and
+        // tint plus the as-shot compensation layer.
+        let mut temperature = sample(&color.temperature);
+        let mut tint = sample(&color.tint);
+        if let Some(wb) = project
+            .analysis
+            .as_ref()
+            .and_then(|a| a.white_balance.as_ref())
+        {
+            temperature += wb.temperature_on(frame, &timeline);
+            tint += wb.tint_on(frame, &timeline);
+        }
+
         Self {
             exposure,
-            temperature: sample(&color.temperature),
-            tint: sample(&color.tint),
+            temperature,
+            tint,
             brightness: sample(&color.brightness),
             contrast: sample(&color.contrast),
             highlights: sample(&color.highlights),
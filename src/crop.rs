@@ -1,7 +1,9 @@
 use serde::{Deserialize, Serialize};
 
-use crate::curve::{Curve, Keyframe};
+use crate::curve::{Curve, Handle, Keyframe};
 use crate::error::{LapsifyError, Result};
+use crate::perspective::Perspective;
+use crate::project::Aspect;
 
 /// A dimension from the legacy crop string: bare numbers are pixels, a `%`
 /// suffix means percent of the image size.
@@ -29,6 +31,23 @@ pub struct CropTrack {
     pub y: Curve,
     pub width: Curve,
     pub height: Curve,
+    /// Angle of the window about its centre in degrees, counterclockwise
+    /// as seen on screen; the picture turns the other way. Levels a tilted
+    /// horizon or animates a slow roll.
+    #[serde(default = "level", skip_serializing_if = "is_level")]
+    pub rotation: Curve,
+    /// Lock the window to this aspect ratio in pixels: `height` is ignored
+    /// and follows `width`, so a zoom can't change the window's shape.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspect: Option<Aspect>,
+}
+
+fn level() -> Curve {
+    Curve::Constant(0.0)
+}
+
+fn is_level(curve: &Curve) -> bool {
+    matches!(curve, Curve::Constant(angle) if *angle == 0.0)
 }
 
 impl CropTrack {
@@ -38,22 +57,55 @@ impl CropTrack {
             y: Curve::Constant(rect.y),
             width: Curve::Constant(rect.width),
             height: Curve::Constant(rect.height),
+            rotation: level(),
+            aspect: None,
         }
     }
 
-    /// The normalized crop rectangle at a frame.
-    pub fn rect_at(&self, frame: u32) -> CropRect {
+    pub fn curves(&self) -> [(&'static str, &Curve); 5] {
+        [
+            ("crop.x", &self.x),
+            ("crop.y", &self.y),
+            ("crop.width", &self.width),
+            ("crop.height", &self.height),
+            ("crop.rotation", &self.rotation),
+        ]
+    }
+
+    pub fn curves_mut(&mut self) -> [(&'static str, &mut Curve); 5] {
+        [
+            ("crop.x", &mut self.x),
+            ("crop.y", &mut self.y),
+            ("crop.width", &mut self.width),
+            ("crop.height", &mut self.height),
+            ("crop.rotation", &mut self.rotation),
+        ]
+    }
+
+    /// The window angle at a frame, in degrees.
+    pub fn rotation_at(&self, frame: u32) -> f32 {
+        self.rotation.sample(frame)
+    }
+
+    /// The normalized crop rectangle at a frame, in a source `src_aspect`
+    /// wide (width over height; it sizes an aspect-locked window).
+    pub fn rect_at(&self, frame: u32, src_aspect: f32) -> CropRect {
+        let width = self.width.sample(frame);
+        let height = match self.aspect {
+            Some(aspect) => width * src_aspect / aspect.ratio(),
+            None => self.height.sample(frame),
+        };
         CropRect {
             x: self.x.sample(frame),
             y: self.y.sample(frame),
-            width: self.width.sample(frame),
-            height: self.height.sample(frame),
+            width,
+            height,
         }
     }
 
     /// The crop rectangle at a frame in pixels, clamped to the image.
     pub fn pixel_rect(&self, frame: u32, src_w: u32, src_h: u32) -> Result<(u32, u32, u32, u32)> {
-        let rect = self.rect_at(frame);
+        let rect = self.rect_at(frame, src_w as f32 / src_h as f32);
         let x = (rect.x * src_w as f32)
             .round()
             .clamp(0.0, (src_w - 1) as f32) as u32;
@@ -72,48 +124,131 @@ impl CropTrack {
         Ok((x, y, w, h))
     }
 
-    /// Structural validation of the four channel curves.
+    /// The exact crop window at a frame in source pixels, not snapped to
+    /// the pixel grid.
+    pub fn window_px(&self, frame: u32, src_w: u32, src_h: u32) -> (f32, f32, f32, f32) {
+        let rect = self.rect_at(frame, src_w as f32 / src_h as f32);
+        (
+            rect.x * src_w as f32,
+            rect.y * src_h as f32,
+            rect.width * src_w as f32,
+            rect.height * src_h as f32,
+        )
+    }
+
+    /// The fixed size every frame's window is resampled to: the window's
+    /// pixel size at `frame`, the first frame rendered. Zooming from there
+    /// magnifies or shrinks the picture rather than changing the frame size.
+    pub fn output_size(&self, frame: u32, src_w: u32, src_h: u32) -> Result<(u32, u32)> {
+        let (_, _, w, h) = self.pixel_rect(frame, src_w, src_h)?;
+        Ok((w, h))
+    }
+
+    /// Structural validation of the channel curves.
     pub fn validate(&self) -> Result<()> {
+        // Geometry samples in plain frame space, where an expression would
+        // have no clip position to work with.
+        for (name, curve) in self.curves() {
+            if matches!(curve, Curve::Expression(_)) {
+                return Err(LapsifyError::InvalidParam {
+                    field: name,
+                    reason: "expressions are supported for color curves only".to_string(),
+                });
+            }
+        }
         self.x.validate("crop.x")?;
         self.y.validate("crop.y")?;
         self.width.validate("crop.width")?;
         self.height.validate("crop.height")?;
+        self.rotation.validate("crop.rotation")?;
         self.x.validate_range("crop.x", 0.0, 1.0)?;
         self.y.validate_range("crop.y", 0.0, 1.0)?;
         self.width.validate_range("crop.width", 0.0, 1.0)?;
         self.height.validate_range("crop.height", 0.0, 1.0)?;
+        self.rotation
+            .validate_range("crop.rotation", -180.0, 180.0)?;
         Ok(())
     }
 
-    /// Exact validation: sample every frame and check the window stays inside
-    /// the image. Cheap (a few curve samples per frame) and exact, unlike
-    /// checking keyframe extremes across independently keyframed channels.
-    pub fn validate_over(&self, total_frames: usize) -> Result<()> {
+    /// Exact validation: sample every frame and check the window, rotated
+    /// about its centre, stays inside a `src_w`x`src_h` image, and inside
+    /// the part of it a perspective correction leaves with pixels. Cheap (a
+    /// few curve samples per frame) and exact, unlike checking keyframe
+    /// extremes across independently keyframed channels.
+    pub fn validate_over(
+        &self,
+        total_frames: usize,
+        src_w: u32,
+        src_h: u32,
+        perspective: Option<&Perspective>,
+    ) -> Result<()> {
         const EPS: f32 = 1e-4;
+        let aspect = src_w as f32 / src_h as f32;
         for frame in 0..total_frames as u32 {
-            let rect = self.rect_at(frame);
+            let rect = self.rect_at(frame, aspect);
             if rect.width <= 0.0 || rect.height <= 0.0 {
                 return Err(LapsifyError::message(format!(
                     "Crop window has no area at frame {frame}"
                 )));
             }
-            if rect.x < -EPS || rect.x + rect.width > 1.0 + EPS {
+            let (half_w, half_h) = rotated_half_extents(rect, self.rotation_at(frame), aspect);
+            let (cx, cy) = (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+            if cx - half_w < -EPS || cx + half_w > 1.0 + EPS {
                 return Err(LapsifyError::message(format!(
-                    "Crop window exceeds horizontal image bounds at frame {frame} (x={}, width={})",
-                    rect.x, rect.width
+                    "Crop window exceeds horizontal image bounds at frame {frame} (x={}, width={}, rotation={})",
+                    rect.x,
+                    rect.width,
+                    self.rotation_at(frame)
                 )));
             }
-            if rect.y < -EPS || rect.y + rect.height > 1.0 + EPS {
+            if cy - half_h < -EPS || cy + half_h > 1.0 + EPS {
                 return Err(LapsifyError::message(format!(
-                    "Crop window exceeds vertical image bounds at frame {frame} (y={}, height={})",
-                    rect.y, rect.height
+                    "Crop window exceeds vertical image bounds at frame {frame} (y={}, height={}, rotation={})",
+                    rect.y,
+                    rect.height,
+                    self.rotation_at(frame)
                 )));
             }
+            if let Some(perspective) = perspective {
+                let inside = window_corners(rect, self.rotation_at(frame), aspect)
+                    .into_iter()
+                    .all(|corner| perspective.contains(frame, corner));
+                if !inside {
+                    return Err(LapsifyError::message(format!(
+                        "Crop window reaches outside the perspective-corrected image at frame {frame}"
+                    )));
+                }
+            }
         }
         Ok(())
     }
 }
 
+/// Corners of `rect` turned by `degrees` (counterclockwise on screen)
+/// about its centre, in normalized coordinates of an image `aspect` wide.
+fn window_corners(rect: CropRect, degrees: f32, aspect: f32) -> [(f32, f32); 4] {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (cx, cy) = (rect.x + rect.width / 2.0, rect.y + rect.height / 2.0);
+    // Turn in units of the image height, where turning is isotropic.
+    let (hw, hh) = (rect.width * aspect / 2.0, rect.height / 2.0);
+    [(-hw, -hh), (hw, -hh), (hw, hh), (-hw, hh)]
+        .map(|(u, v)| (cx + (cos * u + sin * v) / aspect, cy - sin * u + cos * v))
+}
+
+/// Half the normalized width and height of the bounding box of `rect`
+/// turned by `degrees` about its centre, in an image `aspect` (width over
+/// height) wide.
+pub fn rotated_half_extents(rect: CropRect, degrees: f32, aspect: f32) -> (f32, f32) {
+    let (sin, cos) = degrees.to_radians().sin_cos();
+    let (sin, cos) = (sin.abs(), cos.abs());
+    // In units of the image height, where turning is isotropic.
+    let (w, h) = (rect.width * aspect, rect.height);
+    (
+        (w * cos + h * sin) / 2.0 / aspect,
+        (w * sin + h * cos) / 2.0,
+    )
+}
+
 /// Parse a legacy "width:height:x:y" crop string. Bare numbers are pixels
 /// (negative = from the right/bottom edge), `%` values are percentages.
 pub fn parse_crop_dims(input: &str) -> Result<[Dim; 4]> {
@@ -208,13 +343,21 @@ pub fn legacy_crop_to_track(
     let fold = |base: f32, offsets: &Curve, size: f32| -> Curve {
         match offsets {
             Curve::Constant(off) => Curve::Constant((base + off) / size),
+            Curve::Expression(_) => unreachable!("offset flags parse to constants or keyframes"),
             Curve::Keyframed(keyframes) => Curve::Keyframed(
                 keyframes
                     .iter()
-                    .map(|k| Keyframe {
-                        frame: k.frame,
-                        value: (base + k.value) / size,
-                        easing: k.easing,
+                    .map(|k| {
+                        let scale = |h: Handle| Handle {
+                            frames: h.frames,
+                            value: h.value / size,
+                        };
+                        Keyframe {
+                            value: (base + k.value) / size,
+                            in_handle: k.in_handle.map(scale),
+                            out_handle: k.out_handle.map(scale),
+                            ..k.clone()
+                        }
                     })
                     .collect(),
             ),
@@ -226,6 +369,8 @@ pub fn legacy_crop_to_track(
         y: fold(y, offset_y, src_h),
         width: Curve::Constant(w / src_w),
         height: Curve::Constant(h / src_h),
+        rotation: level(),
+        aspect: None,
     })
 }
 
@@ -344,13 +489,35 @@ mod tests {
             y: Curve::Constant(0.0),
             width: Curve::Constant(0.5),
             height: Curve::Constant(0.5),
+            rotation: Curve::Constant(0.0),
+            aspect: None,
         };
         assert!(track.validate().is_ok());
-        assert!(track.validate_over(101).is_ok());
-        let mid = track.rect_at(50);
+        assert!(track.validate_over(101, 100, 100, None).is_ok());
+        let mid = track.rect_at(50, 1.0);
         assert!(mid.x > 0.0 && mid.x < 0.5);
     }
 
+    #[test]
+    fn aspect_lock_sizes_the_height_from_the_width() {
+        let mut track = CropTrack::from_rect(CropRect {
+            x: 0.0,
+            y: 0.0,
+            width: 0.5,
+            height: 0.9,
+        });
+        track.width = Curve::Keyframed(vec![Keyframe::new(0, 0.8), Keyframe::new(10, 0.4)]);
+        track.aspect = Some("16:9".parse().unwrap());
+        for frame in [0, 5, 10] {
+            let (_, _, w, h) = track.window_px(frame, 3000, 2000);
+            assert_relative_eq!(w / h, 16.0 / 9.0, epsilon = 1e-4);
+        }
+        let json = serde_json::to_string(&track).unwrap();
+        assert!(json.contains(r#""aspect":"16:9""#), "{json}");
+        let back: CropTrack = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.aspect, track.aspect);
+    }
+
     #[test]
     fn validate_over_catches_out_of_bounds_frames() {
         let track = CropTrack {
@@ -358,10 +525,28 @@ mod tests {
             y: Curve::Constant(0.0),
             width: Curve::Constant(0.5),
             height: Curve::Constant(0.5),
+            rotation: Curve::Constant(0.0),
+            aspect: None,
         };
         // x + width reaches 1.3 by frame 100.
-        assert!(track.validate_over(101).is_err());
-        assert!(track.validate_over(10).is_ok());
+        assert!(track.validate_over(101, 100, 100, None).is_err());
+        assert!(track.validate_over(10, 100, 100, None).is_ok());
+    }
+
+    #[test]
+    fn validate_over_checks_the_rotated_window() {
+        let mut track = CropTrack::from_rect(CropRect {
+            x: 0.25,
+            y: 0.25,
+            width: 0.5,
+            height: 0.5,
+        });
+        // A centred half-size window can turn freely in a square image...
+        track.rotation = Curve::Keyframed(vec![Keyframe::new(0, 0.0), Keyframe::new(10, 45.0)]);
+        assert!(track.validate_over(11, 100, 100, None).is_ok());
+        // ...but in a wide one its 45° corners cross the top and bottom.
+        assert!(track.validate_over(11, 300, 100, None).is_err());
+        assert!(track.validate_over(3, 300, 100, None).is_ok());
     }
 
     #[test]
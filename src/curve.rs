@@ -1,6 +1,9 @@
 use serde::{Deserialize, Serialize};
 
 use crate::error::{LapsifyError, Result};
+use crate::exif::{format_capture_time, parse_capture_time};
+use crate::expr::{Expression, Vars};
+use crate::timeline::Timeline;
 
 /// Easing applied to the segment that leaves a keyframe.
 #[derive(
@@ -17,14 +20,42 @@ pub enum Easing {
     EaseIn,
     EaseOut,
     EaseInOut,
+    /// Cubic Bezier shaped by this keyframe's out handle and the next
+    /// keyframe's in handle.
+    Bezier,
 }
 
+/// A Bezier tangent handle, relative to its keyframe: `frames` along the
+/// timeline (negative for an in handle) and `value` in curve units. With a
+/// capture-time mapping the handle covers the same fraction of its segment
+/// as it does in frames.
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct Handle {
+    pub frames: f32,
+    pub value: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct Keyframe {
+    /// Source frame index. Derived from `at` when the keyframe is anchored.
+    #[serde(default)]
     pub frame: u32,
     pub value: f32,
+    /// Capture-time anchor ("2026-06-21 21:14:00", camera local time). The
+    /// keyframe sits on the frame captured nearest to it, so it stays with
+    /// the same moment when frames are added to or removed from the source.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub at: Option<String>,
     #[serde(default, skip_serializing_if = "is_default_easing")]
     pub easing: Easing,
+    /// Handle arriving at this keyframe, used when the previous keyframe's
+    /// easing is Bezier. None = flat, a third of the segment long.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_handle: Option<Handle>,
+    /// Handle leaving this keyframe, used when its easing is Bezier.
+    /// None = flat, a third of the segment long.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub out_handle: Option<Handle>,
 }
 
 fn is_default_easing(easing: &Easing) -> bool {
@@ -36,21 +67,74 @@ impl Keyframe {
         Self {
             frame,
             value,
+            at: None,
             easing: Easing::Smooth,
+            in_handle: None,
+            out_handle: None,
         }
     }
 }
 
-/// A parameter value over time: either constant for the whole clip or a set
-/// of keyframes anchored to specific frames.
+/// Effective (in, out) handles of keyframe `index`: None where the adjoining
+/// segment is not Bezier, the stored handle or its flat default otherwise.
+pub fn bezier_handles(keyframes: &[Keyframe], index: usize) -> (Option<Handle>, Option<Handle>) {
+    let k = &keyframes[index];
+    let flat = |frames: f32| Handle { frames, value: 0.0 };
+    let incoming = index
+        .checked_sub(1)
+        .map(|i| &keyframes[i])
+        .filter(|prev| prev.easing == Easing::Bezier)
+        .map(|prev| {
+            k.in_handle
+                .unwrap_or(flat(-((k.frame - prev.frame) as f32) / 3.0))
+        });
+    let outgoing = keyframes
+        .get(index + 1)
+        .filter(|_| k.easing == Easing::Bezier)
+        .map(|next| {
+            k.out_handle
+                .unwrap_or(flat((next.frame - k.frame) as f32 / 3.0))
+        });
+    (incoming, outgoing)
+}
+
+/// Turn the segment leaving keyframe `index` into a Bezier whose handles
+/// reproduce the Smooth curve it replaces, so switching changes nothing
+/// until a handle is moved. `x_of` is the axis the curve is sampled on
+/// (`Timeline::x`), which the Smooth tangents depend on.
+pub fn make_bezier(keyframes: &mut [Keyframe], index: usize, x_of: impl Fn(u32) -> f32) {
+    if index + 1 >= keyframes.len() {
+        return;
+    }
+    let tangents = monotone_tangents(keyframes, &x_of);
+    let (k0, k1) = (&keyframes[index], &keyframes[index + 1]);
+    // A third of the segment, in frames for the handle's position and on
+    // the axis for the tangent's rise.
+    let third = (k1.frame - k0.frame) as f32 / 3.0;
+    let span = (x_of(k1.frame) - x_of(k0.frame)) / 3.0;
+    keyframes[index].out_handle = Some(Handle {
+        frames: third,
+        value: tangents[index] * span,
+    });
+    keyframes[index + 1].in_handle = Some(Handle {
+        frames: -third,
+        value: -tangents[index + 1] * span,
+    });
+    keyframes[index].easing = Easing::Bezier;
+}
+
+/// A parameter value over time: constant for the whole clip, a set of
+/// keyframes anchored to specific frames, or a formula (see `expr`).
 ///
-/// Serializes as a bare number ("exposure": 0.5) or a keyframe list
-/// ("exposure": [{"frame": 0, "value": 0.0}, {"frame": 120, "value": 1.5}]).
+/// Serializes as a bare number ("exposure": 0.5), a keyframe list
+/// ("exposure": [{"frame": 0, "value": 0.0}, {"frame": 120, "value": 1.5}])
+/// or an expression string ("exposure": "0.3 * sin(2 * pi * t)").
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
 #[serde(untagged)]
 pub enum Curve {
     Constant(f32),
     Keyframed(Vec<Keyframe>),
+    Expression(Expression),
 }
 
 impl Curve {
@@ -62,25 +146,142 @@ impl Curve {
 
     /// Sample the curve with an arbitrary frame-to-position mapping. With a
     /// capture-time mapping, interpolation happens in time space, which is
-    /// what irregular shooting intervals need.
+    /// what irregular shooting intervals need. Expressions get no clip
+    /// context here (`t` is 0, `time` is the frame); use `sample_on`.
     pub fn sample_mapped(&self, frame: u32, x_of: impl Fn(u32) -> f32) -> f32 {
         match self {
             Curve::Constant(v) => *v,
             Curve::Keyframed(keyframes) => sample_keyframes(keyframes, frame, x_of),
+            Curve::Expression(expression) => expression.eval(&Vars {
+                frame: frame as f32,
+                t: 0.0,
+                time: frame as f32,
+            }),
+        }
+    }
+
+    /// Sample the curve on a project timeline: keyframes interpolate along
+    /// its axis and expressions see the full clip context.
+    pub fn sample_on(&self, frame: u32, timeline: &Timeline) -> f32 {
+        match self {
+            Curve::Expression(expression) => expression.eval(&timeline.vars(frame)),
+            _ => self.sample_mapped(frame, |f| timeline.x(f)),
         }
     }
 
     /// All control values of the curve. Because interpolation is monotone
-    /// between keyframes, every sampled value lies within the min/max of
-    /// these values, which makes them sufficient for range validation.
+    /// between keyframes, and a Bezier segment stays inside the hull of its
+    /// handles, every sampled value lies within the min/max of these values,
+    /// which makes them sufficient for range validation. Expressions have
+    /// no control values.
     pub fn values(&self) -> Vec<f32> {
         match self {
             Curve::Constant(v) => vec![*v],
-            Curve::Keyframed(keyframes) => keyframes.iter().map(|k| k.value).collect(),
+            Curve::Expression(_) => Vec::new(),
+            Curve::Keyframed(keyframes) => {
+                let mut values = Vec::with_capacity(keyframes.len());
+                for (i, k) in keyframes.iter().enumerate() {
+                    values.push(k.value);
+                    let (incoming, outgoing) = bezier_handles(keyframes, i);
+                    values.extend(incoming.iter().chain(&outgoing).map(|h| k.value + h.value));
+                }
+                values
+            }
         }
     }
 
+    /// Move capture-time anchored keyframes onto the frame captured nearest
+    /// to their anchor, then restore frame order. Anchors that don't parse
+    /// are left where they are for `validate_anchors` to report.
+    pub fn resolve_anchors(&mut self, capture_times: &[i64]) {
+        let Curve::Keyframed(keyframes) = self else {
+            return;
+        };
+        if capture_times.is_empty() {
+            return;
+        }
+        for k in keyframes.iter_mut() {
+            let Some(at) = k.at.as_deref().and_then(parse_capture_time) else {
+                continue;
+            };
+            k.frame = nearest_frame(capture_times, at);
+        }
+        keyframes.sort_by_key(|k| k.frame);
+    }
+
+    /// Check that every anchored keyframe has a parseable anchor inside the
+    /// captured span (give or take one average shooting interval), landing
+    /// on a frame no other keyframe holds.
+    pub fn validate_anchors(
+        &self,
+        name: &'static str,
+        capture_times: Option<&[i64]>,
+    ) -> Result<()> {
+        let Curve::Keyframed(keyframes) = self else {
+            return Ok(());
+        };
+        for (i, at) in keyframes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, k)| Some((i, k.at.as_deref()?)))
+        {
+            let invalid = |reason: String| LapsifyError::InvalidParam {
+                field: name,
+                reason,
+            };
+            let ms = parse_capture_time(at).ok_or_else(|| {
+                invalid(format!(
+                    "keyframe anchor '{at}' is not a capture time (expected \"YYYY-MM-DD HH:MM:SS\")"
+                ))
+            })?;
+            let times = capture_times.filter(|t| !t.is_empty()).ok_or_else(|| {
+                invalid(format!(
+                    "keyframe anchored at '{at}' needs capture times of the current source frames; run `lapsify analyze holygrail` first"
+                ))
+            })?;
+            let first = times.iter().copied().min().unwrap_or_default();
+            let last = times.iter().copied().max().unwrap_or_default();
+            let slack = (last - first) / times.len().max(2) as i64;
+            if ms < first - slack || ms > last + slack {
+                return Err(invalid(format!(
+                    "keyframe anchored at '{at}' is outside the captured span ({} to {})",
+                    format_capture_time(first),
+                    format_capture_time(last)
+                )));
+            }
+            let frame = nearest_frame(times, ms);
+            let lands_on = |k: &Keyframe| match k.at.as_deref() {
+                None => k.frame == frame,
+                Some(other) => parse_capture_time(other)
+                    .is_some_and(|other| nearest_frame(times, other) == frame),
+            };
+            if let Some((_, other)) = keyframes
+                .iter()
+                .enumerate()
+                .find(|&(j, k)| j != i && lands_on(k))
+            {
+                return Err(invalid(match other.at.as_deref() {
+                    Some(other) => format!(
+                        "keyframes anchored at '{other}' and '{at}' both land on frame {frame}"
+                    ),
+                    None => format!(
+                        "keyframe anchored at '{at}' lands on frame {frame}, which already has a keyframe"
+                    ),
+                }));
+            }
+        }
+        Ok(())
+    }
+
     pub fn validate(&self, name: &'static str) -> Result<()> {
+        if let Curve::Expression(expression) = self {
+            if let Some(error) = expression.error() {
+                return Err(LapsifyError::InvalidParam {
+                    field: name,
+                    reason: format!("invalid expression '{}': {error}", expression.source()),
+                });
+            }
+        }
         if let Curve::Keyframed(keyframes) = self {
             if keyframes.is_empty() {
                 return Err(LapsifyError::InvalidParam {
@@ -104,6 +305,7 @@ impl Curve {
     }
 
     /// Validate that every control value lies inside [min, max].
+    /// Expressions have none; `validate_range_on` samples them.
     pub fn validate_range(&self, name: &'static str, min: f32, max: f32) -> Result<()> {
         for value in self.values() {
             if value < min || value > max {
@@ -115,6 +317,43 @@ impl Curve {
         }
         Ok(())
     }
+
+    /// `validate_range` for a curve sampled on `timeline`: an expression is
+    /// evaluated at every frame of it, with the clip position and capture
+    /// times it renders with (non-finite results are rejected too).
+    pub fn validate_range_on(
+        &self,
+        name: &'static str,
+        min: f32,
+        max: f32,
+        timeline: &Timeline,
+    ) -> Result<()> {
+        if let Curve::Expression(expression) = self {
+            for frame in 0..timeline.frames() as u32 {
+                let vars = timeline.vars(frame);
+                let value = expression.eval(&vars);
+                if !(min..=max).contains(&value) {
+                    return Err(LapsifyError::InvalidParam {
+                        field: name,
+                        reason: format!(
+                            "expression '{}' gives {value} at frame {frame} (t = {:.3}), outside valid range [{min}, {max}]",
+                            expression.source(),
+                            vars.t
+                        ),
+                    });
+                }
+            }
+            return Ok(());
+        }
+        self.validate_range(name, min, max)
+    }
+}
+
+/// The frame captured nearest to `at` (capture times must not be empty).
+fn nearest_frame(capture_times: &[i64], at: i64) -> u32 {
+    (0..capture_times.len())
+        .min_by_key(|&i| (capture_times[i] - at).abs())
+        .unwrap_or(0) as u32
 }
 
 fn sample_keyframes(keyframes: &[Keyframe], frame: u32, x_of: impl Fn(u32) -> f32) -> f32 {
@@ -157,6 +396,11 @@ fn sample_keyframes(keyframes: &[Keyframe], frame: u32, x_of: impl Fn(u32) -> f3
                     let tangents = monotone_tangents(keyframes, &x_of);
                     hermite(k0, k1, tangents[i], tangents[i + 1], t, span)
                 }
+                Easing::Bezier => {
+                    let (_, out) = bezier_handles(keyframes, i);
+                    let (incoming, _) = bezier_handles(keyframes, i + 1);
+                    bezier(k0, k1, out.unwrap(), incoming.unwrap(), t)
+                }
             }
         }
     }
@@ -218,6 +462,34 @@ fn hermite(k0: &Keyframe, k1: &Keyframe, m0: f32, m1: f32, t: f32, h: f32) -> f3
     h00 * k0.value + h10 * h * m0 + h01 * k1.value + h11 * h * m1
 }
 
+/// Cubic Bezier segment at position `t` (0..1 of the segment). Handle
+/// lengths are clamped to the segment, which keeps x monotone in the curve
+/// parameter, so bisection finds the unique parameter for `t`.
+fn bezier(k0: &Keyframe, k1: &Keyframe, out: Handle, incoming: Handle, t: f32) -> f32 {
+    let frames = (k1.frame - k0.frame) as f32;
+    let x1 = (out.frames / frames).clamp(0.0, 1.0);
+    let x2 = 1.0 - (-incoming.frames / frames).clamp(0.0, 1.0);
+    let cubic = |p1: f32, p2: f32, p3: f32, s: f32| {
+        let u = 1.0 - s;
+        3.0 * u * u * s * p1 + 3.0 * u * s * s * p2 + s * s * s * p3
+    };
+
+    let (mut lo, mut hi) = (0.0f32, 1.0f32);
+    for _ in 0..32 {
+        let mid = (lo + hi) / 2.0;
+        if cubic(x1, x2, 1.0, mid) < t {
+            lo = mid;
+        } else {
+            hi = mid;
+        }
+    }
+    let s = (lo + hi) / 2.0;
+
+    let y1 = out.value;
+    let y2 = k1.value - k0.value + incoming.value;
+    k0.value + cubic(y1, y2, k1.value - k0.value, s)
+}
+
 /// Convert a legacy comma-array value ("-1.0,0.5,1.0") into a curve: values
 /// are spread evenly over the clip as smooth keyframes.
 pub fn curve_from_legacy_array(values: &[f32], total_frames: usize) -> Curve {
@@ -236,6 +508,64 @@ pub fn curve_from_legacy_array(values: &[f32], total_frames: usize) -> Curve {
     Curve::Keyframed(keyframes)
 }
 
+/// Reduce a per-frame series to the fewest linear keyframes that reproduce
+/// it within `tolerance` at every frame (Ramer-Douglas-Peucker with
+/// vertical error). Segments are straight in the same position mapping the
+/// curve will be sampled with, so time-interpolated projects bake correctly.
+pub fn bake_samples(values: &[f32], tolerance: f32, x_of: impl Fn(u32) -> f32) -> Curve {
+    match values {
+        [] => return Curve::Constant(0.0),
+        [only] => return Curve::Constant(*only),
+        _ => {}
+    }
+    let (min, max) = values
+        .iter()
+        .fold((f32::INFINITY, f32::NEG_INFINITY), |(lo, hi), &v| {
+            (lo.min(v), hi.max(v))
+        });
+    if max - min <= 2.0 * tolerance {
+        return Curve::Constant((min + max) / 2.0);
+    }
+
+    let last = values.len() - 1;
+    let mut keep = vec![false; values.len()];
+    keep[0] = true;
+    keep[last] = true;
+    let mut stack = vec![(0usize, last)];
+    while let Some((a, b)) = stack.pop() {
+        let xa = x_of(a as u32);
+        let span = x_of(b as u32) - xa;
+        let mut worst = (0.0f32, a);
+        for i in a + 1..b {
+            let t = if span > 0.0 {
+                (x_of(i as u32) - xa) / span
+            } else {
+                0.0
+            };
+            let error = (values[i] - lerp(values[a], values[b], t)).abs();
+            if error > worst.0 {
+                worst = (error, i);
+            }
+        }
+        if worst.0 > tolerance {
+            keep[worst.1] = true;
+            stack.push((a, worst.1));
+            stack.push((worst.1, b));
+        }
+    }
+
+    let keyframes = keep
+        .iter()
+        .enumerate()
+        .filter(|(_, &k)| k)
+        .map(|(i, _)| Keyframe {
+            easing: Easing::Linear,
+            ..Keyframe::new(i as u32, values[i])
+        })
+        .collect();
+    Curve::Keyframed(keyframes)
+}
+
 /// Parse a comma-separated list of floats, e.g. "-1.0,0.5,1.0".
 pub fn parse_value_array(input: &str) -> Result<Vec<f32>> {
     input
@@ -297,9 +627,8 @@ mod tests {
     fn linear_easing_is_linear() {
         let curve = Curve::Keyframed(vec![
             Keyframe {
-                frame: 0,
-                value: 0.0,
                 easing: Easing::Linear,
+                ..Keyframe::new(0, 0.0)
             },
             Keyframe::new(10, 1.0),
         ]);
@@ -310,9 +639,8 @@ mod tests {
     fn hold_easing_steps() {
         let curve = Curve::Keyframed(vec![
             Keyframe {
-                frame: 0,
-                value: 1.0,
                 easing: Easing::Hold,
+                ..Keyframe::new(0, 1.0)
             },
             Keyframe::new(10, 2.0),
         ]);
@@ -324,9 +652,8 @@ mod tests {
     fn ease_in_out_hits_midpoint() {
         let curve = Curve::Keyframed(vec![
             Keyframe {
-                frame: 0,
-                value: 0.0,
                 easing: Easing::EaseInOut,
+                ..Keyframe::new(0, 0.0)
             },
             Keyframe::new(10, 1.0),
         ]);
@@ -335,6 +662,107 @@ mod tests {
         assert_relative_eq!(curve.sample(10), 1.0);
     }
 
+    #[test]
+    fn bezier_from_smooth_samples_identically() {
+        let points = [(0, 0.0), (10, 2.0), (25, -1.0), (40, 0.5)];
+        let smooth = keyframed(&points);
+        let Curve::Keyframed(mut kfs) = smooth.clone() else {
+            unreachable!()
+        };
+        let mut timed = kfs.clone();
+        for i in 0..kfs.len() - 1 {
+            make_bezier(&mut kfs, i, |f| f as f32);
+        }
+        let bezier = Curve::Keyframed(kfs);
+        for frame in 0..=40 {
+            assert_relative_eq!(bezier.sample(frame), smooth.sample(frame), epsilon = 1e-4);
+        }
+
+        // On an uneven capture-time axis the handles follow its spacing.
+        let x_of = |f: u32| (f * f) as f32 / 10.0;
+        for i in 0..timed.len() - 1 {
+            make_bezier(&mut timed, i, x_of);
+        }
+        let timed = Curve::Keyframed(timed);
+        for frame in 0..=40 {
+            assert_relative_eq!(
+                timed.sample_mapped(frame, x_of),
+                smooth.sample_mapped(frame, x_of),
+                epsilon = 1e-4
+            );
+        }
+    }
+
+    #[test]
+    fn bezier_handles_shape_the_segment() {
+        // A steep out handle overshoots the end value; the control values
+        // report that for range validation.
+        let curve = Curve::Keyframed(vec![
+            Keyframe {
+                easing: Easing::Bezier,
+                out_handle: Some(Handle {
+                    frames: 5.0,
+                    value: 4.0,
+                }),
+                ..Keyframe::new(0, 0.0)
+            },
+            Keyframe::new(10, 1.0),
+        ]);
+        assert!(curve.sample(5) > 1.0, "{}", curve.sample(5));
+        assert_relative_eq!(curve.sample(0), 0.0);
+        assert_relative_eq!(curve.sample(10), 1.0);
+        assert!(curve.validate_range("test", -3.0, 3.0).is_err());
+
+        // Missing handles default to flat thirds: an ease-in-out shape.
+        let flat = Curve::Keyframed(vec![
+            Keyframe {
+                easing: Easing::Bezier,
+                ..Keyframe::new(0, 0.0)
+            },
+            Keyframe::new(10, 1.0),
+        ]);
+        assert_relative_eq!(flat.sample(5), 0.5, epsilon = 1e-4);
+        assert!(flat.sample(1) < 0.1);
+    }
+
+    #[test]
+    fn expression_curves_round_trip_and_sample() {
+        let curve: Curve = serde_json::from_str(r#""1 + 0.5 * t""#).unwrap();
+        assert!(matches!(curve, Curve::Expression(_)));
+        assert_eq!(serde_json::to_string(&curve).unwrap(), r#""1 + 0.5 * t""#);
+        assert!(curve.validate("test").is_ok());
+
+        let broken: Curve = serde_json::from_str(r#""1 +""#).unwrap();
+        assert!(broken.validate("test").is_err());
+    }
+
+    #[test]
+    fn expression_range_is_checked_by_sampling() {
+        let timeline = Timeline::indices(1001);
+        // Stays in range at t = 0 and t = 1 but peaks at 4 mid-clip.
+        let peak = Curve::Expression(Expression::parse("4 * sin(pi * t)").unwrap());
+        assert!(peak
+            .validate_range_on("test", -3.0, 3.0, &timeline)
+            .is_err());
+
+        let breathing = Curve::Expression(Expression::parse("0.3 * sin(frame / 10)").unwrap());
+        assert!(breathing
+            .validate_range_on("test", -3.0, 3.0, &timeline)
+            .is_ok());
+
+        let undefined = Curve::Expression(Expression::parse("ln(t)").unwrap());
+        assert!(undefined
+            .validate_range_on("test", -100.0, 100.0, &timeline)
+            .is_err());
+
+        // Only goes out of range past frame 1000, on a longer clip.
+        let late = Curve::Expression(Expression::parse("frame / 1000").unwrap());
+        assert!(late.validate_range_on("test", 0.0, 1.0, &timeline).is_ok());
+        assert!(late
+            .validate_range_on("test", 0.0, 1.0, &Timeline::indices(3000))
+            .is_err());
+    }
+
     #[test]
     fn single_keyframe_is_constant() {
         let curve = keyframed(&[(5, 3.0)]);
@@ -403,6 +831,48 @@ mod tests {
         assert_eq!(curve, reparsed);
     }
 
+    #[test]
+    fn bake_reproduces_series_within_tolerance() {
+        // Staircase plus a slow ramp, like a holy grail layer.
+        let values: Vec<f32> = (0..200)
+            .map(|i| (i / 50) as f32 * 0.33 + i as f32 * 0.002)
+            .collect();
+        let curve = bake_samples(&values, 0.01, |f| f as f32);
+        let Curve::Keyframed(kfs) = &curve else {
+            panic!("expected keyframed curve")
+        };
+        assert!(kfs.len() < 20, "{} keyframes", kfs.len());
+        for (frame, &value) in values.iter().enumerate() {
+            assert!((curve.sample(frame as u32) - value).abs() <= 0.01);
+        }
+    }
+
+    #[test]
+    fn bake_follows_the_position_mapping() {
+        // Linear in time, not in frames: two keyframes suffice only when
+        // the segment is straight in the same mapping the curve samples with.
+        let times = [0.0f32, 1.0, 2.0, 10.0, 11.0];
+        let values: Vec<f32> = times.iter().map(|t| t * 0.5).collect();
+        let curve = bake_samples(&values, 1e-4, |f| times[f as usize]);
+        let Curve::Keyframed(kfs) = &curve else {
+            panic!("expected keyframed curve")
+        };
+        assert_eq!(kfs.len(), 2);
+        assert_relative_eq!(
+            curve.sample_mapped(3, |f| times[f as usize]),
+            5.0,
+            epsilon = 1e-5
+        );
+    }
+
+    #[test]
+    fn bake_of_flat_series_is_constant() {
+        assert_eq!(
+            bake_samples(&[1.0, 1.005, 0.995], 0.01, |f| f as f32),
+            Curve::Constant(1.0)
+        );
+    }
+
     #[test]
     fn parse_value_array_single_and_multi() {
         assert_eq!(parse_value_array("1.5").unwrap(), vec![1.5]);
@@ -14,6 +14,9 @@ pub struct FrameExif {
     pub shutter_s: Option<f32>,
     /// ISO sensitivity.
     pub iso: Option<f32>,
+    /// Exposure compensation dialed in on the camera, in EV (may be zero or
+    /// negative).
+    pub exposure_bias: Option<f32>,
     /// Capture time as unix epoch milliseconds (timezone-naive; only the
     /// relative spacing between frames matters).
     pub datetime_ms: Option<i64>,
@@ -61,6 +64,19 @@ fn read_generic_exif(path: &Path) -> FrameExif {
 
     let iso = numeric(Tag::PhotographicSensitivity);
 
+    // Bias is signed and zero is meaningful, so it skips the positivity
+    // filter above.
+    let exposure_bias = exif
+        .get_field(Tag::ExposureBiasValue, In::PRIMARY)
+        .and_then(|f| match &f.value {
+            Value::SRational(v) if !v.is_empty() => Some(v[0].to_f32()),
+            Value::Rational(v) if !v.is_empty() => Some(v[0].to_f32()),
+            Value::Double(v) if !v.is_empty() => Some(v[0] as f32),
+            Value::Float(v) if !v.is_empty() => Some(v[0]),
+            _ => None,
+        })
+        .filter(|v| v.is_finite());
+
     let datetime_ms = exif
         .get_field(Tag::DateTimeOriginal, In::PRIMARY)
         .or_else(|| exif.get_field(Tag::DateTime, In::PRIMARY))
@@ -89,6 +105,7 @@ fn read_generic_exif(path: &Path) -> FrameExif {
         aperture: numeric(Tag::FNumber),
         shutter_s: numeric(Tag::ExposureTime),
         iso,
+        exposure_bias,
         datetime_ms,
     }
 }
@@ -101,6 +118,13 @@ pub fn camera_ev(e: &FrameExif) -> Option<f32> {
     Some((n * n / t).log2() - (iso / 100.0).log2())
 }
 
+/// The EV the capture was actually exposed for once camera exposure
+/// compensation is included: a +1 bias captures one stop brighter, which
+/// reads like one stop less camera EV.
+pub fn biased_camera_ev(e: &FrameExif) -> Option<f32> {
+    Some(camera_ev(e)? - e.exposure_bias.unwrap_or(0.0))
+}
+
 /// Parse "YYYY:MM:DD HH:MM:SS" into unix epoch milliseconds (naive local
 /// time — relative spacing is all the pipeline needs).
 pub(crate) fn parse_exif_datetime_ms(s: &str) -> Option<i64> {
@@ -123,6 +147,34 @@ pub(crate) fn parse_exif_datetime_ms(s: &str) -> Option<i64> {
     Some((days * 86400 + hour * 3600 + minute * 60 + second) * 1000)
 }
 
+/// Parse a capture-time anchor into the same naive epoch milliseconds as
+/// `parse_exif_datetime_ms`. Accepts "YYYY-MM-DD HH:MM[:SS]", the EXIF
+/// "YYYY:MM:DD HH:MM:SS" form, and a `T` separator.
+pub fn parse_capture_time(s: &str) -> Option<i64> {
+    let s = s.trim().replacen('T', " ", 1);
+    let (date, time) = s.split_once(' ')?;
+    let date = date.replace('-', ":");
+    let time = match time.matches(':').count() {
+        1 => format!("{time}:00"),
+        _ => time.to_string(),
+    };
+    parse_exif_datetime_ms(&format!("{date} {time}"))
+}
+
+/// Format naive epoch milliseconds as "YYYY-MM-DD HH:MM:SS", the form
+/// keyframe anchors are written in.
+pub fn format_capture_time(ms: i64) -> String {
+    let secs = ms.div_euclid(1000);
+    let (days, day_secs) = (secs.div_euclid(86400), secs.rem_euclid(86400));
+    let (y, m, d) = civil_from_days(days);
+    format!(
+        "{y:04}-{m:02}-{d:02} {:02}:{:02}:{:02}",
+        day_secs / 3600,
+        day_secs % 3600 / 60,
+        day_secs % 60
+    )
+}
+
 /// Days since 1970-01-01 (Howard Hinnant's algorithm).
 fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
     let y = if m <= 2 { y - 1 } else { y };
@@ -134,6 +186,20 @@ fn days_from_civil(y: i64, m: u32, d: u32) -> i64 {
     era * 146097 + doe - 719468
 }
 
+/// Inverse of `days_from_civil`.
+fn civil_from_days(z: i64) -> (i64, u32, u32) {
+    let z = z + 719468;
+    let era = if z >= 0 { z } else { z - 146096 } / 146097;
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let d = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let m = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let y = yoe + era * 400 + if m <= 2 { 1 } else { 0 };
+    (y, m, d)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -146,6 +212,7 @@ mod tests {
             aperture: Some(2.8),
             shutter_s: Some(0.01),
             iso: Some(100.0),
+            exposure_bias: None,
             datetime_ms: None,
         };
         assert_relative_eq!(camera_ev(&e).unwrap(), 9.615, epsilon = 0.01);
@@ -176,6 +243,32 @@ mod tests {
     #[test]
     fn missing_fields_yield_no_ev() {
         assert!(camera_ev(&FrameExif::default()).is_none());
+        assert!(biased_camera_ev(&FrameExif {
+            exposure_bias: Some(1.0),
+            ..FrameExif::default()
+        })
+        .is_none());
+    }
+
+    #[test]
+    fn exposure_bias_lowers_effective_ev() {
+        let e = FrameExif {
+            aperture: Some(4.0),
+            shutter_s: Some(0.5),
+            iso: Some(100.0),
+            exposure_bias: None,
+            datetime_ms: None,
+        };
+        let biased = FrameExif {
+            exposure_bias: Some(1.0),
+            ..e
+        };
+        assert_relative_eq!(
+            biased_camera_ev(&e).unwrap() - biased_camera_ev(&biased).unwrap(),
+            1.0,
+            epsilon = 1e-5
+        );
+        assert_relative_eq!(biased_camera_ev(&e).unwrap(), camera_ev(&e).unwrap());
     }
 
     #[test]
@@ -194,6 +287,19 @@ mod tests {
         assert!(parse_exif_datetime_ms("2024:13:01 00:00:00").is_none());
     }
 
+    #[test]
+    fn capture_time_anchors_round_trip() {
+        let ms = parse_capture_time("2026-06-21 21:14:00").unwrap();
+        assert_eq!(parse_capture_time("2026:06:21 21:14:00"), Some(ms));
+        assert_eq!(parse_capture_time("2026-06-21T21:14"), Some(ms));
+        assert_eq!(format_capture_time(ms), "2026-06-21 21:14:00");
+        assert_eq!(
+            format_capture_time(1_704_070_801_000),
+            "2024-01-01 01:00:01"
+        );
+        assert!(parse_capture_time("21:14").is_none());
+    }
+
     #[test]
     fn unreadable_file_yields_default() {
         assert_eq!(
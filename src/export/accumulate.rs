@@ -0,0 +1,329 @@
+//! Accumulation stage: star trails and other running combinations.
+//!
+//! Sits between the ordered renderer and the real sink. Every output frame
+//! is combined per pixel with the frames before it (lighten, darken,
+//! average or median, over everything so far or a sliding window), and the
+//! final combination is saved as a still when the export finishes.
+//!
+//! A window keeps its frames in memory. The windowed average is kept as a
+//! running sum; lighten, darken and median revisit every frame in the
+//! window, which is why windows stop at 64 frames.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use image::{imageops, DynamicImage, RgbImage};
+use rayon::prelude::*;
+
+use crate::error::Result;
+use crate::export::FrameSink;
+use crate::project::{Accumulate, AccumulateMode};
+use crate::render::save_image;
+
+/// Combines each frame with the frames before it.
+pub struct Accumulator {
+    settings: Accumulate,
+    size: Option<(u32, u32)>,
+    /// Running combination (without a window).
+    running: Vec<f32>,
+    frames: usize,
+    /// The frames in the window, oldest first.
+    recent: VecDeque<RgbImage>,
+    /// Windowed average: the sum of the frames in the window, each faded
+    /// by its age, kept up to date as frames enter and leave.
+    window_sum: Vec<f64>,
+}
+
+impl Accumulator {
+    pub fn new(settings: Accumulate) -> Self {
+        Self {
+            settings,
+            size: None,
+            running: Vec::new(),
+            frames: 0,
+            recent: VecDeque::new(),
+            window_sum: Vec::new(),
+        }
+    }
+
+    /// Add a frame and return the combination so far. Frames sized unlike
+    /// the first (a keyframed crop) are scaled to it.
+    pub fn push(&mut self, frame: &RgbImage) -> RgbImage {
+        let (width, height) = *self.size.get_or_insert(frame.dimensions());
+        let frame = if frame.dimensions() == (width, height) {
+            frame.clone()
+        } else {
+            imageops::resize(frame, width, height, imageops::FilterType::Triangle)
+        };
+        self.frames += 1;
+        let data = match self.settings.window {
+            Some(window) => {
+                let average = self.settings.mode == AccumulateMode::Average;
+                if average {
+                    self.add_to_window_sum(&frame, window);
+                }
+                self.recent.push_back(frame);
+                while self.recent.len() > window as usize {
+                    self.recent.pop_front();
+                }
+                if average {
+                    self.window_average()
+                } else {
+                    self.combine_window()
+                }
+            }
+            None => {
+                self.combine_running(&frame);
+                self.running
+                    .iter()
+                    .map(|v| v.round().clamp(0.0, 255.0) as u8)
+                    .collect()
+            }
+        };
+        RgbImage::from_raw(width, height, data).expect("buffer matches the frame size")
+    }
+
+    fn combine_running(&mut self, frame: &RgbImage) {
+        if self.running.is_empty() {
+            self.running = frame.iter().map(|&v| v as f32).collect();
+            return;
+        }
+        let keep = 1.0 - self.settings.decay;
+        let rate = self.settings.decay.max(1.0 / self.frames as f32);
+        for (acc, &v) in self.running.iter_mut().zip(frame.iter()) {
+            let v = v as f32;
+            // Faded toward the current frame, then combined with it.
+            let faded = v + (*acc - v) * keep;
+            *acc = match self.settings.mode {
+                AccumulateMode::Lighten => faded.max(v),
+                AccumulateMode::Darken => faded.min(v),
+                AccumulateMode::Average | AccumulateMode::Median => *acc + (v - *acc) * rate,
+            };
+        }
+    }
+
+    /// Fade the window sum by a frame, add `frame` and take out the frame
+    /// leaving the window: O(1) per pixel whatever the window.
+    fn add_to_window_sum(&mut self, frame: &RgbImage, window: u32) {
+        let keep = 1.0 - self.settings.decay as f64;
+        if self.window_sum.is_empty() {
+            self.window_sum = vec![0.0; frame.as_raw().len()];
+        }
+        let leaving = (self.recent.len() >= window as usize).then(|| &self.recent[0]);
+        let fade = keep.powi(window as i32);
+        self.window_sum
+            .par_iter_mut()
+            .enumerate()
+            .for_each(|(i, sum)| {
+                *sum = *sum * keep + frame.as_raw()[i] as f64;
+                if let Some(leaving) = leaving {
+                    *sum -= fade * leaving.as_raw()[i] as f64;
+                }
+            });
+    }
+
+    /// The mean of the window's frames, each faded toward the current frame
+    /// by its age: `v + (sum - v * weights) / count`.
+    fn window_average(&self) -> Vec<u8> {
+        let current = self.recent.back().expect("the current frame was pushed");
+        let keep = 1.0 - self.settings.decay as f64;
+        let count = self.recent.len();
+        let weights: f64 = (0..count).map(|age| keep.powi(age as i32)).sum();
+        current
+            .as_raw()
+            .par_iter()
+            .zip(self.window_sum.par_iter())
+            .map(|(&v, &sum)| {
+                let v = v as f64;
+                let mean = v + (sum - v * weights) / count as f64;
+                mean.round().clamp(0.0, 255.0) as u8
+            })
+            .collect()
+    }
+
+    /// Lighten, darken or median over the window: every frame in it is
+    /// looked at again for each output frame.
+    fn combine_window(&self) -> Vec<u8> {
+        let current = self.recent.back().expect("the current frame was pushed");
+        let keep = 1.0 - self.settings.decay;
+        let count = self.recent.len();
+        (0..current.as_raw().len())
+            .into_par_iter()
+            .map_init(Vec::new, |values, i| {
+                let v = current.as_raw()[i] as f32;
+                values.clear();
+                for (age, frame) in self.recent.iter().rev().enumerate() {
+                    let old = frame.as_raw()[i] as f32;
+                    values.push(v + (old - v) * keep.powi(age as i32));
+                }
+                let combined = match self.settings.mode {
+                    AccumulateMode::Lighten => values.iter().copied().fold(f32::MIN, f32::max),
+                    AccumulateMode::Darken => values.iter().copied().fold(f32::MAX, f32::min),
+                    AccumulateMode::Average => unreachable!("averaged from the window sum"),
+                    AccumulateMode::Median => {
+                        values.sort_by(f32::total_cmp);
+                        values[count / 2]
+                    }
+                };
+                combined.round().clamp(0.0, 255.0) as u8
+            })
+            .collect()
+    }
+}
+
+/// Where and how the final still is saved.
+pub struct StillOutput {
+    pub path: PathBuf,
+    pub format: String,
+    pub jpeg_quality: u8,
+}
+
+/// A sink stage that accumulates frames before passing them on, and saves
+/// the final still.
+pub struct AccumulateSink {
+    inner: Box<dyn FrameSink>,
+    frames: Accumulator,
+    /// Every frame combined without window or fading, for the still (the
+    /// median has no running form: its still is the last output frame).
+    still: Option<Accumulator>,
+    last: Option<RgbImage>,
+    output: StillOutput,
+}
+
+impl AccumulateSink {
+    pub fn new(settings: Accumulate, inner: Box<dyn FrameSink>, output: StillOutput) -> Self {
+        let plain = settings.window.is_none() && settings.decay == 0.0;
+        let still = (!plain && settings.mode != AccumulateMode::Median).then(|| {
+            Accumulator::new(Accumulate {
+                window: None,
+                decay: 0.0,
+                ..settings
+            })
+        });
+        Self {
+            inner,
+            frames: Accumulator::new(settings),
+            still,
+            last: None,
+            output,
+        }
+    }
+}
+
+impl FrameSink for AccumulateSink {
+    fn write_frame(&mut self, index: usize, frame: &RgbImage) -> Result<()> {
+        let combined = self.frames.push(frame);
+        self.inner.write_frame(index, &combined)?;
+        self.last = Some(match self.still.as_mut() {
+            Some(still) => still.push(frame),
+            None => combined,
+        });
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> Result<()> {
+        let Self {
+            inner,
+            last,
+            output,
+            ..
+        } = *self;
+        inner.finish()?;
+        if let Some(still) = last {
+            save_image(
+                &DynamicImage::ImageRgb8(still),
+                &output.path,
+                &output.format,
+                output.jpeg_quality,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gray(value: u8) -> RgbImage {
+        RgbImage::from_pixel(2, 2, image::Rgb([value; 3]))
+    }
+
+    fn run(settings: Accumulate, values: &[u8]) -> Vec<u8> {
+        let mut accumulator = Accumulator::new(settings);
+        values
+            .iter()
+            .map(|&v| accumulator.push(&gray(v)).get_pixel(0, 0)[0])
+            .collect()
+    }
+
+    fn settings(mode: AccumulateMode, window: Option<u32>, decay: f32) -> Accumulate {
+        Accumulate {
+            mode,
+            window,
+            decay,
+        }
+    }
+
+    #[test]
+    fn running_modes_combine_every_frame_so_far() {
+        let values = [10, 200, 50, 100];
+        assert_eq!(
+            run(settings(AccumulateMode::Lighten, None, 0.0), &values),
+            vec![10, 200, 200, 200]
+        );
+        assert_eq!(
+            run(settings(AccumulateMode::Darken, None, 0.0), &values),
+            vec![10, 10, 10, 10]
+        );
+        assert_eq!(
+            run(settings(AccumulateMode::Average, None, 0.0), &values),
+            vec![10, 105, 87, 90]
+        );
+    }
+
+    #[test]
+    fn windows_and_decay_let_trails_fade() {
+        let values = [200, 0, 0, 0];
+        assert_eq!(
+            run(settings(AccumulateMode::Lighten, Some(2), 0.0), &values),
+            vec![200, 200, 0, 0]
+        );
+        assert_eq!(
+            run(settings(AccumulateMode::Lighten, None, 0.5), &values),
+            vec![200, 100, 50, 25]
+        );
+        // A one-frame spike passes a median only while the window is short.
+        assert_eq!(
+            run(
+                settings(AccumulateMode::Median, Some(3), 0.0),
+                &[100, 250, 100, 0, 100]
+            ),
+            vec![100, 250, 100, 100, 100]
+        );
+    }
+
+    #[test]
+    fn windowed_average_matches_the_frames_in_the_window() {
+        let values = [10, 200, 50, 100, 250, 0, 30];
+        for decay in [0.0f32, 0.3] {
+            let expected: Vec<u8> = (0..values.len())
+                .map(|end| {
+                    let v = values[end] as f32;
+                    let window = &values[end.saturating_sub(2)..=end];
+                    let faded: f32 = window
+                        .iter()
+                        .rev()
+                        .enumerate()
+                        .map(|(age, &old)| v + (old as f32 - v) * (1.0 - decay).powi(age as i32))
+                        .sum();
+                    (faded / window.len() as f32).round() as u8
+                })
+                .collect();
+            assert_eq!(
+                run(settings(AccumulateMode::Average, Some(3), decay), &values),
+                expected
+            );
+        }
+    }
+}
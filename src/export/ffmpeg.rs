@@ -6,7 +6,7 @@ use std::thread::JoinHandle;
 use image::RgbImage;
 
 use crate::error::{LapsifyError, Result};
-use crate::export::{parse_resolution, FrameSink};
+use crate::export::FrameSink;
 use crate::project::{Codec, Project};
 
 /// Encodes frames by piping raw RGB24 to ffmpeg's stdin: no intermediate
@@ -38,26 +38,13 @@ impl FfmpegSink {
             .arg("-s")
             .arg(format!("{width}x{height}"))
             .arg("-framerate")
-            .arg(export.fps.to_string())
+            .arg(export.output_rate().to_string())
             .arg("-i")
             .arg("-");
 
-        // Motion blur (a sliding average over neighboring frames) runs
-        // before any scaling; then scale to the requested resolution
-        // (fitting within it, preserving aspect ratio) and force even
-        // dimensions for chroma subsampling.
-        let mut filters = Vec::new();
-        if let Some(blur) = export.motion_blur {
-            filters.push(format!("tmix=frames={blur}"));
-        }
-        if let Some(res) = export.resolution.as_deref() {
-            let (w, h) = parse_resolution(res)?;
-            filters.push(format!(
-                "scale={w}:{h}:force_original_aspect_ratio=decrease:flags=lanczos"
-            ));
-        }
-        filters.push("scale=trunc(iw/2)*2:trunc(ih/2)*2".to_string());
-        cmd.arg("-vf").arg(filters.join(","));
+        // Frames arrive at the output size; force even dimensions for
+        // chroma subsampling.
+        cmd.arg("-vf").arg("scale=trunc(iw/2)*2:trunc(ih/2)*2");
 
         match export.codec {
             Codec::H264 => {
@@ -1,86 +1,169 @@
 use std::fs;
+use std::path::PathBuf;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::time::Instant;
 
+use image::{DynamicImage, RgbImage};
 use rayon::prelude::*;
 
 use crate::error::{LapsifyError, Result};
+use crate::export::accumulate::{AccumulateSink, StillOutput};
+use crate::export::{export_plan, render_ordered, FrameSink};
+use crate::framing::output_size;
 use crate::progress::{ProgressEvent, ProgressReporter};
 use crate::project::Project;
-use crate::render::{generate_output_filename, render_frame, save_image};
-use crate::source::{list_images, scan_dimensions, select_frame_range};
+use crate::render::{generate_output_filename, save_image, PlanRenderer};
+use crate::source::{list_images, scan_dimensions, PlannedFrame};
 
 pub fn render_to_images(
     project: &Project,
     reporter: &ProgressReporter,
     start_time: Instant,
 ) -> Result<()> {
-    let output_path = &project.export.output;
-    fs::create_dir_all(output_path).map_err(|e| LapsifyError::io(output_path, e))?;
-
     let image_files = list_images(&project.input)?;
     let (src_w, src_h) = scan_dimensions(&image_files)?;
 
-    let (start_frame, end_frame) = match project.frame_range {
-        Some((start, end)) => (Some(start), Some(end)),
-        None => (None, None),
-    };
-    let (filtered_files, start_idx, _, _) =
-        select_frame_range(image_files, start_frame, end_frame)?;
+    let plan = export_plan(project, &image_files)?;
 
-    let total = filtered_files.len();
+    let total = plan.len();
     let output_format = &project.export.format;
     let jpeg_quality = project.export.jpeg_quality;
+    let (width, height) = output_size(project, src_w, src_h)?;
 
     reporter.report(ProgressEvent::Start {
         total_frames: total,
-        width: src_w,
-        height: src_h,
+        width,
+        height,
     });
 
-    let done = AtomicUsize::new(0);
-
-    // Image files are independent, so no ordering is needed: write in place
-    // from the rayon pool and count completions for progress.
-    let results: Vec<Result<()>> = filtered_files
-        .par_iter()
-        .enumerate()
-        .map(|(i, image_path)| {
-            let img = crate::source::load_frame(image_path)?;
+    if project.export.accumulate.is_some() {
+        // Each frame builds on the ones before: render in order.
+        let (sink, _) = open_sink(project, &image_files, &plan)?;
+        render_ordered(&image_files, &[project], &plan, vec![sink], reporter)?;
+    } else {
+        let output_files = output_files(project, &image_files, &plan)?;
+        let done = AtomicUsize::new(0);
+        let renderer = PlanRenderer::new(&image_files, project);
+
+        // Image files are independent, so no ordering is needed: write in
+        // place from the rayon pool and count completions for progress.
+        let results: Vec<Result<()>> = plan
+            .par_iter()
+            .zip(&output_files)
+            .enumerate()
+            .map(|(i, (planned, output_file_path))| {
+                let processed_img = DynamicImage::ImageRgb8(renderer.render(*planned)?);
+                save_image(
+                    &processed_img,
+                    output_file_path,
+                    output_format,
+                    jpeg_quality,
+                )?;
+
+                let current = done.fetch_add(1, Ordering::Relaxed) + 1;
+                reporter.report(ProgressEvent::Frame {
+                    index: i,
+                    done: current,
+                    total,
+                });
+
+                Ok(())
+            })
+            .collect();
+
+        for result in results {
+            result?;
+        }
+    }
 
-            // Global frame index keeps curve sampling aligned with the full sequence.
-            let global_frame_index = (start_idx + i) as u32;
-            let processed_img = render_frame(img, project, global_frame_index)?;
+    reporter.report(ProgressEvent::Done {
+        output: project.export.output.clone(),
+        elapsed_ms: start_time.elapsed().as_millis() as u64,
+    });
 
-            let output_filename = generate_output_filename(image_path, output_format);
-            let output_file_path = output_path.join(output_filename);
+    Ok(())
+}
 
-            save_image(
-                &processed_img,
-                &output_file_path,
-                output_format,
+/// A sink writing the frames of `plan`, delivered in order, to their files
+/// (and the accumulated still, when the project accumulates), and the
+/// folder they go to.
+pub fn open_sink(
+    project: &Project,
+    image_files: &[PathBuf],
+    plan: &[PlannedFrame],
+) -> Result<(Box<dyn FrameSink>, PathBuf)> {
+    let output_path = &project.export.output;
+    let format = &project.export.format;
+    let jpeg_quality = project.export.jpeg_quality;
+    let sink = ImageSink {
+        files: output_files(project, image_files, plan)?,
+        format: format.clone(),
+        jpeg_quality,
+    };
+    let sink: Box<dyn FrameSink> = match project.export.accumulate {
+        Some(accumulate) => Box::new(AccumulateSink::new(
+            accumulate,
+            Box::new(sink),
+            StillOutput {
+                path: output_path.join(format!("accumulated.{format}")),
+                format: format.clone(),
                 jpeg_quality,
-            )?;
-
-            let current = done.fetch_add(1, Ordering::Relaxed) + 1;
-            reporter.report(ProgressEvent::Frame {
-                index: i,
-                done: current,
-                total,
-            });
+            },
+        )),
+        None => Box::new(sink),
+    };
+    Ok((sink, output_path.clone()))
+}
 
-            Ok(())
+/// Create the output folder and name the file of each planned frame.
+///
+/// Source frame indices keep curve sampling aligned with the full
+/// sequence; a filled frame takes the excluded file's name. Retiming, speed
+/// ramps and frame-rate conversion repeat source frames, so their outputs
+/// are numbered.
+fn output_files(
+    project: &Project,
+    image_files: &[PathBuf],
+    plan: &[PlannedFrame],
+) -> Result<Vec<PathBuf>> {
+    let output_path = &project.export.output;
+    fs::create_dir_all(output_path).map_err(|e| LapsifyError::io(output_path, e))?;
+    let output_format = &project.export.format;
+    let resampled = project.export.retime.is_some()
+        || project.export.speed.is_some()
+        || project.export.rate_factor() != 1.0;
+    Ok(plan
+        .iter()
+        .enumerate()
+        .map(|(i, planned)| {
+            let name = if resampled {
+                format!("retimed_{i:06}.{output_format}")
+            } else {
+                generate_output_filename(&image_files[planned.frame()], output_format)
+            };
+            output_path.join(name)
         })
-        .collect();
+        .collect())
+}
 
-    for result in results {
-        result?;
-    }
+/// Writes frames delivered in order to their files.
+struct ImageSink {
+    files: Vec<PathBuf>,
+    format: String,
+    jpeg_quality: u8,
+}
 
-    reporter.report(ProgressEvent::Done {
-        output: output_path.clone(),
-        elapsed_ms: start_time.elapsed().as_millis() as u64,
-    });
+impl FrameSink for ImageSink {
+    fn write_frame(&mut self, index: usize, frame: &RgbImage) -> Result<()> {
+        save_image(
+            &DynamicImage::ImageRgb8(frame.clone()),
+            &self.files[index],
+            &self.format,
+            self.jpeg_quality,
+        )
+    }
 
-    Ok(())
+    fn finish(self: Box<Self>) -> Result<()> {
+        Ok(())
+    }
 }
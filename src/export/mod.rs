@@ -1,18 +1,26 @@
+pub mod accumulate;
 pub mod ffmpeg;
 pub mod images;
 pub mod video;
 
 use std::collections::BTreeMap;
 use std::path::PathBuf;
+use std::time::Instant;
 
 use crossbeam_channel::{bounded, Receiver};
 use image::RgbImage;
 use rayon::prelude::*;
 
 use crate::error::{LapsifyError, Result};
+use crate::framing::output_size;
 use crate::progress::{ProgressEvent, ProgressReporter};
 use crate::project::Project;
-use crate::render::render_frame;
+use crate::render::PlanRenderer;
+use crate::source::{
+    list_images, plan_frames, retime_frames, scan_dimensions, select_frame_range,
+    speed_ramp_frames, stack_frames, PlannedFrame, SourceFrames,
+};
+use crate::timeline::Timeline;
 
 /// A consumer of rendered frames. Frames arrive strictly in order.
 pub trait FrameSink: Send {
@@ -41,38 +49,172 @@ pub fn parse_resolution(resolution: &str) -> Result<(u32, u32)> {
     let height = parts[1].parse::<u32>().map_err(|_| {
         LapsifyError::message(format!("Invalid height in resolution: {}", parts[1]))
     })?;
+    if width == 0 || height == 0 {
+        return Err(LapsifyError::message(format!(
+            "Resolution must not be empty: {resolution}"
+        )));
+    }
 
     Ok((width, height))
 }
 
-/// Render frames in parallel and deliver them to the sink strictly in order.
+/// The output frames of an export: the frame range, less excluded frames
+/// (or with them filled), then resampled to a constant capture-time rate or
+/// along the speed ramp when the project asks for either, and to the output
+/// frame rate, or grouped into stacks (validation keeps stacking apart from
+/// resampling; filled frames stack around the frame they stand for).
+pub fn export_plan(project: &Project, image_files: &[PathBuf]) -> Result<Vec<PlannedFrame>> {
+    let (start_frame, end_frame) = match project.frame_range {
+        Some((start, end)) => (Some(start), Some(end)),
+        None => (None, None),
+    };
+    let (_, start_idx, end_idx, source_frames) =
+        select_frame_range(image_files.to_vec(), start_frame, end_frame)?;
+    let plan = plan_frames(
+        start_idx,
+        end_idx,
+        source_frames,
+        &project.excluded_frames,
+        project.export.fill_excluded,
+    )?;
+    match project.export.stack {
+        Some(stack) => Ok(stack_frames(&plan, stack, (start_idx, end_idx))),
+        None => resample_plan(project, plan, source_frames),
+    }
+}
+
+fn resample_plan(
+    project: &Project,
+    plan: Vec<PlannedFrame>,
+    source_frames: usize,
+) -> Result<Vec<PlannedFrame>> {
+    let rate_factor = project.export.rate_factor();
+    if let Some(retime) = project.export.retime {
+        // Only times still matching the source folder pace the output.
+        let times = project
+            .capture_times()
+            .filter(|times| {
+                times.len() == source_frames && times.windows(2).all(|w| w[1] > w[0])
+            })
+            .ok_or_else(|| {
+                LapsifyError::message(
+                    "Retiming needs increasing capture times of the current source frames; run `lapsify analyze holygrail` to read them",
+                )
+            })?;
+        return retime_frames(&plan, times, retime, rate_factor);
+    }
+    if let Some(ref speed) = project.export.speed {
+        let timeline = Timeline::of(project, source_frames);
+        return Ok(speed_ramp_frames(&plan, |frame| {
+            (speed.sample_on(frame, &timeline) as f64 / rate_factor) as f32
+        }));
+    }
+    if rate_factor != 1.0 {
+        let step = (1.0 / rate_factor) as f32;
+        return Ok(speed_ramp_frames(&plan, |_| step));
+    }
+    Ok(plan)
+}
+
+/// Render every output of a project — the main export, then each of
+/// `outputs` — reporting `Done` for each at the end. Outputs whose frames
+/// line up (the same plan) render in one pass that decodes each source frame
+/// once for all of them; an output with its own timing gets its own pass.
+pub fn render_project(
+    project: &Project,
+    reporter: &ProgressReporter,
+    start_time: Instant,
+) -> Result<()> {
+    if project.outputs.is_empty() {
+        return if project.is_video_output() {
+            video::render_to_video(project, reporter, start_time)
+        } else {
+            images::render_to_images(project, reporter, start_time)
+        };
+    }
+
+    let image_files = list_images(&project.input)?;
+    let (src_w, src_h) = scan_dimensions(&image_files)?;
+    let renders = project.renders();
+    let mut passes: Vec<(Vec<PlannedFrame>, Vec<&Project>)> = Vec::new();
+    for render in &renders {
+        let plan = export_plan(render, &image_files)?;
+        match passes.iter_mut().find(|(shared, _)| *shared == plan) {
+            Some((_, group)) => group.push(render),
+            None => passes.push((plan, vec![render])),
+        }
+    }
+
+    let mut outputs = Vec::new();
+    for (plan, group) in &passes {
+        let mut sinks = Vec::new();
+        let mut sizes = Vec::new();
+        for render in group {
+            let (width, height) = output_size(render, src_w, src_h)?;
+            let (sink, output) = if render.is_video_output() {
+                video::open_sink(render, width, height)?
+            } else {
+                images::open_sink(render, &image_files, plan)?
+            };
+            sinks.push(sink);
+            sizes.push((width, height));
+            outputs.push(output);
+        }
+        reporter.report(ProgressEvent::Start {
+            total_frames: plan.len(),
+            width: sizes[0].0,
+            height: sizes[0].1,
+        });
+        render_ordered(&image_files, group, plan, sinks, reporter)?;
+    }
+
+    for output in outputs {
+        reporter.report(ProgressEvent::Done {
+            output,
+            elapsed_ms: start_time.elapsed().as_millis() as u64,
+        });
+    }
+    Ok(())
+}
+
+/// Render the output frames of a plan in parallel for each of `projects`
+/// and deliver them to the matching sink strictly in order. `files` is the
+/// full source sequence the plan indexes into; the projects of one call
+/// share each frame's decoded sources.
 ///
 /// Rendering fans out over rayon; a bounded channel provides backpressure and
 /// a dedicated writer thread reorders results (work-stealing keeps in-flight
 /// indices close together, so the reorder buffer stays small).
 pub fn render_ordered(
     files: &[PathBuf],
-    project: &Project,
-    start_idx: usize,
-    prepare: impl Fn(RgbImage) -> RgbImage + Sync,
-    sink: Box<dyn FrameSink>,
+    projects: &[&Project],
+    plan: &[PlannedFrame],
+    sinks: Vec<Box<dyn FrameSink>>,
     reporter: &ProgressReporter,
 ) -> Result<()> {
-    let total = files.len();
-    let (tx, rx) = bounded::<(usize, RgbImage)>(2 * rayon::current_num_threads());
+    let total = plan.len();
+    let renderers: Vec<PlanRenderer> = projects
+        .iter()
+        .map(|project| PlanRenderer::new(files, project))
+        .collect();
+    let (tx, rx) = bounded::<(usize, Vec<RgbImage>)>(2 * rayon::current_num_threads());
 
     std::thread::scope(|scope| {
-        let writer = scope.spawn(move || deliver_ordered(rx, sink, reporter, total));
+        let writer = scope.spawn(move || deliver_ordered(rx, sinks, reporter, total));
 
         let produced =
-            files
-                .par_iter()
+            plan.par_iter()
                 .enumerate()
-                .try_for_each_with(tx, |tx, (i, path)| -> Result<()> {
-                    let img = crate::source::load_frame(path)?;
-                    let frame = render_frame(img, project, (start_idx + i) as u32)?;
-                    let frame = prepare(frame.into_rgb8());
-                    tx.send((i, frame))
+                .try_for_each_with(tx, |tx, (i, planned)| -> Result<()> {
+                    let sources = match renderers.len() {
+                        1 => SourceFrames::new(files),
+                        _ => SourceFrames::shared(files),
+                    };
+                    let frames = renderers
+                        .iter()
+                        .map(|renderer| renderer.render_from(*planned, &sources))
+                        .collect::<Result<Vec<_>>>()?;
+                    tx.send((i, frames))
                         .map_err(|_| LapsifyError::message("frame writer terminated early"))?;
                     Ok(())
                 });
@@ -88,18 +230,20 @@ pub fn render_ordered(
 }
 
 fn deliver_ordered(
-    rx: Receiver<(usize, RgbImage)>,
-    mut sink: Box<dyn FrameSink>,
+    rx: Receiver<(usize, Vec<RgbImage>)>,
+    mut sinks: Vec<Box<dyn FrameSink>>,
     reporter: &ProgressReporter,
     total: usize,
 ) -> Result<()> {
-    let mut pending: BTreeMap<usize, RgbImage> = BTreeMap::new();
+    let mut pending: BTreeMap<usize, Vec<RgbImage>> = BTreeMap::new();
     let mut next = 0usize;
 
-    for (index, frame) in rx.iter() {
-        pending.insert(index, frame);
+    for (index, frames) in rx.iter() {
+        pending.insert(index, frames);
         while let Some(ready) = pending.remove(&next) {
-            sink.write_frame(next, &ready)?;
+            for (sink, frame) in sinks.iter_mut().zip(&ready) {
+                sink.write_frame(next, frame)?;
+            }
             next += 1;
             reporter.report(ProgressEvent::Frame {
                 index: next - 1,
@@ -116,7 +260,15 @@ fn deliver_ordered(
         )));
     }
 
-    sink.finish()
+    // Finish every sink (closing each encoder) before reporting a failure.
+    let mut finished = Ok(());
+    for sink in sinks {
+        let result = sink.finish();
+        if finished.is_ok() {
+            finished = result;
+        }
+    }
+    finished
 }
 
 #[cfg(test)]
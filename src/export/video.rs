@@ -1,44 +1,30 @@
 use std::fs;
+use std::path::PathBuf;
 use std::time::Instant;
 
-use image::imageops::{self, FilterType};
-
 use crate::error::{LapsifyError, Result};
+use crate::export::accumulate::{AccumulateSink, StillOutput};
 use crate::export::ffmpeg::FfmpegSink;
-use crate::export::render_ordered;
+use crate::export::{export_plan, render_ordered, FrameSink};
+use crate::framing::output_size;
 use crate::progress::{ProgressEvent, ProgressReporter};
 use crate::project::Project;
-use crate::source::{list_images, scan_dimensions, select_frame_range};
+use crate::source::{list_images, scan_dimensions};
 
 pub fn render_to_video(
     project: &Project,
     reporter: &ProgressReporter,
     start_time: Instant,
 ) -> Result<()> {
-    let output_path = &project.export.output;
-    fs::create_dir_all(output_path).map_err(|e| LapsifyError::io(output_path, e))?;
-
     let image_files = list_images(&project.input)?;
     let (src_w, src_h) = scan_dimensions(&image_files)?;
 
-    let (start_frame, end_frame) = match project.frame_range {
-        Some((start, end)) => (Some(start), Some(end)),
-        None => (None, None),
-    };
-    let (filtered_files, start_idx, _, _) =
-        select_frame_range(image_files, start_frame, end_frame)?;
-    let total = filtered_files.len();
+    let plan = export_plan(project, &image_files)?;
+    let total = plan.len();
 
-    // Raw video needs a fixed frame size. With a keyframed crop the window
-    // size can vary per frame, so every frame is scaled to the size of the
-    // first processed frame's window (that scaling is exactly a zoom).
-    let (target_w, target_h) = match &project.crop {
-        Some(track) => {
-            let (_, _, w, h) = track.pixel_rect(start_idx as u32, src_w, src_h)?;
-            (w, h)
-        }
-        None => (src_w, src_h),
-    };
+    // Raw video needs a fixed frame size; the renderer places every frame's
+    // picture in the one output size.
+    let (target_w, target_h) = output_size(project, src_w, src_h)?;
 
     reporter.report(ProgressEvent::Start {
         total_frames: total,
@@ -46,22 +32,8 @@ pub fn render_to_video(
         height: target_h,
     });
 
-    let (sink, output_file) = FfmpegSink::spawn(project, target_w, target_h)?;
-
-    render_ordered(
-        &filtered_files,
-        project,
-        start_idx,
-        |frame| {
-            if frame.dimensions() == (target_w, target_h) {
-                frame
-            } else {
-                imageops::resize(&frame, target_w, target_h, FilterType::Lanczos3)
-            }
-        },
-        Box::new(sink),
-        reporter,
-    )?;
+    let (sink, output_file) = open_sink(project, target_w, target_h)?;
+    render_ordered(&image_files, &[project], &plan, vec![sink], reporter)?;
 
     reporter.report(ProgressEvent::Done {
         output: output_file,
@@ -70,3 +42,29 @@ pub fn render_to_video(
 
     Ok(())
 }
+
+/// Start the encoder for `width`x`height` frames (accumulating them first
+/// when the project asks), returning it and the video file it writes.
+pub fn open_sink(
+    project: &Project,
+    width: u32,
+    height: u32,
+) -> Result<(Box<dyn FrameSink>, PathBuf)> {
+    let output_path = &project.export.output;
+    fs::create_dir_all(output_path).map_err(|e| LapsifyError::io(output_path, e))?;
+
+    let (sink, output_file) = FfmpegSink::spawn(project, width, height)?;
+    let sink: Box<dyn FrameSink> = match project.export.accumulate {
+        Some(accumulate) => Box::new(AccumulateSink::new(
+            accumulate,
+            Box::new(sink),
+            StillOutput {
+                path: output_file.with_extension("png"),
+                format: "png".to_string(),
+                jpeg_quality: project.export.jpeg_quality,
+            },
+        )),
+        None => Box::new(sink),
+    };
+    Ok((sink, output_file))
+}
@@ -0,0 +1,494 @@
+//! A small, sandboxed expression language for formula-driven curves.
+//!
+//! Expressions are pure arithmetic over a fixed set of variables and
+//! functions: no assignment, loops, recursion or I/O, and the parser bounds
+//! both source length and nesting depth, so evaluating one is always cheap
+//! and always terminates.
+//!
+//! ```text
+//! 0.3 * sin(2 * pi * time / 600)      // breathing, one cycle per 10 minutes
+//! lerp(-1, 2, smoothstep(0.2, 0.8, t))
+//! ```
+//!
+//! Variables: `frame` (source frame index), `t` (normalized clip position,
+//! 0 at the first frame and 1 at the last), `time` (seconds since the first
+//! capture; the frame index when there are no capture timestamps), `pi`,
+//! `e`. Operators: `+ - * / % ^` and unary minus, with `^` binding tightest
+//! and right-associative.
+
+use std::fmt;
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const MAX_SOURCE_LEN: usize = 1024;
+const MAX_DEPTH: usize = 64;
+
+/// Values an expression is evaluated against.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Vars {
+    pub frame: f32,
+    pub t: f32,
+    pub time: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Var {
+    Frame,
+    T,
+    Time,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Func {
+    Sin,
+    Cos,
+    Tan,
+    Abs,
+    Sqrt,
+    Exp,
+    Ln,
+    Log2,
+    Floor,
+    Ceil,
+    Round,
+    Min,
+    Max,
+    Pow,
+    Step,
+    Clamp,
+    Lerp,
+    Smoothstep,
+}
+
+impl Func {
+    fn lookup(name: &str) -> Option<(Self, usize)> {
+        Some(match name {
+            "sin" => (Self::Sin, 1),
+            "cos" => (Self::Cos, 1),
+            "tan" => (Self::Tan, 1),
+            "abs" => (Self::Abs, 1),
+            "sqrt" => (Self::Sqrt, 1),
+            "exp" => (Self::Exp, 1),
+            "ln" => (Self::Ln, 1),
+            "log2" => (Self::Log2, 1),
+            "floor" => (Self::Floor, 1),
+            "ceil" => (Self::Ceil, 1),
+            "round" => (Self::Round, 1),
+            "min" => (Self::Min, 2),
+            "max" => (Self::Max, 2),
+            "pow" => (Self::Pow, 2),
+            "step" => (Self::Step, 2),
+            "clamp" => (Self::Clamp, 3),
+            "lerp" => (Self::Lerp, 3),
+            "smoothstep" => (Self::Smoothstep, 3),
+            _ => return None,
+        })
+    }
+
+    fn apply(self, a: &[f32]) -> f32 {
+        match self {
+            Self::Sin => a[0].sin(),
+            Self::Cos => a[0].cos(),
+            Self::Tan => a[0].tan(),
+            Self::Abs => a[0].abs(),
+            Self::Sqrt => a[0].sqrt(),
+            Self::Exp => a[0].exp(),
+            Self::Ln => a[0].ln(),
+            Self::Log2 => a[0].log2(),
+            Self::Floor => a[0].floor(),
+            Self::Ceil => a[0].ceil(),
+            Self::Round => a[0].round(),
+            Self::Min => a[0].min(a[1]),
+            Self::Max => a[0].max(a[1]),
+            Self::Pow => a[0].powf(a[1]),
+            Self::Step => {
+                if a[1] < a[0] {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+            Self::Clamp => a[0].max(a[1]).min(a[2]),
+            Self::Lerp => a[0] + (a[1] - a[0]) * a[2],
+            Self::Smoothstep => {
+                let x = ((a[2] - a[0]) / (a[1] - a[0])).clamp(0.0, 1.0);
+                x * x * (3.0 - 2.0 * x)
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Node {
+    Num(f32),
+    Var(Var),
+    Neg(Box<Node>),
+    Bin(char, Box<Node>, Box<Node>),
+    Call(Func, Vec<Node>),
+}
+
+impl Node {
+    fn eval(&self, vars: &Vars) -> f32 {
+        match self {
+            Node::Num(v) => *v,
+            Node::Var(Var::Frame) => vars.frame,
+            Node::Var(Var::T) => vars.t,
+            Node::Var(Var::Time) => vars.time,
+            Node::Neg(a) => -a.eval(vars),
+            Node::Bin(op, a, b) => {
+                let (a, b) = (a.eval(vars), b.eval(vars));
+                match op {
+                    '+' => a + b,
+                    '-' => a - b,
+                    '*' => a * b,
+                    '/' => a / b,
+                    '%' => a.rem_euclid(b),
+                    _ => a.powf(b),
+                }
+            }
+            Node::Call(func, args) => {
+                let values: Vec<f32> = args.iter().map(|a| a.eval(vars)).collect();
+                func.apply(&values)
+            }
+        }
+    }
+}
+
+/// A parsed expression. Serializes as its source text; a source that fails
+/// to parse still deserializes (so the project loads) and is reported by
+/// `Curve::validate`.
+#[derive(Clone)]
+pub struct Expression {
+    source: String,
+    parsed: std::result::Result<Node, String>,
+}
+
+impl Expression {
+    pub fn parse(source: &str) -> std::result::Result<Self, String> {
+        let expression = Self::new(source);
+        expression.parsed.as_ref().map_err(|e| e.clone())?;
+        Ok(expression)
+    }
+
+    fn new(source: &str) -> Self {
+        Self {
+            source: source.to_string(),
+            parsed: Parser::parse(source),
+        }
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// The parse error, if the source is not a valid expression.
+    pub fn error(&self) -> Option<&str> {
+        self.parsed.as_ref().err().map(String::as_str)
+    }
+
+    /// Evaluate; an invalid expression evaluates to 0.
+    pub fn eval(&self, vars: &Vars) -> f32 {
+        match &self.parsed {
+            Ok(node) => node.eval(vars),
+            Err(_) => 0.0,
+        }
+    }
+}
+
+impl fmt::Debug for Expression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Expression({:?})", self.source)
+    }
+}
+
+impl PartialEq for Expression {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+impl Serialize for Expression {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for Expression {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let source = String::deserialize(deserializer)?;
+        Ok(Self::new(&source))
+    }
+}
+
+impl schemars::JsonSchema for Expression {
+    fn schema_name() -> std::borrow::Cow<'static, str> {
+        "Expression".into()
+    }
+
+    fn json_schema(_: &mut schemars::SchemaGenerator) -> schemars::Schema {
+        schemars::json_schema!({
+            "type": "string",
+            "description": "Formula over frame, t (0..1 clip position) and time (seconds since first capture)"
+        })
+    }
+}
+
+struct Parser<'s> {
+    src: &'s [u8],
+    pos: usize,
+    depth: usize,
+}
+
+impl<'s> Parser<'s> {
+    fn parse(source: &'s str) -> std::result::Result<Node, String> {
+        if source.len() > MAX_SOURCE_LEN {
+            return Err(format!(
+                "expression is longer than {MAX_SOURCE_LEN} characters"
+            ));
+        }
+        let mut parser = Parser {
+            src: source.as_bytes(),
+            pos: 0,
+            depth: 0,
+        };
+        let node = parser.sum()?;
+        parser.skip_space();
+        match parser.peek() {
+            None => Ok(node),
+            Some(c) => Err(format!(
+                "unexpected '{}' at position {}",
+                c as char, parser.pos
+            )),
+        }
+    }
+
+    fn skip_space(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.pos += 1;
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_space();
+        if self.peek() == Some(c) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn enter(&mut self) -> std::result::Result<(), String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            Err(format!("expression nests deeper than {MAX_DEPTH} levels"))
+        } else {
+            Ok(())
+        }
+    }
+
+    fn sum(&mut self) -> std::result::Result<Node, String> {
+        let mut node = self.product()?;
+        loop {
+            let op = if self.eat(b'+') {
+                '+'
+            } else if self.eat(b'-') {
+                '-'
+            } else {
+                return Ok(node);
+            };
+            node = Node::Bin(op, Box::new(node), Box::new(self.product()?));
+        }
+    }
+
+    fn product(&mut self) -> std::result::Result<Node, String> {
+        let mut node = self.unary()?;
+        loop {
+            let op = if self.eat(b'*') {
+                '*'
+            } else if self.eat(b'/') {
+                '/'
+            } else if self.eat(b'%') {
+                '%'
+            } else {
+                return Ok(node);
+            };
+            node = Node::Bin(op, Box::new(node), Box::new(self.unary()?));
+        }
+    }
+
+    fn unary(&mut self) -> std::result::Result<Node, String> {
+        self.enter()?;
+        let node = if self.eat(b'-') {
+            Node::Neg(Box::new(self.unary()?))
+        } else {
+            self.power()?
+        };
+        self.depth -= 1;
+        Ok(node)
+    }
+
+    fn power(&mut self) -> std::result::Result<Node, String> {
+        let base = self.atom()?;
+        if self.eat(b'^') {
+            // Right-associative, and binds tighter than unary minus on its
+            // left: -2^2 = -4.
+            Ok(Node::Bin('^', Box::new(base), Box::new(self.unary()?)))
+        } else {
+            Ok(base)
+        }
+    }
+
+    fn atom(&mut self) -> std::result::Result<Node, String> {
+        self.skip_space();
+        let start = self.pos;
+        match self.peek() {
+            Some(b'(') => {
+                self.pos += 1;
+                self.enter()?;
+                let node = self.sum()?;
+                self.depth -= 1;
+                if !self.eat(b')') {
+                    return Err(format!("missing ')' for '(' at position {start}"));
+                }
+                Ok(node)
+            }
+            Some(c) if c.is_ascii_digit() || c == b'.' => {
+                while self.peek().is_some_and(|c| c.is_ascii_digit() || c == b'.') {
+                    self.pos += 1;
+                }
+                if matches!(self.peek(), Some(b'e' | b'E'))
+                    && self
+                        .src
+                        .get(self.pos + 1)
+                        .is_some_and(|c| c.is_ascii_digit() || *c == b'-' || *c == b'+')
+                {
+                    self.pos += 2;
+                    while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                        self.pos += 1;
+                    }
+                }
+                let text = std::str::from_utf8(&self.src[start..self.pos]).unwrap();
+                text.parse::<f32>()
+                    .map(Node::Num)
+                    .map_err(|_| format!("invalid number '{text}' at position {start}"))
+            }
+            Some(c) if c.is_ascii_alphabetic() || c == b'_' => {
+                while self
+                    .peek()
+                    .is_some_and(|c| c.is_ascii_alphanumeric() || c == b'_')
+                {
+                    self.pos += 1;
+                }
+                let name = std::str::from_utf8(&self.src[start..self.pos]).unwrap();
+                match name {
+                    "frame" => return Ok(Node::Var(Var::Frame)),
+                    "t" => return Ok(Node::Var(Var::T)),
+                    "time" => return Ok(Node::Var(Var::Time)),
+                    "pi" => return Ok(Node::Num(std::f32::consts::PI)),
+                    "e" => return Ok(Node::Num(std::f32::consts::E)),
+                    _ => {}
+                }
+                let (func, arity) = Func::lookup(name)
+                    .ok_or_else(|| format!("unknown name '{name}' at position {start}"))?;
+                if !self.eat(b'(') {
+                    return Err(format!("'{name}' is a function; call it as {name}(...)"));
+                }
+                self.enter()?;
+                let mut args = vec![self.sum()?];
+                while self.eat(b',') {
+                    args.push(self.sum()?);
+                }
+                self.depth -= 1;
+                if !self.eat(b')') {
+                    return Err(format!("missing ')' after arguments of '{name}'"));
+                }
+                if args.len() != arity {
+                    return Err(format!(
+                        "'{name}' takes {arity} argument(s), got {}",
+                        args.len()
+                    ));
+                }
+                Ok(Node::Call(func, args))
+            }
+            Some(c) => Err(format!("unexpected '{}' at position {start}", c as char)),
+            None => Err("unexpected end of expression".to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    fn eval(source: &str, vars: Vars) -> f32 {
+        Expression::parse(source).unwrap().eval(&vars)
+    }
+
+    const AT_ZERO: Vars = Vars {
+        frame: 0.0,
+        t: 0.0,
+        time: 0.0,
+    };
+
+    #[test]
+    fn precedence_and_associativity() {
+        assert_relative_eq!(eval("1 + 2 * 3", AT_ZERO), 7.0);
+        assert_relative_eq!(eval("(1 + 2) * 3", AT_ZERO), 9.0);
+        assert_relative_eq!(eval("2 ^ 3 ^ 2", AT_ZERO), 512.0);
+        assert_relative_eq!(eval("-2 ^ 2", AT_ZERO), -4.0);
+        assert_relative_eq!(eval("10 - 4 - 3", AT_ZERO), 3.0);
+        assert_relative_eq!(eval("-7 % 3", AT_ZERO), 2.0);
+        assert_relative_eq!(eval("1.5e2 / 3", AT_ZERO), 50.0);
+    }
+
+    #[test]
+    fn variables_and_functions() {
+        let vars = Vars {
+            frame: 30.0,
+            t: 0.5,
+            time: 120.0,
+        };
+        assert_relative_eq!(eval("frame / 10", vars), 3.0);
+        assert_relative_eq!(eval("lerp(-1, 3, t)", vars), 1.0);
+        assert_relative_eq!(eval("sin(2 * pi * time / 480)", vars), 1.0, epsilon = 1e-5);
+        assert_relative_eq!(eval("clamp(time, 0, 100)", vars), 100.0);
+        assert_relative_eq!(eval("smoothstep(0, 1, t)", vars), 0.5);
+        assert_relative_eq!(eval("step(0.6, t)", vars), 0.0);
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_run() {
+        for bad in [
+            "",
+            "1 +",
+            "foo",
+            "sin",
+            "sin(1, 2)",
+            "(1",
+            "1 2",
+            "frame = 3",
+            "max(1)",
+        ] {
+            assert!(Expression::parse(bad).is_err(), "accepted {bad:?}");
+        }
+        let deep = format!("{}1{}", "(".repeat(100), ")".repeat(100));
+        assert!(Expression::parse(&deep).unwrap_err().contains("nests"));
+        assert!(Expression::parse(&"1+".repeat(600)).is_err());
+    }
+
+    #[test]
+    fn serializes_as_source_and_keeps_invalid_input() {
+        let expression: Expression = serde_json::from_str(r#""0.5 * t""#).unwrap();
+        assert_eq!(serde_json::to_string(&expression).unwrap(), r#""0.5 * t""#);
+
+        let broken: Expression = serde_json::from_str(r#""0.5 *""#).unwrap();
+        assert!(broken.error().is_some());
+        assert_eq!(broken.eval(&AT_ZERO), 0.0);
+    }
+}
@@ -0,0 +1,597 @@
+//! Motion-compensated frame interpolation.
+//!
+//! Blending two frames for a moment between them shows both positions of
+//! anything that moved — double images of drifting clouds. Instead, the
+//! motion from the first frame to the second is estimated, and both frames
+//! are warped to the in-between moment along it before they are mixed.
+//!
+//! Motion is estimated by coarse-to-fine block matching on a small gray
+//! copy of the frames: each pyramid level refines the level above within a
+//! few pixels, so large motions stay cheap to find, and the last level is
+//! refined to subpixel precision.
+
+use image::{imageops, RgbImage};
+use rayon::prelude::*;
+
+/// Long edge of the gray copy motion is estimated on.
+const WORK_DIM: u32 = 512;
+/// Block size in pixels, at every pyramid level.
+const BLOCK: usize = 8;
+/// Search radius around the prediction from the coarser level.
+const SEARCH: i32 = 3;
+/// Pyramid levels stop halving below this size.
+const MIN_LEVEL_DIM: usize = 32;
+/// Cost added per pixel of motion, so flat areas stay still.
+const MOTION_PENALTY: f32 = 0.5;
+
+/// Per-block motion from one frame to the next.
+pub struct FlowField {
+    cols: usize,
+    rows: usize,
+    /// Full-resolution pixels per work pixel.
+    scale: f32,
+    /// Motion of each block, in work pixels, row by row.
+    vectors: Vec<(f32, f32)>,
+    /// Mean absolute deviation of each block of the first frame: how much
+    /// there was to match on.
+    texture: Vec<f32>,
+}
+
+impl FlowField {
+    /// Motion at a full-resolution position, in full-resolution pixels,
+    /// interpolated between block centers.
+    pub fn at(&self, x: f32, y: f32) -> (f32, f32) {
+        let half = BLOCK as f32 / 2.0;
+        let fx = (x / self.scale - half) / BLOCK as f32;
+        let fy = (y / self.scale - half) / BLOCK as f32;
+        let fx = fx.clamp(0.0, (self.cols - 1) as f32);
+        let fy = fy.clamp(0.0, (self.rows - 1) as f32);
+        let (x0, y0) = (fx.floor() as usize, fy.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(self.cols - 1), (y0 + 1).min(self.rows - 1));
+        let (tx, ty) = (fx - x0 as f32, fy - y0 as f32);
+        let v = |cx: usize, cy: usize| self.vectors[cy * self.cols + cx];
+        let lerp =
+            |a: (f32, f32), b: (f32, f32), t: f32| (a.0 + (b.0 - a.0) * t, a.1 + (b.1 - a.1) * t);
+        let top = lerp(v(x0, y0), v(x1, y0), tx);
+        let bottom = lerp(v(x0, y1), v(x1, y1), tx);
+        let (dx, dy) = lerp(top, bottom, ty);
+        (dx * self.scale, dy * self.scale)
+    }
+
+    /// Every block as (center, motion, texture), positions and motion in
+    /// full-resolution pixels.
+    pub fn blocks(&self) -> impl Iterator<Item = ((f32, f32), (f32, f32), f32)> + '_ {
+        let half = BLOCK as f32 / 2.0;
+        self.vectors
+            .iter()
+            .zip(&self.texture)
+            .enumerate()
+            .map(move |(i, (&(dx, dy), &texture))| {
+                let x = ((i % self.cols) * BLOCK) as f32 + half;
+                let y = ((i / self.cols) * BLOCK) as f32 + half;
+                (
+                    (x * self.scale, y * self.scale),
+                    (dx * self.scale, dy * self.scale),
+                    texture,
+                )
+            })
+    }
+}
+
+/// Estimate the motion from `a` to `b` (same size).
+pub fn estimate_flow(a: &RgbImage, b: &RgbImage) -> FlowField {
+    let (width, height) = a.dimensions();
+    let pa = pyramid(Plane::gray(a, WORK_DIM));
+    let pb = pyramid(Plane::gray(b, WORK_DIM));
+
+    let mut field: Option<(usize, Vec<(f32, f32)>)> = None;
+    for (level, (la, lb)) in pa.iter().zip(&pb).enumerate().rev() {
+        let cols = la.w.div_ceil(BLOCK);
+        let rows = la.h.div_ceil(BLOCK);
+        let coarse = field.take();
+        let vectors: Vec<(f32, f32)> = (0..rows * cols)
+            .into_par_iter()
+            .map(|i| {
+                let (bx, by) = ((i % cols) * BLOCK, (i / cols) * BLOCK);
+                // The coarser level's motion, at twice the scale.
+                let predicted = coarse.as_ref().map_or((0, 0), |(coarse_cols, v)| {
+                    let cx = (bx / 2 / BLOCK).min(coarse_cols - 1);
+                    let cy = (by / 2 / BLOCK).min(v.len() / coarse_cols - 1);
+                    let (dx, dy) = v[cy * coarse_cols + cx];
+                    ((dx * 2.0).round() as i32, (dy * 2.0).round() as i32)
+                });
+                match_block(la, lb, bx, by, predicted, level == 0)
+            })
+            .collect();
+        field = Some((cols, median_filter(&vectors, cols)));
+    }
+
+    let (cols, vectors) = field.expect("the pyramid has at least one level");
+    let texture = (0..vectors.len())
+        .map(|i| pa[0].texture((i % cols) * BLOCK, (i / cols) * BLOCK))
+        .collect();
+    FlowField {
+        cols,
+        rows: vectors.len() / cols,
+        scale: width.max(height) as f32 / pa[0].w.max(pa[0].h) as f32,
+        vectors,
+        texture,
+    }
+}
+
+/// The moment `t` of the way from `a` to `b`: both frames warped along the
+/// motion between them, then mixed.
+pub fn interpolate(a: &RgbImage, b: &RgbImage, t: f32) -> RgbImage {
+    let flow = estimate_flow(a, b);
+    let (width, height) = a.dimensions();
+    let mut out = RgbImage::new(width, height);
+    out.par_chunks_mut(width as usize * 3)
+        .enumerate()
+        .for_each(|(y, row)| {
+            let y = y as f32;
+            for (x, pixel) in row.chunks_mut(3).enumerate() {
+                let x = x as f32;
+                let (dx, dy) = flow.at(x, y);
+                let from = sample_rgb(a, x - t * dx, y - t * dy);
+                let to = sample_rgb(b, x + (1.0 - t) * dx, y + (1.0 - t) * dy);
+                for c in 0..3 {
+                    pixel[c] = (from[c] + (to[c] - from[c]) * t).round().clamp(0.0, 255.0) as u8;
+                }
+            }
+        });
+    out
+}
+
+/// Motion of a whole frame: `p` maps to `to + [a -b; b a](p - from)`, a
+/// shift plus a rotation by `atan2(b, a)` and a scale by `hypot(a, b)`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GlobalMotion {
+    pub from: (f32, f32),
+    pub to: (f32, f32),
+    pub a: f32,
+    pub b: f32,
+}
+
+impl GlobalMotion {
+    pub const IDENTITY: Self = Self {
+        from: (0.0, 0.0),
+        to: (0.0, 0.0),
+        a: 1.0,
+        b: 0.0,
+    };
+
+    pub fn apply(&self, (x, y): (f32, f32)) -> (f32, f32) {
+        let (dx, dy) = (x - self.from.0, y - self.from.1);
+        (
+            self.to.0 + self.a * dx - self.b * dy,
+            self.to.1 + self.b * dx + self.a * dy,
+        )
+    }
+
+    /// The same motion with pixel coordinates scaled by `factor` (from a
+    /// thumbnail to the full frame).
+    pub fn scaled(self, factor: f32) -> Self {
+        Self {
+            from: (self.from.0 * factor, self.from.1 * factor),
+            to: (self.to.0 * factor, self.to.1 * factor),
+            ..self
+        }
+    }
+
+    /// This motion followed by `next`.
+    pub fn then(self, next: Self) -> Self {
+        let (dx, dy) = (self.to.0 - next.from.0, self.to.1 - next.from.1);
+        Self {
+            from: self.from,
+            to: (
+                next.to.0 + next.a * dx - next.b * dy,
+                next.to.1 + next.b * dx + next.a * dy,
+            ),
+            a: next.a * self.a - next.b * self.b,
+            b: next.a * self.b + next.b * self.a,
+        }
+    }
+
+    /// The motion back.
+    pub fn inverse(self) -> Self {
+        let norm = self.a * self.a + self.b * self.b;
+        Self {
+            from: self.to,
+            to: self.from,
+            a: self.a / norm,
+            b: -self.b / norm,
+        }
+    }
+
+    /// Rotation in radians.
+    pub fn rotation(&self) -> f32 {
+        self.b.atan2(self.a)
+    }
+
+    pub fn scale(&self) -> f32 {
+        self.a.hypot(self.b)
+    }
+}
+
+/// What a global motion may hold beyond a shift.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MotionModel {
+    pub rotation: bool,
+    pub scale: bool,
+}
+
+/// A point of the first frame, where it was found in the second, and how
+/// much the match counts.
+type Match = ((f32, f32), (f32, f32), f32);
+
+/// The motion from `a` to `b` (same size) as a whole, fitted to the block
+/// motion weighted by texture.
+pub fn global_motion(a: &RgbImage, b: &RgbImage, model: MotionModel) -> GlobalMotion {
+    let flow = estimate_flow(a, b);
+    let matches: Vec<Match> = flow
+        .blocks()
+        .map(|((x, y), (dx, dy), texture)| ((x, y), (x + dx, y + dy), texture))
+        .collect();
+    fit_global_motion(&matches, model)
+}
+
+/// Weighted least-squares fit to point matches, refitted once without the
+/// matches the first fit explains worst (moving clouds, foreground).
+fn fit_global_motion(matches: &[Match], model: MotionModel) -> GlobalMotion {
+    let Some(first) = fit_once(matches, model) else {
+        return GlobalMotion::IDENTITY;
+    };
+    let residual = |&(from, to, _): &Match| {
+        let (x, y) = first.apply(from);
+        (x - to.0).hypot(y - to.1)
+    };
+    let mut residuals: Vec<f32> = matches.iter().map(residual).collect();
+    residuals.sort_by(f32::total_cmp);
+    let limit = 2.0 * residuals[residuals.len() / 2] + 0.5;
+    let inliers: Vec<Match> = matches
+        .iter()
+        .filter(|m| residual(m) <= limit)
+        .copied()
+        .collect();
+    fit_once(&inliers, model).unwrap_or(first)
+}
+
+fn fit_once(matches: &[Match], model: MotionModel) -> Option<GlobalMotion> {
+    let total: f64 = matches.iter().map(|m| m.2 as f64).sum();
+    if total <= 0.0 {
+        return None;
+    }
+    let centroid = |point: fn(&Match) -> (f32, f32)| {
+        let (mut x, mut y) = (0.0f64, 0.0f64);
+        for m in matches {
+            let (px, py) = point(m);
+            x += px as f64 * m.2 as f64;
+            y += py as f64 * m.2 as f64;
+        }
+        (x / total, y / total)
+    };
+    let from = centroid(|m| m.0);
+    let to = centroid(|m| m.1);
+
+    // Closed-form similarity fit of the centered point sets (2-D
+    // Procrustes), then held to the model.
+    let (mut dot, mut cross, mut spread) = (0.0f64, 0.0f64, 0.0f64);
+    for &((fx, fy), (tx, ty), w) in matches {
+        let (px, py) = (fx as f64 - from.0, fy as f64 - from.1);
+        let (qx, qy) = (tx as f64 - to.0, ty as f64 - to.1);
+        dot += w as f64 * (px * qx + py * qy);
+        cross += w as f64 * (px * qy - py * qx);
+        spread += w as f64 * (px * px + py * py);
+    }
+    let (a, b) = match (model.rotation, model.scale) {
+        _ if spread <= 0.0 => (1.0, 0.0),
+        (true, true) => (dot / spread, cross / spread),
+        (true, false) => {
+            let angle = cross.atan2(dot);
+            (angle.cos(), angle.sin())
+        }
+        (false, true) => (dot / spread, 0.0),
+        (false, false) => (1.0, 0.0),
+    };
+    Some(GlobalMotion {
+        from: (from.0 as f32, from.1 as f32),
+        to: (to.0 as f32, to.1 as f32),
+        a: a as f32,
+        b: b as f32,
+    })
+}
+
+/// A gray image as floats.
+struct Plane {
+    w: usize,
+    h: usize,
+    data: Vec<f32>,
+}
+
+impl Plane {
+    /// A gray copy of `img` fitting within `dim` pixels.
+    fn gray(img: &RgbImage, dim: u32) -> Self {
+        let (w, h) = img.dimensions();
+        let scale = (dim as f32 / w.max(h) as f32).min(1.0);
+        let small = if scale < 1.0 {
+            imageops::resize(
+                img,
+                ((w as f32 * scale).round() as u32).max(1),
+                ((h as f32 * scale).round() as u32).max(1),
+                imageops::FilterType::Triangle,
+            )
+        } else {
+            img.clone()
+        };
+        let data = small
+            .pixels()
+            .map(|p| 0.299 * p[0] as f32 + 0.587 * p[1] as f32 + 0.114 * p[2] as f32)
+            .collect();
+        Self {
+            w: small.width() as usize,
+            h: small.height() as usize,
+            data,
+        }
+    }
+
+    fn get(&self, x: i32, y: i32) -> f32 {
+        let x = x.clamp(0, self.w as i32 - 1) as usize;
+        let y = y.clamp(0, self.h as i32 - 1) as usize;
+        self.data[y * self.w + x]
+    }
+
+    /// Mean absolute deviation of the block at (bx, by).
+    fn texture(&self, bx: usize, by: usize) -> f32 {
+        let values: Vec<f32> = (by..(by + BLOCK).min(self.h))
+            .flat_map(|y| (bx..(bx + BLOCK).min(self.w)).map(move |x| (x, y)))
+            .map(|(x, y)| self.data[y * self.w + x])
+            .collect();
+        let mean = values.iter().sum::<f32>() / values.len() as f32;
+        values.iter().map(|v| (v - mean).abs()).sum::<f32>() / values.len() as f32
+    }
+
+    /// Half-size copy (2x2 averages).
+    fn half(&self) -> Self {
+        let (w, h) = (self.w / 2, self.h / 2);
+        let mut data = Vec::with_capacity(w * h);
+        for y in 0..h {
+            for x in 0..w {
+                let (sx, sy) = (2 * x as i32, 2 * y as i32);
+                data.push(
+                    (self.get(sx, sy)
+                        + self.get(sx + 1, sy)
+                        + self.get(sx, sy + 1)
+                        + self.get(sx + 1, sy + 1))
+                        / 4.0,
+                );
+            }
+        }
+        Self { w, h, data }
+    }
+}
+
+/// Finest level first.
+fn pyramid(base: Plane) -> Vec<Plane> {
+    let mut levels = vec![base];
+    loop {
+        let last = levels.last().expect("starts non-empty");
+        if last.w.min(last.h) / 2 < MIN_LEVEL_DIM {
+            return levels;
+        }
+        let next = last.half();
+        levels.push(next);
+    }
+}
+
+/// Best motion of the block at (bx, by) of `a` into `b`, searched around
+/// the prediction and at zero motion; `subpixel` refines it with a
+/// parabola through the neighboring costs.
+fn match_block(
+    a: &Plane,
+    b: &Plane,
+    bx: usize,
+    by: usize,
+    predicted: (i32, i32),
+    subpixel: bool,
+) -> (f32, f32) {
+    let cost = |dx: i32, dy: i32| -> f32 {
+        let mut sad = 0.0;
+        for y in by..(by + BLOCK).min(a.h) {
+            for x in bx..(bx + BLOCK).min(a.w) {
+                let (x, y) = (x as i32, y as i32);
+                sad += (a.get(x, y) - b.get(x + dx, y + dy)).abs();
+            }
+        }
+        sad + MOTION_PENALTY * ((dx * dx + dy * dy) as f32).sqrt()
+    };
+
+    let mut best = ((0, 0), cost(0, 0));
+    for dy in -SEARCH..=SEARCH {
+        for dx in -SEARCH..=SEARCH {
+            let candidate = (predicted.0 + dx, predicted.1 + dy);
+            let c = cost(candidate.0, candidate.1);
+            if c < best.1 {
+                best = (candidate, c);
+            }
+        }
+    }
+    let ((dx, dy), c0) = best;
+    if !subpixel {
+        return (dx as f32, dy as f32);
+    }
+    let refine = |minus: f32, plus: f32| {
+        let curvature = minus - 2.0 * c0 + plus;
+        if curvature > 0.0 {
+            ((minus - plus) / (2.0 * curvature)).clamp(-0.5, 0.5)
+        } else {
+            0.0
+        }
+    };
+    (
+        dx as f32 + refine(cost(dx - 1, dy), cost(dx + 1, dy)),
+        dy as f32 + refine(cost(dx, dy - 1), cost(dx, dy + 1)),
+    )
+}
+
+/// Componentwise 3x3 median of a vector grid, removing stray matches.
+fn median_filter(vectors: &[(f32, f32)], cols: usize) -> Vec<(f32, f32)> {
+    let rows = vectors.len() / cols;
+    let median = |mut values: Vec<f32>| {
+        values.sort_by(f32::total_cmp);
+        values[values.len() / 2]
+    };
+    (0..vectors.len())
+        .map(|i| {
+            let (x, y) = (i % cols, i / cols);
+            let neighbors: Vec<(f32, f32)> = (y.saturating_sub(1)..(y + 2).min(rows))
+                .flat_map(|ny| {
+                    (x.saturating_sub(1)..(x + 2).min(cols)).map(move |nx| vectors[ny * cols + nx])
+                })
+                .collect();
+            (
+                median(neighbors.iter().map(|v| v.0).collect()),
+                median(neighbors.iter().map(|v| v.1).collect()),
+            )
+        })
+        .collect()
+}
+
+/// Bilinear sample, clamped at the edges.
+pub(crate) fn sample_rgb(img: &RgbImage, x: f32, y: f32) -> [f32; 3] {
+    let (w, h) = img.dimensions();
+    let x = x.clamp(0.0, (w - 1) as f32);
+    let y = y.clamp(0.0, (h - 1) as f32);
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(w - 1), (y0 + 1).min(h - 1));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+    let mut out = [0.0; 3];
+    for (c, value) in out.iter_mut().enumerate() {
+        let p = |px: u32, py: u32| img.get_pixel(px, py)[c] as f32;
+        let top = p(x0, y0) + (p(x1, y0) - p(x0, y0)) * tx;
+        let bottom = p(x0, y1) + (p(x1, y1) - p(x0, y1)) * tx;
+        *value = top + (bottom - top) * ty;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A smooth, unrepeating pattern shifted by (dx, dy).
+    fn pattern(width: u32, height: u32, dx: f32, dy: f32) -> RgbImage {
+        RgbImage::from_fn(width, height, |x, y| {
+            let (x, y) = (x as f32 - dx, y as f32 - dy);
+            let v = 128.0
+                + 50.0 * (x * 0.21).sin() * (y * 0.13).cos()
+                + 40.0 * (x * 0.07 + y * 0.11).sin()
+                + 30.0 * ((x - y) * 0.05).cos();
+            image::Rgb([v as u8, (v * 0.8) as u8, (255.0 - v) as u8])
+        })
+    }
+
+    fn mean_error(a: &RgbImage, b: &RgbImage, margin: u32) -> f32 {
+        let (w, h) = a.dimensions();
+        let mut sum = 0.0;
+        let mut count = 0;
+        for y in margin..h - margin {
+            for x in margin..w - margin {
+                for c in 0..3 {
+                    sum += (a.get_pixel(x, y)[c] as f32 - b.get_pixel(x, y)[c] as f32).abs();
+                    count += 1;
+                }
+            }
+        }
+        sum / count as f32
+    }
+
+    #[test]
+    fn motions_chain_and_invert() {
+        let turn = GlobalMotion {
+            from: (10.0, 5.0),
+            to: (12.0, 4.0),
+            a: 0.6,
+            b: 0.8,
+        };
+        let shift = GlobalMotion {
+            from: (0.0, 0.0),
+            to: (3.0, -2.0),
+            ..GlobalMotion::IDENTITY
+        };
+        let p = (7.0, 9.0);
+        let chained = turn.then(shift).apply(p);
+        let stepped = shift.apply(turn.apply(p));
+        assert!((chained.0 - stepped.0).abs() < 1e-4 && (chained.1 - stepped.1).abs() < 1e-4);
+        let back = turn.inverse().apply(turn.apply(p));
+        assert!((back.0 - p.0).abs() < 1e-4 && (back.1 - p.1).abs() < 1e-4);
+    }
+
+    #[test]
+    fn global_fit_recovers_motion_despite_outliers() {
+        let truth = GlobalMotion {
+            from: (0.0, 0.0),
+            to: (3.0, -2.0),
+            a: 1.02 * 0.05f32.cos(),
+            b: 1.02 * 0.05f32.sin(),
+        };
+        let mut matches: Vec<Match> = (0..10)
+            .flat_map(|y| (0..10).map(move |x| (x as f32 * 20.0, y as f32 * 20.0)))
+            .map(|p| (p, truth.apply(p), 1.0))
+            .collect();
+        // A drifting cloud bank moving its own way.
+        for m in matches.iter_mut().take(8) {
+            m.1 .0 += 40.0;
+        }
+
+        let full = MotionModel {
+            rotation: true,
+            scale: true,
+        };
+        let fit = fit_global_motion(&matches, full);
+        for (p, expected, _) in matches.iter().skip(8) {
+            let (x, y) = fit.apply(*p);
+            assert!((x - expected.0).abs() < 0.05 && (y - expected.1).abs() < 0.05);
+        }
+        assert!((fit.scale() - 1.02).abs() < 1e-3);
+        assert!((fit.rotation() - 0.05).abs() < 1e-3);
+
+        // A shift-only fit stays a pure shift.
+        let shift = fit_global_motion(
+            &[
+                ((0.0, 0.0), (2.0, 1.0), 1.0),
+                ((10.0, 0.0), (12.0, 1.0), 1.0),
+            ],
+            MotionModel::default(),
+        );
+        assert_eq!(shift.apply((5.0, 5.0)), (7.0, 6.0));
+    }
+
+    #[test]
+    fn finds_a_uniform_shift() {
+        let a = pattern(160, 120, 0.0, 0.0);
+        let b = pattern(160, 120, 6.0, -3.0);
+        let flow = estimate_flow(&a, &b);
+        let (dx, dy) = flow.at(80.0, 60.0);
+        assert!((dx - 6.0).abs() < 0.5, "dx = {dx}");
+        assert!((dy + 3.0).abs() < 0.5, "dy = {dy}");
+    }
+
+    #[test]
+    fn in_between_frames_follow_the_motion() {
+        let a = pattern(160, 120, 0.0, 0.0);
+        let b = pattern(160, 120, 8.0, 4.0);
+        let expected = pattern(160, 120, 4.0, 2.0);
+
+        let warped = interpolate(&a, &b, 0.5);
+        let mut mixed = a.clone();
+        for (m, q) in mixed.iter_mut().zip(b.iter()) {
+            *m = ((*m as f32 + *q as f32) / 2.0).round() as u8;
+        }
+        let warped_error = mean_error(&warped, &expected, 12);
+        let mixed_error = mean_error(&mixed, &expected, 12);
+        assert!(
+            warped_error * 4.0 < mixed_error,
+            "flow {warped_error}, blend {mixed_error}"
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Output framing: the fixed size of the output frame, and where each
+//! frame's picture (the crop window, or the whole source) lands in it.
+
+use crate::error::Result;
+use crate::export::parse_resolution;
+use crate::project::{Aspect, Framing, Project};
+
+/// Where one frame's picture goes in the output frame.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Placement {
+    /// The part of the picture shown, about its centre, in source pixels.
+    pub source: (f32, f32),
+    /// The rectangle that part is resampled into, in output pixels.
+    pub target: (u32, u32, u32, u32),
+}
+
+/// The output frame size for sources of `src_w`x`src_h`: the resolution,
+/// or the first frame's picture cut to the aspect, whatever the framing;
+/// without either, the picture's own size.
+pub fn output_size(project: &Project, src_w: u32, src_h: u32) -> Result<(u32, u32)> {
+    scaled_output_size(project, src_w, src_h, 1.0)
+}
+
+/// [`output_size`] for sources shrunk to `scale` of their full size (a
+/// preview): a fixed resolution shrinks with them.
+pub fn scaled_output_size(
+    project: &Project,
+    src_w: u32,
+    src_h: u32,
+    scale: f32,
+) -> Result<(u32, u32)> {
+    let picture = match &project.crop {
+        Some(track) => track.output_size(project.first_frame(), src_w, src_h)?,
+        None => (src_w, src_h),
+    };
+    let export = &project.export;
+    Ok(match (export.resolution.as_deref(), export.aspect) {
+        (Some(res), _) => {
+            let (w, h) = parse_resolution(res)?;
+            let scaled = |px: u32| ((px as f32 * scale).round() as u32).max(1);
+            (scaled(w), scaled(h))
+        }
+        (None, Some(aspect)) => inside(picture, aspect),
+        (None, None) => picture,
+    })
+}
+
+/// The largest frame of `aspect` inside `size`.
+fn inside(size: (u32, u32), aspect: Aspect) -> (u32, u32) {
+    let (w, h) = size;
+    if w as f32 / h as f32 > aspect.ratio() {
+        (((h as f32 * aspect.ratio()).round() as u32).max(1), h)
+    } else {
+        (w, ((w as f32 / aspect.ratio()).round() as u32).max(1))
+    }
+}
+
+/// Place a `picture` (width and height in source pixels) in an `output`
+/// frame. The scale is uniform, and `fit` never enlarges (`upscale` makes
+/// it `letterbox`); a fitted picture within a pixel of the frame is
+/// stretched to it, so rounding never leaves a one-pixel bar.
+pub fn place(picture: (f32, f32), output: (u32, u32), framing: Framing) -> Placement {
+    let (pw, ph) = picture;
+    let (ow, oh) = (output.0 as f32, output.1 as f32);
+    match framing {
+        Framing::Fill => {
+            let scale = (ow / pw).max(oh / ph);
+            Placement {
+                source: (ow / scale, oh / scale),
+                target: (0, 0, output.0, output.1),
+            }
+        }
+        Framing::Fit | Framing::Letterbox => {
+            let scale = (ow / pw).min(oh / ph);
+            let scale = match framing {
+                Framing::Fit => scale.min(1.0),
+                _ => scale,
+            };
+            let snap = |extent: f32, full: u32| {
+                let px = (extent.round() as u32).clamp(1, full);
+                if full - px <= 1 {
+                    full
+                } else {
+                    px
+                }
+            };
+            let (w, h) = (snap(pw * scale, output.0), snap(ph * scale, output.1));
+            Placement {
+                source: picture,
+                target: ((output.0 - w) / 2, (output.1 - h) / 2, w, h),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crop::{CropRect, CropTrack};
+    use crate::project::{ColorGrade, ExportSettings, InterpolationMode, PROJECT_VERSION};
+    use std::path::PathBuf;
+
+    fn project(resolution: Option<&str>, aspect: Option<&str>, framing: Framing) -> Project {
+        let mut export = ExportSettings::new(PathBuf::from("out"));
+        export.resolution = resolution.map(str::to_string);
+        export.aspect = aspect.map(|a| a.parse().unwrap());
+        export.framing = framing;
+        Project {
+            version: PROJECT_VERSION,
+            input: PathBuf::from("in"),
+            frame_range: None,
+            excluded_frames: Vec::new(),
+            interpolation: InterpolationMode::default(),
+            color: ColorGrade::default(),
+            perspective: None,
+            crop: Some(CropTrack::from_rect(CropRect {
+                x: 0.0,
+                y: 0.0,
+                width: 0.5,
+                height: 0.5,
+            })),
+            export,
+            outputs: Vec::new(),
+            analysis: None,
+        }
+    }
+
+    #[test]
+    fn output_size_follows_resolution_aspect_and_framing() {
+        // A 1000x500 window from a 2000x1000 source.
+        let size =
+            |res, aspect, framing| output_size(&project(res, aspect, framing), 2000, 1000).unwrap();
+        assert_eq!(size(None, None, Framing::Fit), (1000, 500));
+        // An explicit size or aspect holds for every framing.
+        assert_eq!(size(Some("720p"), None, Framing::Fit), (1280, 720));
+        assert_eq!(size(Some("1920x1080"), None, Framing::Fit), (1920, 1080));
+        assert_eq!(size(Some("800x800"), None, Framing::Fit), (800, 800));
+        assert_eq!(size(Some("720p"), None, Framing::Fill), (1280, 720));
+        assert_eq!(size(Some("720p"), None, Framing::Letterbox), (1280, 720));
+        assert_eq!(size(None, Some("9:16"), Framing::Fit), (281, 500));
+        assert_eq!(size(None, Some("9:16"), Framing::Fill), (281, 500));
+        assert_eq!(size(None, Some("1:1"), Framing::Letterbox), (500, 500));
+
+        // A quarter-size preview shrinks the resolution with the source.
+        let fill = project(Some("720p"), None, Framing::Fill);
+        assert_eq!(
+            scaled_output_size(&fill, 500, 250, 0.25).unwrap(),
+            (320, 180)
+        );
+    }
+
+    #[test]
+    fn fill_trims_and_letterbox_pads_without_stretching() {
+        let fill = place((1600.0, 900.0), (900, 900), Framing::Fill);
+        assert_eq!(fill.source, (900.0, 900.0));
+        assert_eq!(fill.target, (0, 0, 900, 900));
+
+        let boxed = place((1600.0, 900.0), (900, 900), Framing::Letterbox);
+        assert_eq!(boxed.source, (1600.0, 900.0));
+        assert_eq!(boxed.target, (0, 197, 900, 506));
+    }
+
+    #[test]
+    fn fit_scales_inside_the_output_without_enlarging() {
+        // A 1000x500 picture in 1920x1080: fit keeps it at full size with
+        // bars all round, letterbox enlarges it to the width.
+        let fit = place((1000.0, 500.0), (1920, 1080), Framing::Fit);
+        assert_eq!(fit.target, (460, 290, 1000, 500));
+        let boxed = place((1000.0, 500.0), (1920, 1080), Framing::Letterbox);
+        assert_eq!(boxed.target, (0, 60, 1920, 960));
+
+        // Into a 9:16 cut of it, fit shrinks the picture to the width.
+        let tall = place((1000.0, 500.0), (281, 500), Framing::Fit);
+        assert_eq!(tall.target, (0, 179, 281, 141));
+    }
+
+    #[test]
+    fn rounding_never_leaves_a_one_pixel_bar() {
+        let placed = place((120.4, 110.6), (120, 111), Framing::Fit);
+        assert_eq!(placed.target, (0, 0, 120, 111));
+    }
+}
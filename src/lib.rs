@@ -1,4 +1,5 @@
 pub mod analysis;
+pub mod blur;
 pub mod cli;
 pub mod color;
 pub mod crop;
@@ -6,16 +7,23 @@ pub mod curve;
 pub mod error;
 pub mod exif;
 pub mod export;
+pub mod expr;
+pub mod flow;
+pub mod framing;
+pub mod perspective;
 pub mod progress;
 pub mod project;
 #[cfg(feature = "raw")]
 pub mod raw;
+pub mod rebase;
 pub mod render;
+pub mod resample;
 pub mod source;
+pub mod stack;
 pub mod timeline;
 
 pub use crop::{CropRect, CropTrack};
-pub use curve::{Curve, Easing, Keyframe};
+pub use curve::{Curve, Easing, Handle, Keyframe};
 pub use error::LapsifyError;
 pub use project::{ColorGrade, ExportSettings, Project};
 pub use render::{render_frame, render_preview};
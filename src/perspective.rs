@@ -0,0 +1,335 @@
+//! Perspective (keystone) correction.
+//!
+//! A camera tilted up at a building makes its verticals converge. Moving
+//! the four corners of the source image and resampling through the
+//! homography that takes them there straightens them again. The warp runs
+//! before the crop, and the crop must stay inside the warped image: what
+//! lies outside it has no source pixels. Without a crop, the warped image
+//! must cover the whole frame.
+
+use image::{Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+
+use crate::curve::Curve;
+use crate::error::{LapsifyError, Result};
+use crate::flow::sample_rgb;
+
+/// Where the corners of the source image move, as offsets in normalized
+/// source-image coordinates. Every offset is a keyframable curve.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct Perspective {
+    pub top_left: CornerOffset,
+    pub top_right: CornerOffset,
+    pub bottom_right: CornerOffset,
+    pub bottom_left: CornerOffset,
+    pub resampling: Resampling,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(default)]
+pub struct CornerOffset {
+    pub x: Curve,
+    pub y: Curve,
+}
+
+impl Default for CornerOffset {
+    fn default() -> Self {
+        Self {
+            x: Curve::Constant(0.0),
+            y: Curve::Constant(0.0),
+        }
+    }
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Resampling {
+    #[default]
+    Bilinear,
+    /// Catmull-Rom: sharper, for fine detail such as windows and brickwork.
+    Bicubic,
+}
+
+/// Corners of the unit square, in the order of `Perspective::curves`.
+const UNIT: [(f32, f32); 4] = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+
+impl Perspective {
+    /// Every offset curve with its field name.
+    pub fn curves(&self) -> [(&'static str, &Curve); 8] {
+        [
+            ("perspective.top_left.x", &self.top_left.x),
+            ("perspective.top_left.y", &self.top_left.y),
+            ("perspective.top_right.x", &self.top_right.x),
+            ("perspective.top_right.y", &self.top_right.y),
+            ("perspective.bottom_right.x", &self.bottom_right.x),
+            ("perspective.bottom_right.y", &self.bottom_right.y),
+            ("perspective.bottom_left.x", &self.bottom_left.x),
+            ("perspective.bottom_left.y", &self.bottom_left.y),
+        ]
+    }
+
+    pub fn curves_mut(&mut self) -> [(&'static str, &mut Curve); 8] {
+        [
+            ("perspective.top_left.x", &mut self.top_left.x),
+            ("perspective.top_left.y", &mut self.top_left.y),
+            ("perspective.top_right.x", &mut self.top_right.x),
+            ("perspective.top_right.y", &mut self.top_right.y),
+            ("perspective.bottom_right.x", &mut self.bottom_right.x),
+            ("perspective.bottom_right.y", &mut self.bottom_right.y),
+            ("perspective.bottom_left.x", &mut self.bottom_left.x),
+            ("perspective.bottom_left.y", &mut self.bottom_left.y),
+        ]
+    }
+
+    /// Where the source corners land at a frame (top left, top right,
+    /// bottom right, bottom left), in normalized coordinates.
+    pub fn corners_at(&self, frame: u32) -> [(f32, f32); 4] {
+        let corners = [
+            &self.top_left,
+            &self.top_right,
+            &self.bottom_right,
+            &self.bottom_left,
+        ];
+        std::array::from_fn(|i| {
+            (
+                UNIT[i].0 + corners[i].x.sample(frame),
+                UNIT[i].1 + corners[i].y.sample(frame),
+            )
+        })
+    }
+
+    pub fn is_identity_at(&self, frame: u32) -> bool {
+        self.corners_at(frame) == UNIT
+    }
+
+    /// Whether normalized point `p` lies in the warped image at a frame.
+    /// The corrected quad is convex (see `validate_over`).
+    pub fn contains(&self, frame: u32, p: (f32, f32)) -> bool {
+        const EPS: f32 = 1e-4;
+        let quad = self.corners_at(frame);
+        (0..4).all(|i| {
+            let (a, b) = (quad[i], quad[(i + 1) % 4]);
+            // Clockwise on screen (y down): the inside is on the right.
+            (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0) >= -EPS
+        })
+    }
+
+    /// Structural validation of the offset curves.
+    pub fn validate(&self) -> Result<()> {
+        for (name, curve) in self.curves() {
+            // Geometry samples in plain frame space, like the crop.
+            if matches!(curve, Curve::Expression(_)) {
+                return Err(LapsifyError::InvalidParam {
+                    field: name,
+                    reason: "expressions are supported for color curves only".to_string(),
+                });
+            }
+            curve.validate(name)?;
+            curve.validate_range(name, -1.0, 1.0)?;
+        }
+        Ok(())
+    }
+
+    /// Check at every frame that the corners still form a convex quad in
+    /// their original order, so the warp neither folds nor flips.
+    pub fn validate_over(&self, total_frames: usize) -> Result<()> {
+        for frame in 0..total_frames as u32 {
+            let quad = self.corners_at(frame);
+            let convex = (0..4).all(|i| {
+                let (a, b, c) = (quad[i], quad[(i + 1) % 4], quad[(i + 2) % 4]);
+                (b.0 - a.0) * (c.1 - b.1) - (b.1 - a.1) * (c.0 - b.0) > 0.0
+            });
+            if !convex {
+                return Err(LapsifyError::message(format!(
+                    "Perspective corners fold over at frame {frame}; they must stay a convex quad"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Check at every frame that the warped image covers the whole frame,
+    /// as it must when no crop window keeps clear of the empty edges.
+    pub fn validate_coverage(&self, total_frames: usize) -> Result<()> {
+        for frame in 0..total_frames as u32 {
+            if !UNIT.iter().all(|&corner| self.contains(frame, corner)) {
+                return Err(LapsifyError::message(format!(
+                    "Perspective correction leaves empty edges at frame {frame}; add a crop inside the corrected image or move the corners outward"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Warp a frame: every output pixel is resampled from where the
+    /// homography puts it in the source. Pixels outside the warped image
+    /// are black.
+    pub fn warp(&self, img: &RgbImage, frame: u32) -> RgbImage {
+        let (w, h) = img.dimensions();
+        let to_pixels = |(x, y): (f32, f32)| (x as f64 * w as f64, y as f64 * h as f64);
+        let from = self.corners_at(frame).map(to_pixels);
+        let to = UNIT.map(to_pixels);
+        let Some(inverse) = homography(&from, &to) else {
+            return img.clone();
+        };
+        RgbImage::from_fn(w, h, |i, j| {
+            let (x, y) = apply(&inverse, (i as f64 + 0.5, j as f64 + 0.5));
+            let (x, y) = (x as f32 - 0.5, y as f32 - 0.5);
+            if x < -0.5 || y < -0.5 || x > w as f32 - 0.5 || y > h as f32 - 0.5 {
+                return Rgb([0, 0, 0]);
+            }
+            let rgb = match self.resampling {
+                Resampling::Bilinear => sample_rgb(img, x, y),
+                Resampling::Bicubic => sample_bicubic(img, x, y),
+            };
+            Rgb(rgb.map(|c| c.round().clamp(0.0, 255.0) as u8))
+        })
+    }
+}
+
+/// The homography taking `from[i]` to `to[i]`, as the first eight entries
+/// of its 3x3 matrix (the last is 1), or None for a degenerate quad.
+fn homography(from: &[(f64, f64); 4], to: &[(f64, f64); 4]) -> Option<[f64; 8]> {
+    let mut a = [[0.0f64; 9]; 8];
+    for (k, (&(x, y), &(u, v))) in from.iter().zip(to).enumerate() {
+        a[2 * k] = [x, y, 1.0, 0.0, 0.0, 0.0, -u * x, -u * y, u];
+        a[2 * k + 1] = [0.0, 0.0, 0.0, x, y, 1.0, -v * x, -v * y, v];
+    }
+    // Gaussian elimination with partial pivoting on the augmented system.
+    for col in 0..8 {
+        let pivot = (col..8).max_by(|&p, &q| a[p][col].abs().total_cmp(&a[q][col].abs()))?;
+        if a[pivot][col].abs() < 1e-12 {
+            return None;
+        }
+        a.swap(col, pivot);
+        let pivot_row = a[col];
+        for (row, values) in a.iter_mut().enumerate() {
+            if row != col {
+                let factor = values[col] / pivot_row[col];
+                for (value, p) in values.iter_mut().zip(pivot_row).skip(col) {
+                    *value -= factor * p;
+                }
+            }
+        }
+    }
+    Some(std::array::from_fn(|i| a[i][8] / a[i][i]))
+}
+
+fn apply(m: &[f64; 8], (x, y): (f64, f64)) -> (f64, f64) {
+    let d = m[6] * x + m[7] * y + 1.0;
+    (
+        (m[0] * x + m[1] * y + m[2]) / d,
+        (m[3] * x + m[4] * y + m[5]) / d,
+    )
+}
+
+/// Catmull-Rom sample, clamped at the edges.
+fn sample_bicubic(img: &RgbImage, x: f32, y: f32) -> [f32; 3] {
+    let (w, h) = img.dimensions();
+    let (x0, y0) = (x.floor(), y.floor());
+    let (tx, ty) = (x - x0, y - y0);
+    let weights = |t: f32| {
+        [
+            ((-t + 2.0) * t - 1.0) * t / 2.0,
+            ((3.0 * t - 5.0) * t * t + 2.0) / 2.0,
+            ((-3.0 * t + 4.0) * t + 1.0) * t / 2.0,
+            (t - 1.0) * t * t / 2.0,
+        ]
+    };
+    let (wx, wy) = (weights(tx), weights(ty));
+    let mut out = [0.0; 3];
+    for (j, wj) in wy.iter().enumerate() {
+        let py = (y0 as i64 + j as i64 - 1).clamp(0, h as i64 - 1) as u32;
+        for (i, wi) in wx.iter().enumerate() {
+            let px = (x0 as i64 + i as i64 - 1).clamp(0, w as i64 - 1) as u32;
+            let pixel = img.get_pixel(px, py);
+            for (c, value) in out.iter_mut().enumerate() {
+                *value += wi * wj * pixel[c] as f32;
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use approx::assert_relative_eq;
+
+    #[test]
+    fn homography_maps_the_corners() {
+        let from = [(0.0, 0.0), (100.0, 10.0), (90.0, 80.0), (5.0, 100.0)];
+        let to = [(0.0, 0.0), (100.0, 0.0), (100.0, 100.0), (0.0, 100.0)];
+        let m = homography(&from, &to).unwrap();
+        for (p, q) in from.iter().zip(&to) {
+            let (x, y) = apply(&m, *p);
+            assert_relative_eq!(x, q.0, epsilon = 1e-6);
+            assert_relative_eq!(y, q.1, epsilon = 1e-6);
+        }
+    }
+
+    fn moved(corner: (f32, f32)) -> CornerOffset {
+        CornerOffset {
+            x: Curve::Constant(corner.0),
+            y: Curve::Constant(corner.1),
+        }
+    }
+
+    #[test]
+    fn keystone_straightens_converging_verticals() {
+        // A line that leans in toward the top, as shot looking up: x = 30
+        // at the top and 22 at the bottom of a 100x100 frame.
+        let img = RgbImage::from_fn(100, 100, |x, y| {
+            let line = 30.0 - 8.0 * y as f32 / 99.0;
+            Rgb([if (x as f32 - line).abs() < 1.0 {
+                255
+            } else {
+                0
+            }; 3])
+        });
+        // Pulling the top corners apart by 20 % of the width moves the
+        // line's top to 30 * 1.4 - 20 = 22: vertical again.
+        let perspective = Perspective {
+            top_left: moved((-0.2, 0.0)),
+            top_right: moved((0.2, 0.0)),
+            ..Perspective::default()
+        };
+        let out = perspective.warp(&img, 0);
+        let column = |y: u32| (0..100).max_by_key(|&x| out.get_pixel(x, y)[0]).unwrap();
+        assert!(
+            column(5).abs_diff(column(95)) <= 1,
+            "{} vs {}",
+            column(5),
+            column(95)
+        );
+        // The top corners left the frame, so the whole frame has pixels.
+        assert!(perspective.contains(0, (0.0, 0.0)));
+        assert!(perspective.contains(0, (0.0, 1.0)));
+        assert!(perspective.validate_coverage(1).is_ok());
+
+        // Pulling the bottom corners in instead leaves empty wedges there.
+        let perspective = Perspective {
+            bottom_left: moved((0.1, 0.0)),
+            bottom_right: moved((-0.1, 0.0)),
+            ..Perspective::default()
+        };
+        assert!(perspective.contains(0, (0.05, 0.02)));
+        assert!(!perspective.contains(0, (0.05, 0.98)));
+        assert_eq!(perspective.warp(&img, 0).get_pixel(1, 98).0, [0, 0, 0]);
+        // Which only a crop may leave out.
+        assert!(perspective.validate_coverage(1).is_err());
+    }
+
+    #[test]
+    fn folded_corners_are_rejected() {
+        let mut perspective = Perspective::default();
+        assert!(perspective.validate_over(1).is_ok());
+        assert!(perspective.is_identity_at(0));
+        perspective.top_left.x = Curve::Constant(0.8);
+        perspective.top_right.x = Curve::Constant(-0.8);
+        assert!(perspective.validate_over(1).is_err());
+    }
+}
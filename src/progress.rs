@@ -2,7 +2,10 @@ use std::path::PathBuf;
 use std::time::Duration;
 
 use indicatif::{ProgressBar, ProgressStyle};
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+
+use crate::analysis::intervals::IntervalReport;
+use crate::analysis::scenes::{Scene, SceneCut};
 
 /// Progress events emitted during a render. In `--progress json` mode each
 /// event is one NDJSON line on stdout — the wire protocol for UIs driving
@@ -31,6 +34,32 @@ pub enum ProgressEvent {
     KeyframeSuggestion {
         frames: Vec<u32>,
     },
+    /// Rotate and stretch solved by a holy grail luminance fit, with the
+    /// per-frame residual.
+    HolyGrailFit {
+        rotate: f32,
+        stretch: f32,
+        rms_ev: f32,
+        residuals_ev: Vec<f32>,
+    },
+    /// Frames flagged by bad-frame detection.
+    Outliers {
+        flags: Vec<OutlierFlag>,
+    },
+    /// Scenes found by scene-cut detection.
+    Scenes {
+        scenes: Vec<Scene>,
+    },
+    /// Camera shake removed by stabilisation: the zoom that keeps the
+    /// window inside the image and the largest window move (normalized).
+    Stabilized {
+        zoom: f32,
+        max_shift: f32,
+    },
+    /// Capture interval report.
+    Intervals {
+        report: IntervalReport,
+    },
     /// One completed deflicker correction pass.
     DeflickerPass {
         pass: u32,
@@ -46,6 +75,44 @@ pub enum ProgressEvent {
     },
 }
 
+/// Why bad-frame detection flagged a frame. Defined here, below the
+/// analysis that produces it, because progress events carry it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OutlierReason {
+    /// Sudden brightness change against the neighbors (score: EV).
+    LuminanceSpike,
+    /// Much less fine detail than the neighbors (score: detail ratio).
+    Blur,
+    /// Image jumped against both neighbors (score: fraction of width, against
+    /// the previous frame).
+    Displacement,
+    /// Almost all pixels black (score: fraction of pixels).
+    NearBlack,
+    /// Almost all pixels white (score: fraction of pixels).
+    NearWhite,
+}
+
+impl OutlierReason {
+    pub fn label(self) -> &'static str {
+        match self {
+            Self::LuminanceSpike => "luminance spike",
+            Self::Blur => "blur",
+            Self::Displacement => "displacement",
+            Self::NearBlack => "near black",
+            Self::NearWhite => "near white",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct OutlierFlag {
+    pub frame: u32,
+    pub reason: OutlierReason,
+    /// How far the frame is off, in the reason's unit.
+    pub score: f32,
+}
+
 pub enum ProgressReporter {
     Human(ProgressBar),
     Json,
@@ -97,6 +164,72 @@ impl ProgressReporter {
                 ProgressEvent::KeyframeSuggestion { frames } => {
                     bar.suspend(|| eprintln!("Suggested keyframes at frames: {frames:?}"));
                 }
+                ProgressEvent::HolyGrailFit {
+                    rotate,
+                    stretch,
+                    rms_ev,
+                    residuals_ev,
+                } => {
+                    let worst = residuals_ev
+                        .iter()
+                        .enumerate()
+                        .max_by(|a, b| a.1.abs().total_cmp(&b.1.abs()));
+                    bar.suspend(|| {
+                        eprintln!(
+                            "Luminance fit: rotate {rotate:.3} EV, stretch {stretch:.3}, residual RMS {rms_ev:.3} EV"
+                        );
+                        if let Some((frame, residual)) = worst {
+                            eprintln!("  largest residual {residual:+.3} EV at frame {frame}");
+                        }
+                    });
+                }
+                ProgressEvent::Outliers { flags } => {
+                    bar.suspend(|| {
+                        eprintln!("Flagged {} outlier(s):", flags.len());
+                        for flag in flags {
+                            eprintln!(
+                                "  frame {}: {} ({:.3})",
+                                flag.frame,
+                                flag.reason.label(),
+                                flag.score
+                            );
+                        }
+                    });
+                }
+                ProgressEvent::Scenes { scenes } => {
+                    bar.suspend(|| {
+                        eprintln!("Found {} scene(s):", scenes.len());
+                        for (i, scene) in scenes.iter().enumerate() {
+                            let cut = match scene.cut {
+                                None => String::new(),
+                                Some(SceneCut::TimeGap { seconds }) => {
+                                    format!(" (after a {:.0} s gap)", seconds.abs())
+                                }
+                                Some(SceneCut::Dissimilar { score }) => {
+                                    format!(" (visual cut, {score:.2})")
+                                }
+                            };
+                            eprintln!(
+                                "  scene {}: frames {}-{}{cut}",
+                                i + 1,
+                                scene.start,
+                                scene.end
+                            );
+                        }
+                    });
+                }
+                ProgressEvent::Stabilized { zoom, max_shift } => {
+                    bar.suspend(|| {
+                        eprintln!(
+                            "Stabilized: window zoomed to {:.1}% of the frame, largest move {:.2}% of the image",
+                            zoom * 100.0,
+                            max_shift * 100.0
+                        )
+                    });
+                }
+                ProgressEvent::Intervals { report } => {
+                    bar.suspend(|| print_interval_report(report));
+                }
                 ProgressEvent::DeflickerPass {
                     pass,
                     frames_corrected,
@@ -124,6 +257,66 @@ impl ProgressReporter {
     }
 }
 
+fn print_interval_report(report: &IntervalReport) {
+    let seconds = |ms: i64| ms as f64 / 1000.0;
+    let Some(nominal) = report.nominal_ms else {
+        eprintln!(
+            "No capture intervals: {} of {} frame(s) have a capture time",
+            report.frames - report.frames_without_time.len(),
+            report.frames
+        );
+        return;
+    };
+    eprintln!(
+        "Capture intervals over {} frame(s): nominal {:.1} s",
+        report.frames,
+        seconds(nominal)
+    );
+    let most = report.histogram.iter().map(|b| b.count).max().unwrap_or(1);
+    for bin in &report.histogram {
+        let bar = "#".repeat((bin.count * 40).div_ceil(most));
+        eprintln!("  {:>8.1} s  {bar} {}", seconds(bin.interval_ms), bin.count);
+    }
+    if report.is_clean() {
+        eprintln!("No gaps, duplicate timestamps or clock jumps");
+        return;
+    }
+    if !report.gaps.is_empty() {
+        let missing: u32 = report.gaps.iter().map(|g| g.missing_frames).sum();
+        eprintln!(
+            "{} gap(s), about {missing} frame(s) missing:",
+            report.gaps.len()
+        );
+        for gap in &report.gaps {
+            eprintln!(
+                "  before frame {}: {:.1} s ({} missing)",
+                gap.frame,
+                seconds(gap.interval_ms),
+                gap.missing_frames
+            );
+        }
+    }
+    if !report.duplicates.is_empty() {
+        eprintln!("Duplicate timestamps at frame(s): {:?}", report.duplicates);
+    }
+    if !report.clock_jumps.is_empty() {
+        eprintln!("{} clock jump(s):", report.clock_jumps.len());
+        for jump in &report.clock_jumps {
+            eprintln!(
+                "  before frame {}: {:+.1} s",
+                jump.frame,
+                seconds(jump.delta_ms)
+            );
+        }
+    }
+    if !report.frames_without_time.is_empty() {
+        eprintln!(
+            "Frames without a capture time: {:?}",
+            report.frames_without_time
+        );
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -3,11 +3,14 @@ use std::path::{Path, PathBuf};
 
 use serde::{Deserialize, Serialize};
 
-use crate::analysis::Analysis;
+use crate::analysis::{frame_keys, Analysis};
 use crate::color::ToneCurve;
 use crate::crop::CropTrack;
 use crate::curve::Curve;
 use crate::error::{LapsifyError, Result};
+use crate::perspective::Perspective;
+use crate::source::list_images;
+use crate::timeline::Timeline;
 
 pub const PROJECT_VERSION: u32 = 1;
 
@@ -21,16 +24,28 @@ pub struct Project {
     /// Inclusive frame range to process, 0-based. None = all frames.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub frame_range: Option<(usize, usize)>,
+    /// Source frames left out of the render (0-based, like `frame_range`),
+    /// e.g. a flash or someone walking past the lens. Keyframes and analysis
+    /// stay indexed by source frame; analysis bridges over these frames.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub excluded_frames: Vec<u32>,
     /// How color curves interpolate between keyframes: by frame index, or by
     /// capture time (needs timestamps from `analyze holygrail`).
     #[serde(default)]
     pub interpolation: InterpolationMode,
     #[serde(default)]
     pub color: ColorGrade,
+    /// Keystone correction, applied before the crop.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub perspective: Option<Perspective>,
     /// Crop window over time in normalized source-image coordinates.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub crop: Option<CropTrack>,
     pub export: ExportSettings,
+    /// Further deliverables rendered alongside `export` in the same pass,
+    /// e.g. vertical and square reframes of a landscape master.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outputs: Vec<Output>,
     /// Machine-generated analysis data, written back by `lapsify analyze`.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub analysis: Option<Analysis>,
@@ -124,6 +139,122 @@ impl std::str::FromStr for Codec {
     }
 }
 
+/// An aspect ratio such as 16:9, written `"16:9"` in project files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(try_from = "String", into = "String")]
+pub struct Aspect {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Aspect {
+    /// Width over height.
+    pub fn ratio(self) -> f32 {
+        self.width as f32 / self.height as f32
+    }
+}
+
+impl std::str::FromStr for Aspect {
+    type Err = LapsifyError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || {
+            LapsifyError::message(format!(
+                "Invalid aspect ratio '{s}' (expected W:H, e.g. 16:9)"
+            ))
+        };
+        let (width, height) = s.split_once(':').ok_or_else(invalid)?;
+        let width = width.trim().parse::<u32>().map_err(|_| invalid())?;
+        let height = height.trim().parse::<u32>().map_err(|_| invalid())?;
+        if width == 0 || height == 0 {
+            return Err(invalid());
+        }
+        Ok(Self { width, height })
+    }
+}
+
+impl std::fmt::Display for Aspect {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.width, self.height)
+    }
+}
+
+impl TryFrom<String> for Aspect {
+    type Error = LapsifyError;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl From<Aspect> for String {
+    fn from(aspect: Aspect) -> Self {
+        aspect.to_string()
+    }
+}
+
+/// How each frame's picture — the crop window, or the whole source — is
+/// placed in an output frame of a different shape. Pictures are only ever
+/// scaled uniformly, never stretched.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Framing {
+    /// Scale to fit inside the output without enlarging (unless `upscale`
+    /// is set), centred; black bars fill the rest. Without a resolution or
+    /// aspect the output is the picture's own size.
+    #[default]
+    Fit,
+    /// Scale to cover the output and trim what overhangs, centred.
+    Fill,
+    /// Scale to fit inside the output and pad the rest with black bars.
+    Letterbox,
+}
+
+impl std::str::FromStr for Framing {
+    type Err = LapsifyError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "fit" => Ok(Self::Fit),
+            "fill" | "crop" => Ok(Self::Fill),
+            "letterbox" | "pad" => Ok(Self::Letterbox),
+            other => Err(LapsifyError::message(format!(
+                "Unknown framing '{other}' (expected fit, fill or letterbox)"
+            ))),
+        }
+    }
+}
+
+/// How an in-between frame (slow motion, frame-rate conversion, a filled
+/// frame) is made from the two frames around it.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum FrameInterpolation {
+    /// Mix the two frames; fast, but anything moving shows twice.
+    #[default]
+    Blend,
+    /// Warp both frames along the motion between them, then mix.
+    Flow,
+}
+
+impl std::str::FromStr for FrameInterpolation {
+    type Err = LapsifyError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "blend" => Ok(Self::Blend),
+            "flow" | "optical-flow" => Ok(Self::Flow),
+            other => Err(LapsifyError::message(format!(
+                "Unknown frame interpolation '{other}' (expected blend or flow)"
+            ))),
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
 pub struct ExportSettings {
     /// Output directory.
@@ -136,9 +267,21 @@ pub struct ExportSettings {
     /// Video quality (CRF, 0-51, lower is better). Ignored by ProRes.
     #[serde(default = "default_quality")]
     pub quality: u32,
-    /// Target video resolution, e.g. "1920x1080", "4K".
+    /// Output size, e.g. "1920x1080", "4K". None = the size of the first
+    /// frame's picture.
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub resolution: Option<String>,
+    /// Output aspect ratio when no resolution is given: the largest frame
+    /// of this shape inside the first frame's picture.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub aspect: Option<Aspect>,
+    /// How pictures are placed in an output frame of another shape.
+    #[serde(default)]
+    pub framing: Framing,
+    /// Let `fit` scale a picture up to fill a larger output. Without it a
+    /// fitted picture only ever shrinks, and bars take up the rest.
+    #[serde(default)]
+    pub upscale: bool,
     #[serde(default)]
     pub codec: Codec,
     /// Encode with 10-bit chroma (h265 and prores only).
@@ -147,10 +290,267 @@ pub struct ExportSettings {
     /// JPEG quality for image-sequence output (1-100).
     #[serde(default = "default_jpeg_quality")]
     pub jpeg_quality: u8,
-    /// Motion blur as frame blending: each output frame averages this many
-    /// neighboring frames (video only). None/1 = off.
+    /// Motion blur: each output frame integrates the source frames around
+    /// it. A bare number (the original format) is a window in frames.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub motion_blur: Option<MotionBlur>,
+    /// Replace excluded frames with a blend of their nearest kept neighbors
+    /// instead of dropping them, so the clip keeps its length and pacing.
+    #[serde(default)]
+    pub fill_excluded: bool,
+    /// Resample to a constant capture-time rate, so a changing shooting
+    /// interval doesn't change the playback speed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retime: Option<Retime>,
+    /// Speed ramp: source frames each output frame advances, keyed by
+    /// source frame (0.25 = four times slower, 4 = four times faster).
+    /// Fractional positions blend neighboring frames.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub speed: Option<Curve>,
+    #[serde(default)]
+    pub frame_interpolation: FrameInterpolation,
+    /// Encode at this frame rate instead of `fps`, generating in-between
+    /// frames so the clip plays as long as it would at `fps`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub output_fps: Option<u32>,
+    /// Accumulate output frames into trails (star trails and the like).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accumulate: Option<Accumulate>,
+    /// Average aligned source frames to reduce noise (night sections).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stack: Option<Stack>,
+}
+
+/// A named deliverable cut from the project's grade and analysis with its
+/// own crop and export settings.
+#[derive(Debug, Clone, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct Output {
+    pub name: String,
+    /// Crop window over time; None shows the whole frame.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub crop: Option<CropTrack>,
+    pub export: ExportSettings,
+}
+
+/// Real-time retiming: every output frame covers the same stretch of
+/// capture time, using the analysis capture times.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct Retime {
+    /// Capture time per output frame, in seconds. None uses the median
+    /// capture interval.
     #[serde(default, skip_serializing_if = "Option::is_none")]
-    pub motion_blur: Option<u32>,
+    pub seconds_per_frame: Option<f32>,
+    /// Blend the two frames around each output time instead of taking the
+    /// nearest one (which duplicates or drops frames).
+    #[serde(default)]
+    pub blend: bool,
+}
+
+/// Motion blur rendered by blending source frames: each output frame
+/// integrates the frames within an exposure window centered on it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(from = "MotionBlurSpec")]
+pub struct MotionBlur {
+    /// Window length, keyframable by source frame: in source frames, or a
+    /// shutter angle in degrees with `unit: shutter_angle`. A fractional
+    /// window weights its edge frames partially; below one frame is off.
+    pub window: Curve,
+    pub unit: BlurUnit,
+    pub weighting: BlurWeighting,
+}
+
+impl MotionBlur {
+    pub fn frames(window: f32) -> Self {
+        Self {
+            window: Curve::Constant(window),
+            unit: BlurUnit::Frames,
+            weighting: BlurWeighting::Box,
+        }
+    }
+}
+
+#[derive(Deserialize, schemars::JsonSchema)]
+#[serde(untagged)]
+enum MotionBlurSpec {
+    /// A window in frames.
+    Frames(Curve),
+    Full {
+        window: Curve,
+        #[serde(default)]
+        unit: BlurUnit,
+        #[serde(default)]
+        weighting: BlurWeighting,
+    },
+}
+
+impl From<MotionBlurSpec> for MotionBlur {
+    fn from(spec: MotionBlurSpec) -> Self {
+        match spec {
+            MotionBlurSpec::Frames(window) => Self {
+                window,
+                unit: BlurUnit::Frames,
+                weighting: BlurWeighting::Box,
+            },
+            MotionBlurSpec::Full {
+                window,
+                unit,
+                weighting,
+            } => Self {
+                window,
+                unit,
+                weighting,
+            },
+        }
+    }
+}
+
+/// What a motion-blur window is measured in.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum BlurUnit {
+    /// Source frames.
+    #[default]
+    Frames,
+    /// Degrees of the step between output frames, as on a film camera:
+    /// 360 integrates everything up to the next output frame, 180 half of
+    /// it. Follows speed ramps and frame-rate conversion.
+    ShutterAngle,
+}
+
+/// How the frames in a motion-blur window are weighted.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum BlurWeighting {
+    /// Equal weights: an open shutter.
+    #[default]
+    Box,
+    /// Falling off linearly from the center: softer trails.
+    Triangle,
+    /// A Gaussian two standard deviations wide on each side.
+    Gaussian,
+}
+
+impl std::str::FromStr for BlurWeighting {
+    type Err = LapsifyError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "box" => Ok(Self::Box),
+            "triangle" | "tent" => Ok(Self::Triangle),
+            "gaussian" | "gauss" => Ok(Self::Gaussian),
+            other => Err(LapsifyError::message(format!(
+                "Unknown blur weighting '{other}' (expected box, triangle or gaussian)"
+            ))),
+        }
+    }
+}
+
+/// Accumulation across output frames: each frame shows the frames so far
+/// combined per pixel, and the export also writes the final combination as
+/// a still.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct Accumulate {
+    pub mode: AccumulateMode,
+    /// Combine only the last this many frames (2-64); None = every frame so
+    /// far. The median always needs a window. The window's frames are held
+    /// in memory at output size (64 4K frames is about 1.6 GB).
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub window: Option<u32>,
+    /// How much older frames fade per frame, 0-1: lighten and darken trails
+    /// fade back into the current frame (comet tails), and the average
+    /// becomes an exponential moving average. 0 keeps everything.
+    #[serde(default)]
+    pub decay: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, schemars::JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum AccumulateMode {
+    /// Per-pixel maximum: star trails, light painting.
+    Lighten,
+    /// Per-pixel minimum.
+    Darken,
+    /// Per-pixel mean: long exposure, noise reduction.
+    Average,
+    /// Per-pixel median over the window: removes passing objects.
+    Median,
+}
+
+impl std::str::FromStr for AccumulateMode {
+    type Err = LapsifyError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "lighten" | "max" => Ok(Self::Lighten),
+            "darken" | "min" => Ok(Self::Darken),
+            "average" | "mean" => Ok(Self::Average),
+            "median" => Ok(Self::Median),
+            other => Err(LapsifyError::message(format!(
+                "Unknown accumulation mode '{other}' (expected lighten, darken, average or median)"
+            ))),
+        }
+    }
+}
+
+/// Frame stacking: each output frame averages several source frames in
+/// linear light, aligned to the middle one, before color grading.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, schemars::JsonSchema)]
+pub struct Stack {
+    /// Source frames per stack.
+    pub frames: u32,
+    #[serde(default)]
+    pub mode: StackMode,
+    #[serde(default)]
+    pub align: StackAlign,
+}
+
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum StackMode {
+    /// Consecutive groups of frames become one output frame each, dividing
+    /// the frame count.
+    #[default]
+    Groups,
+    /// Every output frame stacks the frames centered on it, keeping the
+    /// frame count.
+    Sliding,
+}
+
+/// How stacked frames are registered to the middle frame, estimated on
+/// thumbnails.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, schemars::JsonSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum StackAlign {
+    /// Stack as shot (a locked-off camera).
+    None,
+    /// Shift each frame onto the middle one.
+    #[default]
+    Translation,
+    /// Shift and rotate: follows the sky turning around the pole.
+    Rotation,
+}
+
+impl std::str::FromStr for StackAlign {
+    type Err = LapsifyError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_lowercase().as_str() {
+            "none" | "off" => Ok(Self::None),
+            "translation" | "shift" => Ok(Self::Translation),
+            "rotation" | "rigid" => Ok(Self::Rotation),
+            other => Err(LapsifyError::message(format!(
+                "Unknown stack alignment '{other}' (expected none, translation or rotation)"
+            ))),
+        }
+    }
 }
 
 fn default_format() -> String {
@@ -179,9 +579,115 @@ impl Project {
                 project.version, PROJECT_VERSION
             )));
         }
+        let mut project = project;
+        project.resolve_anchors();
         Ok(project)
     }
 
+    /// The first source frame of the frame range.
+    pub fn first_frame(&self) -> u32 {
+        self.frame_range.map_or(0, |(start, _)| start as u32)
+    }
+
+    /// Source frames in the input folder, or when it can't be read, in the
+    /// recorded frame list.
+    pub fn source_frame_count(&self) -> Option<usize> {
+        if let Ok(files) = list_images(&self.input) {
+            return Some(files.len());
+        }
+        let analysis = self.analysis.as_ref()?;
+        analysis.frames.as_ref().map(Vec::len)
+    }
+
+    /// The analysis capture times, while they still belong to the source
+    /// frames: one per frame in the folder, read from the frames recorded
+    /// with them. None without capture times or once the folder changed
+    /// (until `project rebase` or a new analysis).
+    pub fn capture_times(&self) -> Option<&[i64]> {
+        let analysis = self.analysis.as_ref()?;
+        let times = analysis.capture_times_ms.as_deref()?;
+        let fresh = match list_images(&self.input) {
+            Ok(files) => {
+                files.len() == times.len()
+                    && analysis
+                        .frames
+                        .as_ref()
+                        .is_none_or(|recorded| frame_keys(&files).ok().as_ref() == Some(recorded))
+            }
+            Err(_) => analysis
+                .frames
+                .as_ref()
+                .is_none_or(|recorded| recorded.len() == times.len()),
+        };
+        fresh.then_some(times)
+    }
+
+    /// Whether a source frame is excluded from the render.
+    pub fn is_excluded(&self, frame: u32) -> bool {
+        self.excluded_frames.binary_search(&frame).is_ok()
+    }
+
+    /// Add frames to the exclusion list, keeping it sorted and unique.
+    pub fn exclude_frames(&mut self, frames: &[u32]) {
+        self.excluded_frames.extend_from_slice(frames);
+        self.excluded_frames.sort_unstable();
+        self.excluded_frames.dedup();
+    }
+
+    /// Place capture-time anchored keyframes on their frames using the
+    /// analysis capture times (stale ones are left for `validate` to
+    /// report). Called on load; call it again after the capture times
+    /// change.
+    pub fn resolve_anchors(&mut self) {
+        let Some(times) = self.capture_times().map(<[i64]>::to_vec) else {
+            return;
+        };
+        for (_, curve) in self.curves_mut() {
+            curve.resolve_anchors(&times);
+        }
+    }
+
+    /// Every keyframeable curve with its field name: the color grade, the
+    /// geometry tracks, the export timing, then each output's own.
+    pub fn curves_mut(&mut self) -> Vec<(&'static str, &mut Curve)> {
+        let c = &mut self.color;
+        let mut curves = vec![
+            ("exposure", &mut c.exposure),
+            ("temperature", &mut c.temperature),
+            ("tint", &mut c.tint),
+            ("brightness", &mut c.brightness),
+            ("contrast", &mut c.contrast),
+            ("highlights", &mut c.highlights),
+            ("shadows", &mut c.shadows),
+            ("whites", &mut c.whites),
+            ("blacks", &mut c.blacks),
+            ("gamma", &mut c.gamma),
+            ("saturation", &mut c.saturation),
+            ("vibrance", &mut c.vibrance),
+        ];
+        if let Some(crop) = &mut self.crop {
+            curves.extend(crop.curves_mut());
+        }
+        if let Some(perspective) = &mut self.perspective {
+            curves.extend(perspective.curves_mut());
+        }
+        if let Some(wb) = self
+            .analysis
+            .as_mut()
+            .and_then(|a| a.white_balance.as_mut())
+        {
+            curves.push(("white_balance.strength", &mut wb.strength));
+        }
+        curves.extend(self.export.curves_mut());
+        for output in &mut self.outputs {
+            if let Some(crop) = &mut output.crop {
+                curves.extend(crop.curves_mut());
+            }
+            curves.extend(output.export.curves_mut());
+        }
+        curves
+    }
+
     pub fn from_json_file(path: &Path) -> Result<Self> {
         let json = fs::read_to_string(path).map_err(|e| LapsifyError::io(path, e))?;
         Self::from_json(&json)
@@ -223,14 +729,77 @@ impl Project {
             ("saturation", &color.saturation, 0.0, 2.0),
             ("vibrance", &color.vibrance, -100.0, 100.0),
         ];
+        // Anchors first: one that can't be placed would otherwise surface
+        // as a keyframe out of order.
+        let capture_times = self.capture_times();
+        for (name, curve, _, _) in curves {
+            curve.validate_anchors(name, capture_times)?;
+        }
+        if let Some(ref crop) = self.crop {
+            for (name, curve) in crop.curves() {
+                curve.validate_anchors(name, capture_times)?;
+            }
+        }
+        if let Some(ref perspective) = self.perspective {
+            for (name, curve) in perspective.curves() {
+                curve.validate_anchors(name, capture_times)?;
+            }
+        }
+        // Expressions are sampled on the clip they render on; with neither
+        // the folder nor a recorded frame list there is no clip to check.
+        let timeline = self
+            .source_frame_count()
+            .map(|frames| Timeline::of(self, frames));
+        let check_range = |name: &'static str, curve: &Curve, min: f32, max: f32| match &timeline {
+            Some(timeline) => curve.validate_range_on(name, min, max, timeline),
+            None => curve.validate_range(name, min, max),
+        };
         for (name, curve, min, max) in curves {
             curve.validate(name)?;
-            curve.validate_range(name, min, max)?;
+            check_range(name, curve, min, max)?;
         }
         if let Some(ref tone_curve) = color.tone_curve {
             tone_curve.validate()?;
         }
+        if let Some(wb) = self
+            .analysis
+            .as_ref()
+            .and_then(|a| a.white_balance.as_ref())
+        {
+            wb.strength.validate("white_balance.strength")?;
+            check_range("white_balance.strength", &wb.strength, 0.0, 1.0)?;
+            // The compensation adds onto the sliders: the sum must stay in
+            // their range too.
+            if let Some(timeline) = &timeline {
+                for frame in 0..timeline.frames() as u32 {
+                    let combined = [
+                        (
+                            "temperature",
+                            color.temperature.sample_on(frame, timeline)
+                                + wb.temperature_on(frame, timeline),
+                        ),
+                        (
+                            "tint",
+                            color.tint.sample_on(frame, timeline) + wb.tint_on(frame, timeline),
+                        ),
+                    ];
+                    for (field, value) in combined {
+                        if !(-100.0..=100.0).contains(&value) {
+                            return Err(LapsifyError::InvalidParam {
+                                field,
+                                reason: format!(
+                                    "with the white balance compensation it reaches {value} at frame {frame}, outside valid range [-100, 100]; lower the layer's strength or smooth it more"
+                                ),
+                            });
+                        }
+                    }
+                }
+            }
+        }
 
+        if let Some(ref perspective) = self.perspective {
+            perspective.validate()?;
+        }
         if let Some(ref crop) = self.crop {
             crop.validate()?;
         }
@@ -238,11 +807,28 @@ impl Project {
         if !(1..=120).contains(&self.export.fps) {
             return Err(LapsifyError::message("FPS must be between 1 and 120"));
         }
+        if let Some(fps) = self.export.output_fps {
+            if !(1..=120).contains(&fps) {
+                return Err(LapsifyError::message(
+                    "Output FPS must be between 1 and 120",
+                ));
+            }
+        }
         if self.export.quality > 51 {
             return Err(LapsifyError::message(
                 "Quality (CRF) must be between 0 and 51",
             ));
         }
+        if let Some(res) = self.export.resolution.as_deref() {
+            let (width, height) = crate::export::parse_resolution(res)?;
+            if let Some(aspect) = self.export.aspect {
+                if width as u64 * aspect.height as u64 != height as u64 * aspect.width as u64 {
+                    return Err(LapsifyError::message(format!(
+                        "Resolution {width}x{height} is not {aspect}; give one or the other"
+                    )));
+                }
+            }
+        }
         if let Some((start, end)) = self.frame_range {
             if start > end {
                 return Err(LapsifyError::message(
@@ -250,6 +836,11 @@ impl Project {
                 ));
             }
         }
+        if self.excluded_frames.windows(2).any(|w| w[1] <= w[0]) {
+            return Err(LapsifyError::message(
+                "Excluded frames must be sorted with no duplicates",
+            ));
+        }
         if !(1..=100).contains(&self.export.jpeg_quality) {
             return Err(LapsifyError::message(
                 "JPEG quality must be between 1 and 100",
@@ -265,19 +856,166 @@ impl Project {
                 "ProRes requires the mov container (use -f mov)",
             ));
         }
-        if let Some(blur) = self.export.motion_blur {
-            if !(2..=128).contains(&blur) {
-                return Err(LapsifyError::message(
-                    "Motion blur must blend between 2 and 128 frames",
+        if let Some(ref blur) = self.export.motion_blur {
+            let max = match blur.unit {
+                BlurUnit::Frames => 128.0,
+                BlurUnit::ShutterAngle => 3600.0,
+            };
+            blur.window.validate("motion_blur.window")?;
+            check_range("motion_blur.window", &blur.window, 0.0, max)?;
+            blur.window
+                .validate_anchors("motion_blur.window", capture_times)?;
+        }
+        if let Some(seconds) = self.export.retime.and_then(|r| r.seconds_per_frame) {
+            if !(seconds.is_finite() && seconds > 0.0) {
+                return Err(LapsifyError::InvalidParam {
+                    field: "retime.seconds_per_frame",
+                    reason: "must be a positive number of seconds".into(),
+                });
+            }
+        }
+        if let Some(accumulate) = self.export.accumulate {
+            let invalid = |field, reason: &str| LapsifyError::InvalidParam {
+                field,
+                reason: reason.into(),
+            };
+            match accumulate.window {
+                Some(window) if !(2..=64).contains(&window) => {
+                    return Err(invalid(
+                        "accumulate.window",
+                        "must be between 2 and 64 frames",
+                    ));
+                }
+                None if accumulate.mode == AccumulateMode::Median => {
+                    return Err(invalid(
+                        "accumulate.window",
+                        "the median needs a window (e.g. 30 frames)",
+                    ));
+                }
+                _ => {}
+            }
+            if !(0.0..1.0).contains(&accumulate.decay) {
+                return Err(invalid(
+                    "accumulate.decay",
+                    "must be at least 0 and below 1",
                 ));
             }
         }
+        if let Some(stack) = self.export.stack {
+            if !(2..=64).contains(&stack.frames) {
+                return Err(LapsifyError::InvalidParam {
+                    field: "stack.frames",
+                    reason: "must be between 2 and 64".into(),
+                });
+            }
+            if self.export.motion_blur.is_some() {
+                return Err(LapsifyError::InvalidParam {
+                    field: "stack",
+                    reason: "stacking can't be combined with motion blur".into(),
+                });
+            }
+            // Stacks are whole source frames; the in-between frames of a
+            // resampled plan would collapse onto them.
+            if self.export.speed.is_some()
+                || self.export.retime.is_some()
+                || self.export.rate_factor() != 1.0
+            {
+                return Err(LapsifyError::InvalidParam {
+                    field: "stack",
+                    reason: "stacking can't be combined with speed, retime or output_fps".into(),
+                });
+            }
+        }
+        if let Some(ref speed) = self.export.speed {
+            speed.validate("speed")?;
+            check_range("speed", speed, 0.01, 100.0)?;
+            speed.validate_anchors("speed", capture_times)?;
+            if self.export.retime.is_some() {
+                return Err(LapsifyError::InvalidParam {
+                    field: "speed",
+                    reason: "a speed ramp can't be combined with retiming".into(),
+                });
+            }
+        }
+
+        let mut folders = vec![&self.export.output];
+        for (i, output) in self.outputs.iter().enumerate() {
+            if output.name.trim().is_empty() {
+                return Err(LapsifyError::InvalidParam {
+                    field: "outputs.name",
+                    reason: "every output needs a name".into(),
+                });
+            }
+            if self.outputs[..i].iter().any(|o| o.name == output.name) {
+                return Err(LapsifyError::message(format!(
+                    "Output name '{}' is used twice",
+                    output.name
+                )));
+            }
+            if folders.contains(&&output.export.output) {
+                return Err(LapsifyError::message(format!(
+                    "Output '{}' writes to {}, which another output already uses",
+                    output.name,
+                    output.export.output.display()
+                )));
+            }
+            folders.push(&output.export.output);
+            self.reframe(output)
+                .validate()
+                .map_err(|e| LapsifyError::message(format!("Output '{}': {e}", output.name)))?;
+        }
 
         Ok(())
     }
+
+    /// The project as rendered for `output`: its crop and export settings
+    /// over this grade and analysis.
+    pub fn reframe(&self, output: &Output) -> Project {
+        Project {
+            crop: output.crop.clone(),
+            export: output.export.clone(),
+            outputs: Vec::new(),
+            ..self.clone()
+        }
+    }
+
+    /// Every render this project makes: the main export, then each output.
+    pub fn renders(&self) -> Vec<Project> {
+        let main = Project {
+            outputs: Vec::new(),
+            ..self.clone()
+        };
+        std::iter::once(main)
+            .chain(self.outputs.iter().map(|output| self.reframe(output)))
+            .collect()
+    }
 }
 
 impl ExportSettings {
+    /// The keyframeable export curves with their field names.
+    pub fn curves_mut(&mut self) -> Vec<(&'static str, &mut Curve)> {
+        let mut curves: Vec<(&'static str, &mut Curve)> = self
+            .speed
+            .iter_mut()
+            .map(|speed| ("speed", speed))
+            .collect();
+        if let Some(blur) = &mut self.motion_blur {
+            curves.push(("motion_blur.window", &mut blur.window));
+        }
+        curves
+    }
+
+    /// The frame rate frames are encoded at.
+    pub fn output_rate(&self) -> u32 {
+        self.output_fps.unwrap_or(self.fps)
+    }
+
+    /// Output frames per source frame of playback: above 1 when converting
+    /// up to a higher output frame rate.
+    pub fn rate_factor(&self) -> f64 {
+        self.output_rate() as f64 / self.fps as f64
+    }
+
     pub fn new(output: PathBuf) -> Self {
         Self {
             output,
@@ -285,10 +1023,20 @@ impl ExportSettings {
             fps: default_fps(),
             quality: default_quality(),
             resolution: None,
+            aspect: None,
+            framing: Framing::default(),
+            upscale: false,
             codec: Codec::default(),
             ten_bit: false,
             jpeg_quality: default_jpeg_quality(),
             motion_blur: None,
+            fill_excluded: false,
+            retime: None,
+            speed: None,
+            frame_interpolation: FrameInterpolation::Blend,
+            output_fps: None,
+            accumulate: None,
+            stack: None,
         }
     }
 }
@@ -303,14 +1051,27 @@ mod tests {
             version: PROJECT_VERSION,
             input: PathBuf::from("frames"),
             frame_range: None,
+            excluded_frames: Vec::new(),
             interpolation: Default::default(),
             color: ColorGrade::default(),
+            perspective: None,
             crop: None,
             export: ExportSettings::new(PathBuf::from("out")),
+            outputs: Vec::new(),
             analysis: None,
         }
     }
 
+    /// An analysis that recorded `count` source frames.
+    fn recorded_frames(count: usize) -> Option<Vec<crate::analysis::FrameKey>> {
+        let key = crate::analysis::FrameKey {
+            name: String::new(),
+            size: 0,
+            modified_unix: 0,
+        };
+        Some(vec![key; count])
+    }
+
     #[test]
     fn json_roundtrip() {
         let mut project = minimal_project();
@@ -337,6 +1098,36 @@ mod tests {
         assert!(project.validate().is_ok());
     }
 
+    #[test]
+    fn motion_blur_reads_a_bare_frame_count() {
+        let json = r#"{
+            "version": 1,
+            "input": "frames",
+            "export": { "output": "out", "motion_blur": 3 }
+        }"#;
+        let project = Project::from_json(json).unwrap();
+        assert_eq!(project.export.motion_blur, Some(MotionBlur::frames(3.0)));
+
+        let json = r#"{
+            "version": 1,
+            "input": "frames",
+            "export": { "output": "out", "motion_blur": {
+                "window": [{ "frame": 0, "value": 90 }, { "frame": 50, "value": 270 }],
+                "unit": "shutter_angle",
+                "weighting": "gaussian"
+            } }
+        }"#;
+        let project = Project::from_json(json).unwrap();
+        let blur = project.export.motion_blur.as_ref().unwrap();
+        assert_eq!(blur.unit, BlurUnit::ShutterAngle);
+        assert_eq!(blur.weighting, BlurWeighting::Gaussian);
+        assert_eq!(blur.window.sample(50), 270.0);
+        assert!(project.validate().is_ok());
+
+        let reparsed = Project::from_json(&project.to_json_pretty().unwrap()).unwrap();
+        assert_eq!(reparsed.export.motion_blur, project.export.motion_blur);
+    }
+
     #[test]
     fn rejects_unknown_version() {
         let json = r#"{ "version": 99, "input": "frames", "export": { "output": "out" } }"#;
@@ -357,4 +1148,241 @@ mod tests {
         project.frame_range = Some((10, 5));
         assert!(project.validate().is_err());
     }
+
+    #[test]
+    fn validate_rejects_stacks_on_a_resampled_plan() {
+        let mut project = minimal_project();
+        project.export.stack = Some(Stack {
+            frames: 3,
+            mode: StackMode::Sliding,
+            align: StackAlign::None,
+        });
+        assert!(project.validate().is_ok());
+        project.export.output_fps = Some(48);
+        assert!(project.validate().is_err());
+    }
+
+    #[test]
+    fn validate_limits_temperature_with_white_balance() {
+        // The white balance layer adds onto the temperature slider.
+        let mut project = minimal_project();
+        project.color.temperature = Curve::Constant(60.0);
+        let mut wb = crate::analysis::WhiteBalanceLayer {
+            temperature: vec![0.0, 50.0],
+            tint: vec![],
+            strength: Curve::Constant(1.0),
+            smoothing_frames: 0,
+            frames_missing_wb: vec![],
+            computed_at_unix: 0,
+            source_fingerprint: String::new(),
+        };
+        project.analysis = Some(Analysis {
+            frames: recorded_frames(2),
+            white_balance: Some(wb.clone()),
+            ..Analysis::default()
+        });
+        assert!(project.validate().is_err());
+        wb.strength = Curve::Constant(0.5);
+        project.analysis.as_mut().unwrap().white_balance = Some(wb.clone());
+        assert!(project.validate().is_ok());
+        wb.strength = Curve::Constant(1.5);
+        project.analysis.as_mut().unwrap().white_balance = Some(wb);
+        assert!(project.validate().is_err());
+    }
+
+    #[test]
+    fn validate_checks_expressions_over_the_recorded_clip() {
+        let mut project = minimal_project();
+        project.color.exposure =
+            Curve::Expression(crate::expr::Expression::parse("frame / 500").unwrap());
+        project.analysis = Some(Analysis {
+            frames: recorded_frames(1000),
+            ..Analysis::default()
+        });
+        assert!(project.validate().is_ok());
+        project.analysis = Some(Analysis {
+            frames: recorded_frames(3000),
+            ..Analysis::default()
+        });
+        assert!(project.validate().is_err());
+    }
+
+    #[test]
+    fn outputs_reframe_the_shared_grade() {
+        let mut project = minimal_project();
+        project.color.contrast = Curve::Constant(1.2);
+        let mut export = ExportSettings::new(PathBuf::from("out-vertical"));
+        export.aspect = Some("9:16".parse().unwrap());
+        project.outputs.push(Output {
+            name: "vertical".to_string(),
+            crop: None,
+            export,
+        });
+        assert!(project.validate().is_ok());
+
+        let renders = project.renders();
+        assert_eq!(renders.len(), 2);
+        assert!(renders.iter().all(|r| r.outputs.is_empty()));
+        assert_eq!(renders[1].export.output, PathBuf::from("out-vertical"));
+        assert_eq!(renders[1].color.contrast, Curve::Constant(1.2));
+
+        // Two outputs writing into one folder would overwrite each other.
+        let mut clash = project.clone();
+        clash.outputs[0].export.output = PathBuf::from("out");
+        assert!(clash.validate().is_err());
+
+        let mut twice = project.clone();
+        let mut again = twice.outputs[0].clone();
+        again.export.output = PathBuf::from("out-other");
+        twice.outputs.push(again);
+        assert!(twice.validate().is_err());
+
+        // An output's own settings are validated too.
+        project.outputs[0].export.fps = 500;
+        assert!(project.validate().is_err());
+    }
+
+    #[test]
+    fn anchored_keyframes_follow_capture_time() {
+        let json = r#"{
+            "version": 1,
+            "input": "frames",
+            "color": { "exposure": [
+                { "frame": 0, "value": 0.0 },
+                { "at": "2026-06-21 21:14:00", "value": 1.0 }
+            ] },
+            "export": { "output": "out" },
+            "analysis": { "capture_times_ms": [] }
+        }"#;
+        let minute =
+            |m: i64| crate::exif::parse_capture_time("2026-06-21 21:10").unwrap() + m * 60_000;
+
+        // One frame a minute from 21:10: 21:14 is frame 4.
+        let mut value: serde_json::Value = serde_json::from_str(json).unwrap();
+        value["analysis"]["capture_times_ms"] = (0..10).map(minute).collect();
+        let project = Project::from_json(&value.to_string()).unwrap();
+        assert!(project.validate().is_ok());
+        let Curve::Keyframed(kfs) = &project.color.exposure else {
+            panic!("expected keyframes");
+        };
+        assert_eq!(kfs[1].frame, 4);
+
+        // Drop the first two frames: the keyframe moves to frame 2.
+        value["analysis"]["capture_times_ms"] = (2..10).map(minute).collect();
+        let project = Project::from_json(&value.to_string()).unwrap();
+        let Curve::Keyframed(kfs) = &project.color.exposure else {
+            panic!("expected keyframes");
+        };
+        assert_eq!(kfs[1].frame, 2);
+
+        // No capture times to resolve against, or a moment outside the span.
+        value["analysis"]["capture_times_ms"] = serde_json::Value::Null;
+        assert!(Project::from_json(&value.to_string())
+            .unwrap()
+            .validate()
+            .is_err());
+        value["analysis"]["capture_times_ms"] = (5..10).map(minute).collect();
+        assert!(Project::from_json(&value.to_string())
+            .unwrap()
+            .validate()
+            .is_err());
+
+        // Capture times recorded for another set of frames are stale.
+        value["analysis"]["capture_times_ms"] = (0..10).map(minute).collect();
+        let key = serde_json::json!({ "name": "f.jpg", "size": 0, "modified_unix": 0 });
+        value["analysis"]["frames"] = vec![key; 12].into();
+        let error = Project::from_json(&value.to_string())
+            .unwrap()
+            .validate()
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("current source frames"),
+            "{error}"
+        );
+        value["analysis"]["frames"] = serde_json::Value::Null;
+
+        // Two anchors can't share a frame, nor take an unanchored one's.
+        let anchored = |at: &str| serde_json::json!({ "at": at, "value": 2.0 });
+        let exposure = value["color"]["exposure"].as_array_mut().unwrap();
+        exposure.push(anchored("2026-06-21 21:14:10"));
+        assert!(Project::from_json(&value.to_string())
+            .unwrap()
+            .validate()
+            .is_err());
+        value["color"]["exposure"][2] = anchored("2026-06-21 21:10:05");
+        assert!(Project::from_json(&value.to_string())
+            .unwrap()
+            .validate()
+            .is_err());
+        value["color"]["exposure"][2] = anchored("2026-06-21 21:15:00");
+        assert!(Project::from_json(&value.to_string())
+            .unwrap()
+            .validate()
+            .is_ok());
+    }
+
+    #[test]
+    fn anchored_speed_ramps_are_placed() {
+        let minute =
+            |m: i64| crate::exif::parse_capture_time("2026-06-21 21:10").unwrap() + m * 60_000;
+        let export = serde_json::json!({
+            "output": "out",
+            "speed": [
+                { "frame": 0, "value": 1.0 },
+                { "at": "2026-06-21 21:14:00", "value": 0.25 }
+            ]
+        });
+        let mut vertical = export.clone();
+        vertical["output"] = "out-vertical".into();
+        let value = serde_json::json!({
+            "version": 1,
+            "input": "frames",
+            "export": export,
+            "outputs": [{ "name": "vertical", "export": vertical }],
+            "analysis": { "capture_times_ms": (0..10).map(minute).collect::<Vec<_>>() }
+        });
+        let project = Project::from_json(&value.to_string()).unwrap();
+        project.validate().unwrap();
+        for speed in [&project.export.speed, &project.outputs[0].export.speed] {
+            let Some(Curve::Keyframed(kfs)) = speed else {
+                panic!("expected keyframes");
+            };
+            assert_eq!(kfs[1].frame, 4);
+        }
+    }
+
+    #[test]
+    fn anchored_blur_windows_are_placed() {
+        let minute =
+            |m: i64| crate::exif::parse_capture_time("2026-06-21 21:10").unwrap() + m * 60_000;
+        let export = serde_json::json!({
+            "output": "out",
+            "motion_blur": {
+                "window": [
+                    { "frame": 0, "value": 2.0 },
+                    { "at": "2026-06-21 21:16:00", "value": 8.0 }
+                ]
+            }
+        });
+        let mut vertical = export.clone();
+        vertical["output"] = "out-vertical".into();
+        let value = serde_json::json!({
+            "version": 1,
+            "input": "frames",
+            "export": export,
+            "outputs": [{ "name": "vertical", "export": vertical }],
+            "analysis": { "capture_times_ms": (0..10).map(minute).collect::<Vec<_>>() }
+        });
+        let project = Project::from_json(&value.to_string()).unwrap();
+        project.validate().unwrap();
+        for blur in [
+            &project.export.motion_blur,
+            &project.outputs[0].export.motion_blur,
+        ] {
+            let Curve::Keyframed(kfs) = &blur.as_ref().unwrap().window else {
+                panic!("expected keyframes");
+            };
+            assert_eq!(kfs[1].frame, 6);
+        }
+    }
 }
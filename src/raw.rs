@@ -123,10 +123,27 @@ pub fn raw_exif(path: &Path) -> Option<FrameExif> {
             .map(|v| v as f32)
             .or(exif.iso_speed.map(|v| v as f32))
             .filter(|v| *v > 0.0),
+        exposure_bias: exif
+            .exposure_bias
+            .and_then(|r| (r.d != 0).then(|| r.n as f32 / r.d as f32)),
         datetime_ms,
     })
 }
 
+/// The camera's as-shot white balance multipliers (R, G, B), normalized to
+/// green. `decode_raw` develops with exactly these, so they describe how
+/// each frame's colors were shifted. None when the file doesn't record them.
+pub fn as_shot_wb(path: &Path) -> Option<[f32; 3]> {
+    let source = open_source(path).ok()?;
+    let decoder = rawler::get_decoder(&source).ok()?;
+    let rawimage = decoder
+        .raw_image(&source, &RawDecodeParams::default(), true)
+        .ok()?;
+    let [r, g, b, _] = rawimage.wb_coeffs;
+    let usable = |v: f32| v.is_finite() && v > 0.0;
+    (usable(r) && usable(g) && usable(b)).then(|| [r / g, 1.0, b / g])
+}
+
 fn apply_orientation(img: DynamicImage, orientation: Orientation) -> DynamicImage {
     match orientation {
         Orientation::Normal | Orientation::Unknown => img,
@@ -158,6 +175,7 @@ mod tests {
         assert!(decode_raw(Path::new("/nonexistent/file.dng")).is_err());
         assert!(raw_dimensions(Path::new("/nonexistent/file.dng")).is_err());
         assert!(raw_exif(Path::new("/nonexistent/file.dng")).is_none());
+        assert!(as_shot_wb(Path::new("/nonexistent/file.dng")).is_none());
     }
 
     #[test]
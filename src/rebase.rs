@@ -0,0 +1,658 @@
+//! Remap a project onto a changed source folder.
+//!
+//! Every frame index in a project — keyframes, EV events, the frame range and
+//! the per-frame analysis arrays — is a position in `list_images`, so
+//! deleting one bad frame shifts everything after it. The analysis records a
+//! `FrameKey` per frame; `rebase` matches those keys against the current
+//! folder (by filename, then by size and mtime to catch renames) and moves
+//! all frame-indexed data onto the new positions. Inserted frames have no
+//! measurements yet: their per-frame values are interpolated from their
+//! neighbors until the analysis is re-run.
+//!
+//! Splitting a folder into scenes is the same remap: each scene's project is
+//! the original rebased onto the scene's frames alone.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use crate::analysis::scenes::{scenes_from_cuts, Scene, SceneCut};
+use crate::analysis::{frame_keys, source_fingerprint, FrameKey, LumaSeries};
+use crate::curve::{Curve, Keyframe};
+use crate::error::{LapsifyError, Result};
+use crate::project::Project;
+use crate::timeline::Timeline;
+
+/// Correspondence between the recorded frame list and the current one.
+#[derive(Debug, Clone)]
+pub struct FrameMap {
+    old_to_new: Vec<Option<usize>>,
+    new_to_old: Vec<Option<usize>>,
+    renamed: usize,
+}
+
+impl FrameMap {
+    /// Match frames by filename, then pair the remaining frames whose size
+    /// and mtime are unique on both sides (a rename).
+    pub fn between(old: &[FrameKey], new: &[FrameKey]) -> Self {
+        let mut old_to_new = vec![None; old.len()];
+        let mut new_to_old = vec![None; new.len()];
+
+        let by_name: HashMap<&str, usize> = old
+            .iter()
+            .enumerate()
+            .map(|(i, k)| (k.name.as_str(), i))
+            .collect();
+        for (j, key) in new.iter().enumerate() {
+            if let Some(&i) = by_name.get(key.name.as_str()) {
+                old_to_new[i] = Some(j);
+                new_to_old[j] = Some(i);
+            }
+        }
+
+        let stamp_counts = |keys: &[FrameKey], matched: &[Option<usize>]| {
+            let mut counts: HashMap<(u64, u64), (usize, usize)> = HashMap::new();
+            for (i, key) in keys.iter().enumerate() {
+                if matched[i].is_none() {
+                    let entry = counts
+                        .entry((key.size, key.modified_unix))
+                        .or_insert((0, i));
+                    entry.0 += 1;
+                }
+            }
+            counts
+        };
+        let old_stamps = stamp_counts(old, &old_to_new);
+        let new_stamps = stamp_counts(new, &new_to_old);
+        let mut renamed = 0;
+        for (stamp, &(count, j)) in &new_stamps {
+            if let Some(&(1, i)) = old_stamps.get(stamp) {
+                if count == 1 {
+                    old_to_new[i] = Some(j);
+                    new_to_old[j] = Some(i);
+                    renamed += 1;
+                }
+            }
+        }
+
+        Self {
+            old_to_new,
+            new_to_old,
+            renamed,
+        }
+    }
+
+    /// Recorded frames still present (including renamed ones).
+    pub fn kept(&self) -> usize {
+        self.old_to_new.iter().flatten().count()
+    }
+
+    /// Current frames with no recorded counterpart.
+    pub fn inserted(&self) -> Vec<u32> {
+        (0..self.new_to_old.len())
+            .filter(|&j| self.new_to_old[j].is_none())
+            .map(|j| j as u32)
+            .collect()
+    }
+
+    /// New position of a recorded frame index. A deleted frame moves to the
+    /// next surviving frame (or the previous one at the end); indices past
+    /// the recorded list keep their distance from its last surviving frame.
+    pub fn frame(&self, old: u32) -> u32 {
+        let old = old as usize;
+        let len = self.old_to_new.len();
+        let survivor = |i: usize| self.old_to_new[i].map(|j| (i, j));
+        if old < len {
+            let found = (old..len)
+                .find_map(survivor)
+                .or_else(|| (0..old).rev().find_map(survivor));
+            return found.map_or(old as u32, |(_, j)| j as u32);
+        }
+        match (0..len).rev().find_map(survivor) {
+            Some((i, j)) => (j + (old - i)) as u32,
+            None => old as u32,
+        }
+    }
+
+    /// `frame` for the last frame of a range: a deleted frame moves back to
+    /// the previous surviving frame (or the next one at the start), so the
+    /// range never takes in a frame that was outside it.
+    pub fn last_frame(&self, old: u32) -> u32 {
+        let old = old as usize;
+        let len = self.old_to_new.len();
+        if old >= len {
+            return self.frame(old as u32);
+        }
+        let survivor = |i: usize| self.old_to_new[i];
+        (0..=old)
+            .rev()
+            .find_map(survivor)
+            .or_else(|| (old..len).find_map(survivor))
+            .map_or(old as u32, |j| j as u32)
+    }
+
+    /// Whether a recorded frame index is still in the folder (indices past
+    /// the recorded list count as present).
+    fn survives(&self, old: u32) -> bool {
+        self.old_to_new
+            .get(old as usize)
+            .is_none_or(|j| j.is_some())
+    }
+
+    /// Recorded frame indices that still exist, at their new positions.
+    pub fn indices(&self, frames: &[u32]) -> Vec<u32> {
+        frames
+            .iter()
+            .filter_map(|&f| self.old_to_new.get(f as usize).copied().flatten())
+            .map(|j| j as u32)
+            .collect()
+    }
+
+    /// A per-frame series on the new positions. Frames without a recorded
+    /// value are interpolated between their neighbors (held at the ends).
+    /// An empty series stays empty.
+    pub fn series<T: Copy>(&self, values: &[T], lerp: impl Fn(T, T, f32) -> T) -> Vec<T> {
+        let known: Vec<Option<T>> = self
+            .new_to_old
+            .iter()
+            .map(|old| old.and_then(|i| values.get(i).copied()))
+            .collect();
+        let anchors: Vec<usize> = (0..known.len()).filter(|&j| known[j].is_some()).collect();
+        if anchors.is_empty() {
+            return Vec::new();
+        }
+        (0..known.len())
+            .map(|j| {
+                if let Some(v) = known[j] {
+                    return v;
+                }
+                let after = anchors.partition_point(|&a| a < j);
+                match (after.checked_sub(1).map(|a| anchors[a]), anchors.get(after)) {
+                    (Some(lo), Some(&hi)) => {
+                        let t = (j - lo) as f32 / (hi - lo) as f32;
+                        lerp(known[lo].unwrap(), known[hi].unwrap(), t)
+                    }
+                    (Some(lo), None) => known[lo].unwrap(),
+                    (None, Some(&hi)) => known[hi].unwrap(),
+                    (None, None) => unreachable!("anchors is not empty"),
+                }
+            })
+            .collect()
+    }
+}
+
+/// What a rebase changed.
+#[derive(Debug, Clone, Default)]
+pub struct RebaseReport {
+    /// Recorded frames found again, by name or after a rename.
+    pub kept: usize,
+    /// Of those, frames recognized under a new name.
+    pub renamed: usize,
+    /// Recorded frames no longer in the folder.
+    pub deleted: usize,
+    /// Current frames that were not recorded (values interpolated).
+    pub inserted: Vec<u32>,
+    /// Keyframes dropped because a deletion moved them onto another
+    /// keyframe's frame.
+    pub merged_keyframes: usize,
+}
+
+impl RebaseReport {
+    pub fn is_unchanged(&self) -> bool {
+        self.deleted == 0 && self.renamed == 0 && self.inserted.is_empty()
+    }
+}
+
+/// Remap a project's frame-indexed data from its recorded frame list onto
+/// `image_files` (the current folder), and record the new list.
+pub fn rebase(project: &mut Project, image_files: &[PathBuf]) -> Result<RebaseReport> {
+    let current = frame_keys(image_files)?;
+    let fingerprint = source_fingerprint(image_files)?;
+    rebase_onto(project, current, &fingerprint)
+}
+
+fn rebase_onto(
+    project: &mut Project,
+    current: Vec<FrameKey>,
+    fingerprint: &str,
+) -> Result<RebaseReport> {
+    let recorded = project
+        .analysis
+        .as_ref()
+        .and_then(|a| a.frames.clone())
+        .ok_or_else(|| {
+            LapsifyError::message(
+                "The project has no recorded frame list to rebase from (analysis commands record one)",
+            )
+        })?;
+    let map = FrameMap::between(&recorded, &current);
+    if map.kept() == 0 && !recorded.is_empty() {
+        return Err(LapsifyError::message(
+            "None of the recorded frames are in the source folder; check the input path",
+        ));
+    }
+
+    let mut report = RebaseReport {
+        kept: map.kept(),
+        renamed: map.renamed,
+        deleted: recorded.len() - map.kept(),
+        inserted: map.inserted(),
+        merged_keyframes: 0,
+    };
+
+    // Anchored keyframes follow their capture time instead (see below).
+    for (_, curve) in project.curves_mut() {
+        if let Curve::Keyframed(keyframes) = curve {
+            let before = keyframes.len();
+            // On a collision, the keyframe whose own frame survived wins.
+            let moved: Vec<(bool, Keyframe)> = keyframes
+                .drain(..)
+                .map(|mut k| {
+                    let displaced = k.at.is_none() && !map.survives(k.frame);
+                    if k.at.is_none() {
+                        k.frame = map.frame(k.frame);
+                    }
+                    (displaced, k)
+                })
+                .collect();
+            // Anchored keyframes still sit on their old frames: they only
+            // collide once placed, which `validate` reports.
+            let (anchored, mut moved): (Vec<_>, Vec<_>) =
+                moved.into_iter().partition(|(_, k)| k.at.is_some());
+            moved.sort_by_key(|(displaced, k)| (k.frame, *displaced));
+            moved.dedup_by_key(|(_, k)| k.frame);
+            *keyframes = moved.into_iter().chain(anchored).map(|(_, k)| k).collect();
+            report.merged_keyframes += before - keyframes.len();
+        }
+    }
+    project.excluded_frames = map.indices(&project.excluded_frames);
+    if let Some((start, end)) = project.frame_range {
+        let range = (
+            map.frame(start as u32) as usize,
+            map.last_frame(end as u32) as usize,
+        );
+        if range.1 < range.0 {
+            return Err(LapsifyError::message(format!(
+                "Every frame of the frame range {start}-{end} was deleted; set a new range"
+            )));
+        }
+        project.frame_range = Some(range);
+    }
+
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+    let lerp2 = |a: [f32; 2], b: [f32; 2], t: f32| [lerp(a[0], b[0], t), lerp(a[1], b[1], t)];
+    let with_inserted = |frames: Vec<u32>| {
+        let mut frames = [frames, report.inserted.clone()].concat();
+        frames.sort_unstable();
+        frames.dedup();
+        frames
+    };
+
+    let analysis = project.analysis.as_mut().expect("checked above");
+    let remap_luma = |series: &mut LumaSeries| {
+        series.values = map.series(&series.values, lerp);
+        series.chromaticity = map.series(&series.chromaticity, lerp2);
+        series.excluded_frames = map.indices(&series.excluded_frames);
+        series.source_fingerprint = fingerprint.to_string();
+    };
+    if let Some(series) = &mut analysis.source_luminance {
+        remap_luma(series);
+    }
+    if let Some(series) = &mut analysis.developed_luminance {
+        remap_luma(series);
+    }
+    if let Some(layer) = &mut analysis.holy_grail {
+        layer.raw = map.series(&layer.raw, lerp);
+        layer.fit_residuals = map.series(&layer.fit_residuals, lerp);
+        layer.frames_missing_exif = with_inserted(map.indices(&layer.frames_missing_exif));
+        for event in &mut layer.ev_events {
+            event.frame = map.frame(event.frame);
+        }
+        layer.source_fingerprint = fingerprint.to_string();
+    }
+    if let Some(layer) = &mut analysis.white_balance {
+        layer.temperature = map.series(&layer.temperature, lerp);
+        layer.tint = map.series(&layer.tint, lerp);
+        layer.frames_missing_wb = with_inserted(map.indices(&layer.frames_missing_wb));
+        layer.source_fingerprint = fingerprint.to_string();
+    }
+    if let Some(layer) = &mut analysis.deflicker {
+        layer.target = map.series(&layer.target, lerp);
+        layer.offsets = map.series(&layer.offsets, lerp);
+        layer.source_fingerprint = fingerprint.to_string();
+    }
+    if let Some(layer) = &mut analysis.stabilize {
+        layer.shift = map.series(&layer.shift, lerp2);
+        layer.scale = map.series(&layer.scale, lerp);
+        layer.rotation = map.series(&layer.rotation, lerp);
+        layer.source_fingerprint = fingerprint.to_string();
+    }
+    if let Some(layer) = &mut analysis.outliers {
+        layer.flags.retain(|flag| map.survives(flag.frame));
+        for flag in &mut layer.flags {
+            flag.frame = map.frame(flag.frame);
+        }
+        layer.source_fingerprint = fingerprint.to_string();
+    }
+    if let Some(layer) = &mut analysis.scenes {
+        let cuts: Vec<(u32, SceneCut)> = layer
+            .scenes
+            .iter()
+            .filter_map(|scene| Some((map.frame(scene.start), scene.cut?)))
+            .collect();
+        layer.scenes = scenes_from_cuts(current.len(), &cuts);
+        layer.source_fingerprint = fingerprint.to_string();
+    }
+    if let Some(times) = &mut analysis.capture_times_ms {
+        *times = map.series(times, |a, b, t| {
+            a + ((b - a) as f64 * t as f64).round() as i64
+        });
+    }
+    analysis.frames = Some(current);
+
+    project.resolve_anchors();
+    Ok(report)
+}
+
+/// The project for one scene after its frames moved to `image_files` (its
+/// own folder). Curves keep the keyframes inside the scene, plus keyframes
+/// at its ends holding the values the curve had there when keyframes lay
+/// beyond them; every frame-indexed layer keeps only the scene's frames.
+pub fn extract_scene(project: &Project, scene: &Scene, image_files: &[PathBuf]) -> Result<Project> {
+    let current = frame_keys(image_files)?;
+    let fingerprint = source_fingerprint(image_files)?;
+    extract_scene_onto(project, scene, current, &fingerprint)
+}
+
+fn extract_scene_onto(
+    project: &Project,
+    scene: &Scene,
+    current: Vec<FrameKey>,
+    fingerprint: &str,
+) -> Result<Project> {
+    let total = project
+        .analysis
+        .as_ref()
+        .and_then(|a| a.frames.as_ref())
+        .map_or(0, Vec::len);
+    let (start, end) = (scene.start, scene.end);
+    let timeline = Timeline::of(project, total);
+
+    let mut scene_project = project.clone();
+    for (name, curve) in scene_project.curves_mut() {
+        let sample = |curve: &Curve, frame: u32| {
+            // Geometry samples in plain frame space.
+            if name.starts_with("crop.") || name.starts_with("perspective.") {
+                curve.sample(frame)
+            } else {
+                curve.sample_on(frame, &timeline)
+            }
+        };
+        let (at_start, at_end) = (sample(curve, start), sample(curve, end));
+        let Curve::Keyframed(keyframes) = curve else {
+            continue;
+        };
+        let before = keyframes.iter().any(|k| k.frame < start);
+        let after = keyframes.iter().any(|k| k.frame > end);
+        keyframes.retain(|k| (start..=end).contains(&k.frame));
+        if before && keyframes.first().is_none_or(|k| k.frame != start) {
+            keyframes.insert(0, Keyframe::new(start, at_start));
+        }
+        if after && keyframes.last().is_none_or(|k| k.frame != end) {
+            keyframes.push(Keyframe::new(end, at_end));
+        }
+    }
+    if let Some(layer) = scene_project
+        .analysis
+        .as_mut()
+        .and_then(|a| a.holy_grail.as_mut())
+    {
+        // Events past the scene would land on its last frame; earlier ones
+        // moving to its first frame keep the compensation level.
+        layer.ev_events.retain(|event| event.frame <= end);
+    }
+
+    rebase_onto(&mut scene_project, current, fingerprint)?;
+    scene_project.frame_range = None;
+
+    let analysis = scene_project
+        .analysis
+        .as_mut()
+        .expect("rebase needs analysis");
+    analysis.scenes = None;
+    if let Some(layer) = &mut analysis.holy_grail {
+        // The baseline tilt runs over the clip position: re-express the
+        // scene's share of it over the scene alone.
+        let span = total.saturating_sub(1).max(1) as f32;
+        let offset = layer.rotate * start as f32 / span;
+        for value in &mut layer.raw {
+            *value += offset;
+        }
+        layer.rotate *= (end - start) as f32 / span;
+    }
+    Ok(scene_project)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::analysis::{Analysis, DeflickerLayer};
+    use crate::project::{ColorGrade, ExportSettings, PROJECT_VERSION};
+
+    fn keys(names: &[&str]) -> Vec<FrameKey> {
+        names
+            .iter()
+            .enumerate()
+            .map(|(i, name)| FrameKey {
+                name: name.to_string(),
+                size: 1000 + i as u64,
+                modified_unix: 0,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn deleted_frames_shift_everything_after_them() {
+        let old = keys(&["a", "b", "c", "d"]);
+        let new = vec![old[0].clone(), old[2].clone(), old[3].clone()];
+        let map = FrameMap::between(&old, &new);
+        assert_eq!(map.kept(), 3);
+        assert_eq!(map.frame(0), 0);
+        // The deleted frame's keyframe moves onto the next surviving frame.
+        assert_eq!(map.frame(1), 1);
+        assert_eq!(map.frame(3), 2);
+        // Past the recorded list, the distance to the last frame is kept.
+        assert_eq!(map.frame(5), 4);
+        assert_eq!(
+            map.series(&[0.0, 1.0, 2.0, 3.0], |a, b, t| a + (b - a) * t),
+            vec![0.0, 2.0, 3.0]
+        );
+    }
+
+    #[test]
+    fn range_ends_move_inward_onto_surviving_frames() {
+        let old = keys(&["a", "b", "c", "d", "e"]);
+        // "b" and "d" deleted: a range of b..=d keeps just "c".
+        let new = vec![old[0].clone(), old[2].clone(), old[4].clone()];
+        let map = FrameMap::between(&old, &new);
+        assert_eq!(map.frame(1), 1);
+        assert_eq!(map.last_frame(3), 1);
+        // A kept end frame stays put.
+        assert_eq!(map.last_frame(4), 2);
+        // With nothing before it, a deleted end falls forward.
+        let new = vec![old[1].clone(), old[2].clone()];
+        assert_eq!(FrameMap::between(&old, &new).last_frame(0), 0);
+    }
+
+    #[test]
+    fn renames_are_matched_by_size_and_mtime() {
+        let old = keys(&["a", "b", "c"]);
+        let mut new = old.clone();
+        new[1].name = "b-renamed".to_string();
+        let map = FrameMap::between(&old, &new);
+        assert_eq!(map.kept(), 3);
+        assert_eq!(map.renamed, 1);
+        assert!(map.inserted().is_empty());
+    }
+
+    #[test]
+    fn inserted_frames_are_interpolated() {
+        let old = keys(&["a", "c"]);
+        let mut new = keys(&["a", "b", "c"]);
+        new[0] = old[0].clone();
+        new[2] = old[1].clone();
+        new[1].size = 5;
+        let map = FrameMap::between(&old, &new);
+        assert_eq!(map.inserted(), vec![1]);
+        assert_eq!(
+            map.series(&[0.0, 1.0], |a, b, t| a + (b - a) * t),
+            vec![0.0, 0.5, 1.0]
+        );
+    }
+
+    #[test]
+    fn rebase_moves_keyframes_and_layers() {
+        let old = keys(&["a", "b", "c", "d"]);
+        let mut project = Project {
+            version: PROJECT_VERSION,
+            input: PathBuf::from("frames"),
+            frame_range: Some((0, 1)),
+            excluded_frames: Vec::new(),
+            interpolation: Default::default(),
+            color: ColorGrade {
+                exposure: Curve::Keyframed(vec![
+                    Keyframe::new(0, 0.0),
+                    Keyframe::new(1, 0.5),
+                    Keyframe::new(2, 1.0),
+                ]),
+                ..ColorGrade::default()
+            },
+            perspective: None,
+            crop: None,
+            export: ExportSettings::new(PathBuf::from("out")),
+            outputs: Vec::new(),
+            analysis: Some(Analysis {
+                deflicker: Some(DeflickerLayer {
+                    target: vec![0.1, 0.2, 0.3, 0.4],
+                    offsets: vec![0.0, 0.1, 0.2, 0.3],
+                    smoothing_frames: 30,
+                    region: None,
+                    threshold_ev: 0.03,
+                    passes_run: 1,
+                    converged: true,
+                    computed_at_unix: 0,
+                    source_fingerprint: String::new(),
+                }),
+                frames: Some(old.clone()),
+                ..Analysis::default()
+            }),
+        };
+
+        // Frame "b" deleted: its keyframe lands on "c", which keeps its own.
+        let new = vec![old[0].clone(), old[2].clone(), old[3].clone()];
+        let report = rebase_onto(&mut project, new.clone(), "fp").unwrap();
+        assert_eq!(report.deleted, 1);
+        assert_eq!(report.merged_keyframes, 1);
+        let Curve::Keyframed(kfs) = &project.color.exposure else {
+            panic!("expected keyframes");
+        };
+        assert_eq!(kfs.iter().map(|k| k.frame).collect::<Vec<_>>(), vec![0, 1]);
+        assert_eq!(kfs[1].value, 1.0);
+        // The range ended on "b", so it ends on "a" now, not on "c".
+        assert_eq!(project.frame_range, Some((0, 0)));
+        let analysis = project.analysis.as_ref().unwrap();
+        let deflicker = analysis.deflicker.as_ref().unwrap();
+        assert_eq!(deflicker.offsets, vec![0.0, 0.2, 0.3]);
+        assert_eq!(deflicker.source_fingerprint, "fp");
+        assert_eq!(analysis.frames.as_deref(), Some(&new[..]));
+
+        // Rebasing onto the same folder again changes nothing.
+        assert!(rebase_onto(&mut project, new, "fp").unwrap().is_unchanged());
+    }
+
+    #[test]
+    fn rebase_moves_speed_ramps() {
+        let minute =
+            |m: i64| crate::exif::parse_capture_time("2026-06-21 21:10").unwrap() + m * 60_000;
+        let old = keys(&["a", "b", "c", "d"]);
+        let mut export = ExportSettings::new(PathBuf::from("out"));
+        export.speed = Some(Curve::Keyframed(vec![
+            Keyframe {
+                at: Some("2026-06-21 21:12:00".to_string()),
+                ..Keyframe::new(2, 0.5)
+            },
+            Keyframe::new(3, 1.0),
+        ]));
+        let mut project = Project {
+            version: PROJECT_VERSION,
+            input: PathBuf::from("frames"),
+            frame_range: None,
+            excluded_frames: Vec::new(),
+            interpolation: Default::default(),
+            color: ColorGrade::default(),
+            perspective: None,
+            crop: None,
+            export,
+            outputs: Vec::new(),
+            analysis: Some(Analysis {
+                capture_times_ms: Some((0..4).map(minute).collect()),
+                frames: Some(old.clone()),
+                ..Analysis::default()
+            }),
+        };
+
+        // Frame "b" deleted: the anchor stays on 21:12 ("c") and the plain
+        // keyframe moves down with "d".
+        let new = vec![old[0].clone(), old[2].clone(), old[3].clone()];
+        rebase_onto(&mut project, new, "fp").unwrap();
+        let Some(Curve::Keyframed(kfs)) = &project.export.speed else {
+            panic!("expected keyframes");
+        };
+        assert_eq!(kfs.iter().map(|k| k.frame).collect::<Vec<_>>(), vec![1, 2]);
+        project.validate().unwrap();
+    }
+
+    #[test]
+    fn extracted_scene_keeps_its_frames_and_curve_values() {
+        let old = keys(&["a", "b", "c", "d", "e", "f"]);
+        let mut project = Project {
+            version: PROJECT_VERSION,
+            input: PathBuf::from("frames"),
+            frame_range: None,
+            excluded_frames: vec![1, 4],
+            interpolation: Default::default(),
+            color: ColorGrade {
+                exposure: Curve::Keyframed(vec![Keyframe::new(0, 0.0), Keyframe::new(4, 1.0)]),
+                ..ColorGrade::default()
+            },
+            perspective: None,
+            crop: None,
+            export: ExportSettings::new(PathBuf::from("out")),
+            outputs: Vec::new(),
+            analysis: Some(Analysis {
+                capture_times_ms: Some(vec![0, 10, 20, 9000, 9010, 9020]),
+                frames: Some(old.clone()),
+                ..Analysis::default()
+            }),
+        };
+        let scenes = scenes_from_cuts(6, &[(3, SceneCut::TimeGap { seconds: 8.98 })]);
+        project.analysis.as_mut().unwrap().scenes = Some(crate::analysis::SceneLayer {
+            scenes: scenes.clone(),
+            min_gap_s: 1.0,
+            cut_threshold: 0.35,
+            measure_dim: 256,
+            computed_at_unix: 0,
+            source_fingerprint: String::new(),
+        });
+
+        let scene = extract_scene_onto(&project, &scenes[1], old[3..].to_vec(), "fp").unwrap();
+        let Curve::Keyframed(kfs) = &scene.color.exposure else {
+            panic!("expected keyframes");
+        };
+        let kfs: Vec<(u32, f32)> = kfs.iter().map(|k| (k.frame, k.value)).collect();
+        assert_eq!(kfs, vec![(0, 0.75), (1, 1.0)]);
+        assert_eq!(scene.excluded_frames, vec![1]);
+        let analysis = scene.analysis.as_ref().unwrap();
+        assert_eq!(analysis.capture_times_ms, Some(vec![9000, 9010, 9020]));
+        assert!(analysis.scenes.is_none());
+    }
+}
@@ -1,23 +1,52 @@
-use std::path::Path;
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use image::{imageops, DynamicImage, RgbImage};
+use rayon::prelude::*;
 
+use crate::blur::{blur_weights, window_frames};
 use crate::color::{ColorParams, FrameColorOps};
 use crate::error::{LapsifyError, Result};
-use crate::project::Project;
-
-pub fn render_frame(img: DynamicImage, project: &Project, frame: u32) -> Result<DynamicImage> {
-    let params = ColorParams::at_frame(project, frame);
-    let rgb_img = img.into_rgb8();
-    let (width, height) = rgb_img.dimensions();
-
-    // Crop first so color work only touches pixels that survive.
-    let mut out: RgbImage = match &project.crop {
-        Some(track) => {
-            let (x, y, w, h) = track.pixel_rect(frame, width, height)?;
-            imageops::crop_imm(&rgb_img, x, y, w, h).to_image()
+use crate::framing::{place, scaled_output_size};
+use crate::project::{FrameInterpolation, Framing, Project};
+use crate::resample::{resample_rotated, resample_window};
+use crate::source::{PlannedFrame, SourceFrames};
+use crate::stack::{stack_frames, StackCache};
+use crate::timeline::Timeline;
+
+/// Render source frame `frame` of a sequence of `frames` source frames.
+pub fn render_frame(
+    img: DynamicImage,
+    project: &Project,
+    frame: u32,
+    frames: usize,
+) -> Result<DynamicImage> {
+    render_scaled(img, project, frame, frames, 1.0)
+}
+
+/// [`render_frame`] for a source shrunk to `scale` of its full size.
+fn render_scaled(
+    img: DynamicImage,
+    project: &Project,
+    frame: u32,
+    frames: usize,
+    scale: f32,
+) -> Result<DynamicImage> {
+    let params = ColorParams::at_frame(project, frame, frames);
+    let mut rgb_img = img.into_rgb8();
+    if let Some(perspective) = &project.perspective {
+        if !perspective.is_identity_at(frame) {
+            rgb_img = perspective.warp(&rgb_img, frame);
         }
-        None => rgb_img,
+    }
+    // Crop and frame first so color work only touches pixels that survive.
+    let export = &project.export;
+    let framed = project.crop.is_some() || export.resolution.is_some() || export.aspect.is_some();
+    let mut out = if framed {
+        frame_picture(&rgb_img, project, frame, scale)?
+    } else {
+        rgb_img
     };
 
     FrameColorOps::from_params(&params).apply(&mut out);
@@ -25,9 +54,242 @@ pub fn render_frame(img: DynamicImage, project: &Project, frame: u32) -> Result<
     Ok(DynamicImage::ImageRgb8(out))
 }
 
-/// Render a single frame for preview. With `max_dim`, the source is
-/// downscaled before the pipeline runs — the crop track is in normalized
-/// coordinates, so it applies identically at any scale.
+/// The frame's picture — the crop window, or the whole source — placed
+/// in the output frame. The exact window is resampled straight to its place
+/// in one step, so slow pans and zooms glide instead of stepping a pixel at
+/// a time; whatever the picture leaves uncovered is black.
+fn frame_picture(img: &RgbImage, project: &Project, frame: u32, scale: f32) -> Result<RgbImage> {
+    let (width, height) = img.dimensions();
+    let size = scaled_output_size(project, width, height, scale)?;
+    let ((x, y, w, h), degrees) = match &project.crop {
+        Some(track) => (
+            track.window_px(frame, width, height),
+            track.rotation_at(frame),
+        ),
+        None => ((0.0, 0.0, width as f32, height as f32), 0.0),
+    };
+    if w <= 0.0 || h <= 0.0 {
+        return Err(LapsifyError::message(format!(
+            "Crop window is empty at frame {frame}"
+        )));
+    }
+
+    let framing = match project.export.framing {
+        Framing::Fit if project.export.upscale => Framing::Letterbox,
+        framing => framing,
+    };
+    let placement = place((w, h), size, framing);
+    let centre = (x + w / 2.0, y + h / 2.0);
+    let (shown_w, shown_h) = placement.source;
+    let (to_x, to_y, to_w, to_h) = placement.target;
+    let picture = if degrees != 0.0 {
+        resample_rotated(img, centre, placement.source, degrees, to_w, to_h)
+    } else {
+        let window = (
+            centre.0 - shown_w / 2.0,
+            centre.1 - shown_h / 2.0,
+            shown_w,
+            shown_h,
+        );
+        resample_window(img, window, to_w, to_h)
+    };
+    if (to_w, to_h) == size {
+        return Ok(picture);
+    }
+    let mut canvas = RgbImage::new(size.0, size.1);
+    imageops::replace(&mut canvas, &picture, to_x as i64, to_y as i64);
+    Ok(canvas)
+}
+
+/// Render one planned output frame from `sources`. An in-between frame
+/// renders both neighbors with their own parameters and blends them
+/// (along the motion between them with flow interpolation), so nothing
+/// measured on an excluded frame reaches the output. A stack is averaged
+/// before grading and graded as its reference frame; `stacks` carries
+/// what overlapping stacks share.
+pub fn render_planned(
+    sources: &SourceFrames,
+    project: &Project,
+    planned: PlannedFrame,
+    stacks: &StackCache,
+) -> Result<RgbImage> {
+    let frames = sources.files().len();
+    let render = |frame: usize| -> Result<RgbImage> {
+        let img = sources.load(frame)?;
+        Ok(render_frame(img, project, frame as u32, frames)?.into_rgb8())
+    };
+    match planned {
+        PlannedFrame::Source(frame) => render(frame),
+        PlannedFrame::Blend { from, to, t, .. } => {
+            let a = render(from)?;
+            if to == from || t <= 0.0 {
+                return Ok(a);
+            }
+            let mut b = render(to)?;
+            // A keyframed crop can size the two windows differently.
+            if b.dimensions() != a.dimensions() {
+                b = imageops::resize(&b, a.width(), a.height(), imageops::FilterType::Triangle);
+            }
+            Ok(match project.export.frame_interpolation {
+                FrameInterpolation::Blend => blend(&a, &b, t),
+                FrameInterpolation::Flow => crate::flow::interpolate(&a, &b, t),
+            })
+        }
+        PlannedFrame::Stack { frame, first, last } => {
+            let stacked = stack_frames(sources, project, frame, (first, last), stacks)?;
+            Ok(render_frame(stacked, project, frame as u32, frames)?.into_rgb8())
+        }
+    }
+}
+
+/// Renders the output frames of a plan, with motion blur when the project
+/// asks for it. A blurred frame integrates several rendered source frames
+/// and its neighbors need most of the same ones, so recently rendered
+/// source frames are kept for reuse.
+pub struct PlanRenderer<'a> {
+    files: &'a [PathBuf],
+    project: &'a Project,
+    timeline: Timeline<'a>,
+    cache: Mutex<FrameCache>,
+    stacks: StackCache,
+}
+
+impl<'a> PlanRenderer<'a> {
+    /// `files` is the full source sequence plans index into.
+    pub fn new(files: &'a [PathBuf], project: &'a Project) -> Self {
+        Self {
+            files,
+            project,
+            timeline: Timeline::of(project, files.len()),
+            cache: Mutex::new(FrameCache::default()),
+            stacks: StackCache::default(),
+        }
+    }
+
+    pub fn render(&self, planned: PlannedFrame) -> Result<RgbImage> {
+        self.render_from(planned, &SourceFrames::new(self.files))
+    }
+
+    /// Render reading source frames through `sources`, which other outputs
+    /// of the same frame may share.
+    pub fn render_from(&self, planned: PlannedFrame, sources: &SourceFrames) -> Result<RgbImage> {
+        let weights = blur_weights_at(
+            self.project,
+            &self.timeline,
+            planned.position(),
+            self.files.len(),
+        );
+        if weights.len() < 2 {
+            return render_planned(sources, self.project, planned, &self.stacks);
+        }
+        // Enough for every worker's window at once.
+        let capacity = rayon::current_num_threads() + weights.len() + 2;
+        integrate(&weights, |frame| {
+            if let Some(hit) = self.cache.lock().unwrap().get(frame) {
+                return Ok(hit);
+            }
+            let img = sources.load(frame)?;
+            let rendered = Arc::new(
+                render_frame(img, self.project, frame as u32, self.files.len())?.into_rgb8(),
+            );
+            self.cache
+                .lock()
+                .unwrap()
+                .insert(frame, rendered.clone(), capacity);
+            Ok(rendered)
+        })
+    }
+}
+
+/// The most recently used rendered source frames.
+#[derive(Default)]
+struct FrameCache {
+    frames: VecDeque<(usize, Arc<RgbImage>)>,
+}
+
+impl FrameCache {
+    fn get(&mut self, frame: usize) -> Option<Arc<RgbImage>> {
+        let index = self.frames.iter().position(|(f, _)| *f == frame)?;
+        let entry = self.frames.remove(index)?;
+        let img = entry.1.clone();
+        self.frames.push_back(entry);
+        Some(img)
+    }
+
+    fn insert(&mut self, frame: usize, img: Arc<RgbImage>, capacity: usize) {
+        if self.frames.iter().any(|(f, _)| *f == frame) {
+            return;
+        }
+        self.frames.push_back((frame, img));
+        while self.frames.len() > capacity {
+            self.frames.pop_front();
+        }
+    }
+}
+
+/// Motion-blur weights of the source frames for an output frame at
+/// `position`, or nothing without blur.
+fn blur_weights_at(
+    project: &Project,
+    timeline: &Timeline,
+    position: f64,
+    frames: usize,
+) -> Vec<(usize, f32)> {
+    let Some(ref blur) = project.export.motion_blur else {
+        return Vec::new();
+    };
+    let frame = (position.round() as u32).min(frames.saturating_sub(1) as u32);
+    let window = window_frames(blur, project, frame, timeline);
+    let excluded = &project.excluded_frames;
+    blur_weights(position, window, blur.weighting, frames, |i| {
+        excluded.binary_search(&(i as u32)).is_err()
+    })
+}
+
+/// Weighted sum of rendered source frames, rendered in parallel. Frames
+/// sized differently from the first (a keyframed crop) are scaled to it.
+fn integrate(
+    weights: &[(usize, f32)],
+    render: impl Fn(usize) -> Result<Arc<RgbImage>> + Sync,
+) -> Result<RgbImage> {
+    let rendered: Vec<Arc<RgbImage>> = weights
+        .par_iter()
+        .map(|&(frame, _)| render(frame))
+        .collect::<Result<_>>()?;
+    let (width, height) = rendered[0].dimensions();
+    let mut sum = vec![0.0f32; width as usize * height as usize * 3];
+    for (img, &(_, weight)) in rendered.iter().zip(weights) {
+        let scaled;
+        let img: &RgbImage = if img.dimensions() == (width, height) {
+            img
+        } else {
+            scaled = imageops::resize(&**img, width, height, imageops::FilterType::Triangle);
+            &scaled
+        };
+        for (s, &v) in sum.iter_mut().zip(img.iter()) {
+            *s += v as f32 * weight;
+        }
+    }
+    let data = sum
+        .into_iter()
+        .map(|v| v.round().clamp(0.0, 255.0) as u8)
+        .collect();
+    Ok(RgbImage::from_raw(width, height, data).expect("buffer matches the frame size"))
+}
+
+/// Per-pixel mix of two same-sized images, `t` of the way from `a` to `b`.
+fn blend(a: &RgbImage, b: &RgbImage, t: f32) -> RgbImage {
+    let mut out = a.clone();
+    for (o, q) in out.iter_mut().zip(b.iter()) {
+        *o = (*o as f32 + (*q as f32 - *o as f32) * t).round() as u8;
+    }
+    out
+}
+
+/// Render a single frame for preview, motion blur included. With
+/// `max_dim`, the source is downscaled before the pipeline runs — the crop
+/// track is in normalized coordinates, so it applies identically at any
+/// scale — and a fixed output resolution is scaled down with it.
 pub fn render_preview(project: &Project, frame: u32, max_dim: Option<u32>) -> Result<DynamicImage> {
     let files = crate::source::list_images(&project.input)?;
     let path = files.get(frame as usize).ok_or_else(|| {
@@ -37,13 +299,31 @@ pub fn render_preview(project: &Project, frame: u32, max_dim: Option<u32>) -> Re
         ))
     })?;
 
-    let mut img = crate::source::load_frame(path)?;
-    if let Some(dim) = max_dim {
-        if img.width() > dim || img.height() > dim {
-            img = img.thumbnail(dim, dim);
+    let render = |path: &Path, frame: u32| -> Result<DynamicImage> {
+        let mut img = crate::source::load_frame(path)?;
+        let full_width = img.width();
+        if let Some(dim) = max_dim {
+            if img.width() > dim || img.height() > dim {
+                img = img.thumbnail(dim, dim);
+            }
         }
+        let scale = img.width() as f32 / full_width as f32;
+        let out = render_scaled(img, project, frame, files.len(), scale)?;
+        Ok(match max_dim {
+            Some(dim) if out.width() > dim || out.height() > dim => out.thumbnail(dim, dim),
+            _ => out,
+        })
+    };
+
+    let timeline = Timeline::of(project, files.len());
+    let weights = blur_weights_at(project, &timeline, frame as f64, files.len());
+    if weights.len() < 2 {
+        return render(path, frame);
     }
-    render_frame(img, project, frame)
+    let blurred = integrate(&weights, |i| {
+        Ok(Arc::new(render(&files[i], i as u32)?.into_rgb8()))
+    })?;
+    Ok(DynamicImage::ImageRgb8(blurred))
 }
 
 pub fn generate_output_filename(input_path: &Path, output_format: &str) -> String {
@@ -81,8 +361,8 @@ pub fn save_image(
 mod tests {
     use super::*;
     use crate::crop::{CropRect, CropTrack};
-    use crate::curve::{Curve, Keyframe};
-    use crate::project::{ColorGrade, ExportSettings, Project, PROJECT_VERSION};
+    use crate::curve::{Curve, Easing, Keyframe};
+    use crate::project::{ColorGrade, ExportSettings, Framing, Project, PROJECT_VERSION};
     use image::{ImageBuffer, Rgb};
     use std::path::PathBuf;
 
@@ -91,14 +371,17 @@ mod tests {
             version: PROJECT_VERSION,
             input: PathBuf::from("frames"),
             frame_range: None,
+            excluded_frames: Vec::new(),
             interpolation: Default::default(),
             color: ColorGrade::default(),
+            perspective: None,
             crop: None,
             export: {
                 let mut export = ExportSettings::new(PathBuf::from("out"));
                 export.format = "jpg".to_string();
                 export
             },
+            outputs: Vec::new(),
             analysis: None,
         }
     }
@@ -114,7 +397,7 @@ mod tests {
     #[test]
     fn identity_render_keeps_pixels() {
         let img = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(4, 4, Rgb([100, 150, 200])));
-        let out = render_frame(img, &test_project(), 0).unwrap().to_rgb8();
+        let out = render_frame(img, &test_project(), 0, 1).unwrap().to_rgb8();
         assert_eq!(out.dimensions(), (4, 4));
         assert_eq!(out.get_pixel(0, 0).0, [100, 150, 200]);
     }
@@ -126,7 +409,7 @@ mod tests {
         let img = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(2, 2, Rgb([100, 100, 100])));
         let mut project = test_project();
         project.color.exposure = Curve::Constant(1.0);
-        let out = render_frame(img, &project, 0).unwrap().to_rgb8();
+        let out = render_frame(img, &project, 0, 1).unwrap().to_rgb8();
 
         let linear_in = srgb_to_linear(100.0 / 255.0);
         let linear_out = srgb_to_linear(out.get_pixel(0, 0).0[0] as f32 / 255.0);
@@ -144,8 +427,10 @@ mod tests {
             Curve::Keyframed(vec![Keyframe::new(0, 0.0), Keyframe::new(10, 1.0)]);
 
         let img = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(2, 2, Rgb([64, 64, 64])));
-        let at_start = render_frame(img.clone(), &project, 0).unwrap().to_rgb8();
-        let at_end = render_frame(img, &project, 10).unwrap().to_rgb8();
+        let at_start = render_frame(img.clone(), &project, 0, 11)
+            .unwrap()
+            .to_rgb8();
+        let at_end = render_frame(img, &project, 10, 11).unwrap().to_rgb8();
         assert_eq!(at_start.get_pixel(0, 0).0, [64, 64, 64]);
         assert!(
             at_end.get_pixel(0, 0).0[0] > 80,
@@ -163,7 +448,7 @@ mod tests {
             width: 0.60,
             height: 0.55,
         }));
-        let out = render_frame(img, &project, 0).unwrap();
+        let out = render_frame(img, &project, 0, 1).unwrap();
         assert_eq!(out.to_rgb8().dimensions(), (120, 110));
     }
 
@@ -184,12 +469,110 @@ mod tests {
             y: Curve::Constant(0.0),
             width: Curve::Constant(0.5),
             height: Curve::Constant(0.5),
+            rotation: Curve::Constant(0.0),
+            aspect: None,
         });
 
-        let left = render_frame(img.clone(), &project, 0).unwrap().to_rgb8();
-        let right = render_frame(img, &project, 10).unwrap().to_rgb8();
+        let left = render_frame(img.clone(), &project, 0, 11)
+            .unwrap()
+            .to_rgb8();
+        let right = render_frame(img, &project, 10, 11).unwrap().to_rgb8();
         assert_eq!(left.dimensions(), (50, 50));
         assert_eq!(left.get_pixel(0, 0).0, [0, 0, 0]);
         assert_eq!(right.get_pixel(0, 0).0, [255, 255, 255]);
     }
+
+    #[test]
+    fn zooming_crop_keeps_the_first_frame_size() {
+        let img = DynamicImage::ImageRgb8(ImageBuffer::from_fn(100, 100, |x, _| {
+            Rgb([x as u8 * 2, 0, 0])
+        }));
+        let linear = |from: f32, to: f32| {
+            Curve::Keyframed(vec![
+                Keyframe {
+                    easing: Easing::Linear,
+                    ..Keyframe::new(0, from)
+                },
+                Keyframe::new(10, to),
+            ])
+        };
+        let mut project = test_project();
+        project.crop = Some(CropTrack {
+            x: linear(0.0, 0.1),
+            y: Curve::Constant(0.0),
+            width: linear(0.6, 0.3),
+            height: linear(0.6, 0.3),
+            rotation: Curve::Constant(0.0),
+            aspect: None,
+        });
+
+        // Frame 1 starts 1% (one pixel) in and spans 57 source pixels,
+        // magnified to the 60 pixels of frame 0's window.
+        let out = render_frame(img.clone(), &project, 1, 11)
+            .unwrap()
+            .to_rgb8();
+        assert_eq!(out.dimensions(), (60, 60));
+        let zoomed = render_frame(img, &project, 10, 11).unwrap().to_rgb8();
+        assert_eq!(zoomed.dimensions(), (60, 60));
+        // Two output pixels per source pixel from x = 10: the ramp steps by
+        // one per output pixel instead of two.
+        let ramp: Vec<i32> = (20..24)
+            .map(|x| zoomed.get_pixel(x, 25)[0] as i32)
+            .collect();
+        assert!(
+            ramp.windows(2).all(|w| (w[1] - w[0] - 1).abs() <= 1),
+            "{ramp:?}"
+        );
+    }
+
+    #[test]
+    fn framing_places_the_picture_without_stretching() {
+        let img = DynamicImage::ImageRgb8(ImageBuffer::from_pixel(100, 100, Rgb([128u8; 3])));
+        let mut project = test_project();
+        project.export.resolution = Some("200x100".to_string());
+        let render =
+            |project: &Project| render_frame(img.clone(), project, 0, 1).unwrap().to_rgb8();
+
+        // Fit keeps the picture at its size, centred in the output.
+        project.export.framing = Framing::Fit;
+        let fitted = render(&project);
+        assert_eq!(fitted.dimensions(), (200, 100));
+        assert_eq!(fitted.get_pixel(10, 50).0, [0; 3]);
+        assert_eq!(fitted.get_pixel(100, 50).0, [128; 3]);
+
+        project.export.framing = Framing::Fill;
+        let filled = render(&project);
+        assert_eq!(filled.dimensions(), (200, 100));
+        assert!(filled.pixels().all(|p| p.0 == [128; 3]));
+
+        project.export.framing = Framing::Letterbox;
+        let boxed = render(&project);
+        assert_eq!(boxed.dimensions(), (200, 100));
+        assert_eq!(boxed.get_pixel(10, 50).0, [0; 3]);
+        assert_eq!(boxed.get_pixel(100, 50).0, [128; 3]);
+        assert_eq!(boxed.get_pixel(190, 50).0, [0; 3]);
+    }
+
+    #[test]
+    fn rotated_crop_turns_the_picture() {
+        // Top half white: a window turned a quarter counterclockwise shows
+        // the top of the image on its right.
+        let img = ImageBuffer::from_fn(100, 100, |_, y| Rgb([if y < 50 { 255u8 } else { 0 }; 3]));
+        let mut project = test_project();
+        let mut track = CropTrack::from_rect(CropRect {
+            x: 0.0,
+            y: 0.0,
+            width: 1.0,
+            height: 1.0,
+        });
+        track.rotation = Curve::Constant(90.0);
+        project.crop = Some(track);
+
+        let out = render_frame(DynamicImage::ImageRgb8(img), &project, 0, 1)
+            .unwrap()
+            .to_rgb8();
+        assert_eq!(out.dimensions(), (100, 100));
+        assert_eq!(out.get_pixel(95, 50).0, [255, 255, 255]);
+        assert_eq!(out.get_pixel(5, 50).0, [0, 0, 0]);
+    }
 }
@@ -1,9 +1,12 @@
+use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 
 use image::DynamicImage;
 
 use crate::error::{LapsifyError, Result};
+use crate::project::{Retime, Stack, StackMode};
 
 pub fn is_image_file(path: &Path) -> bool {
     path.extension()
@@ -46,6 +49,50 @@ pub fn load_frame(path: &Path) -> Result<DynamicImage> {
     Ok(image::open(path)?)
 }
 
+/// The source frames one output frame reads. Shared by every output
+/// rendered from that frame, it decodes each file once and hands the others
+/// copies; on its own it decodes on demand and keeps nothing.
+pub struct SourceFrames<'a> {
+    files: &'a [PathBuf],
+    decoded: Option<Mutex<HashMap<usize, Arc<DynamicImage>>>>,
+}
+
+impl<'a> SourceFrames<'a> {
+    /// Decode on every request.
+    pub fn new(files: &'a [PathBuf]) -> Self {
+        Self {
+            files,
+            decoded: None,
+        }
+    }
+
+    /// Keep what is decoded for the other outputs of the same frame.
+    pub fn shared(files: &'a [PathBuf]) -> Self {
+        Self {
+            files,
+            decoded: Some(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// The full source sequence.
+    pub fn files(&self) -> &'a [PathBuf] {
+        self.files
+    }
+
+    /// Source frame `index`, decoded.
+    pub fn load(&self, index: usize) -> Result<DynamicImage> {
+        let Some(decoded) = &self.decoded else {
+            return load_frame(&self.files[index]);
+        };
+        if let Some(img) = decoded.lock().unwrap().get(&index) {
+            return Ok((**img).clone());
+        }
+        let img = Arc::new(load_frame(&self.files[index])?);
+        decoded.lock().unwrap().insert(index, img.clone());
+        Ok((*img).clone())
+    }
+}
+
 /// List image files in a directory, sorted by filename.
 pub fn list_images(input_dir: &Path) -> Result<Vec<PathBuf>> {
     let mut image_files: Vec<PathBuf> = fs::read_dir(input_dir)
@@ -136,9 +183,284 @@ pub fn select_frame_range(
     Ok((filtered, start_idx, end_idx, total_available))
 }
 
+/// One output frame of a render, by source frame index.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlannedFrame {
+    /// Render this source frame.
+    Source(usize),
+    /// Source frames `from` and `to`, each rendered, then blended `t` of the
+    /// way to `to`: the stand-in for an excluded frame, or a moment between
+    /// two frames when retiming. `frame` is the source frame it stands for.
+    Blend {
+        frame: usize,
+        from: usize,
+        to: usize,
+        t: f32,
+    },
+    /// Source frames `first..=last` (less excluded ones) stacked onto the
+    /// kept frame nearest `frame`, the one whose parameters they take.
+    Stack {
+        frame: usize,
+        first: usize,
+        last: usize,
+    },
+}
+
+impl PlannedFrame {
+    /// The source frame this output frame stands for.
+    pub fn frame(&self) -> usize {
+        match *self {
+            PlannedFrame::Source(frame)
+            | PlannedFrame::Blend { frame, .. }
+            | PlannedFrame::Stack { frame, .. } => frame,
+        }
+    }
+
+    /// Where the output frame sits on the source sequence, in frames.
+    pub fn position(&self) -> f64 {
+        match *self {
+            PlannedFrame::Source(frame) | PlannedFrame::Stack { frame, .. } => frame as f64,
+            PlannedFrame::Blend { from, to, t, .. } => {
+                from as f64 + (to as f64 - from as f64) * t as f64
+            }
+        }
+    }
+}
+
+/// The output frames for source frames `start..=end` of `total`: excluded
+/// frames are dropped, or with `fill` replaced by a blend of the nearest
+/// kept frames on either side (anywhere in the sequence; held at the ends).
+pub fn plan_frames(
+    start: usize,
+    end: usize,
+    total: usize,
+    excluded: &[u32],
+    fill: bool,
+) -> Result<Vec<PlannedFrame>> {
+    let is_excluded = |i: usize| excluded.binary_search(&(i as u32)).is_ok();
+    let mut plan = Vec::with_capacity(end + 1 - start);
+    for frame in start..=end {
+        if !is_excluded(frame) {
+            plan.push(PlannedFrame::Source(frame));
+            continue;
+        }
+        if !fill {
+            continue;
+        }
+        let before = (0..frame).rev().find(|&i| !is_excluded(i));
+        let after = (frame + 1..total).find(|&i| !is_excluded(i));
+        match (before, after) {
+            (Some(from), Some(to)) => plan.push(PlannedFrame::Blend {
+                frame,
+                from,
+                to,
+                t: (frame - from) as f32 / (to - from) as f32,
+            }),
+            (Some(only), None) | (None, Some(only)) => plan.push(PlannedFrame::Blend {
+                frame,
+                from: only,
+                to: only,
+                t: 0.0,
+            }),
+            (None, None) => {}
+        }
+    }
+    if plan.is_empty() {
+        return Err(LapsifyError::message(format!(
+            "Every frame in {start}-{end} is excluded; nothing to render"
+        )));
+    }
+    Ok(plan)
+}
+
+/// Resample a plan to a constant capture-time rate: output frame `k` shows
+/// the moment `k * step` after the first frame, where `step` is
+/// `seconds_per_frame` or the median capture interval, divided by
+/// `rate_factor` (output frames per frame of playback). Each moment takes
+/// the nearest source frame (duplicating or dropping frames), or with
+/// `blend` mixes the two frames around it. Only source frames take part;
+/// fills for excluded frames have no capture time of their own.
+pub fn retime_frames(
+    plan: &[PlannedFrame],
+    capture_times: &[i64],
+    retime: Retime,
+    rate_factor: f64,
+) -> Result<Vec<PlannedFrame>> {
+    let kept: Vec<(usize, i64)> = plan
+        .iter()
+        .filter_map(|planned| match *planned {
+            PlannedFrame::Source(frame) => Some((frame, *capture_times.get(frame)?)),
+            PlannedFrame::Blend { .. } | PlannedFrame::Stack { .. } => None,
+        })
+        .collect();
+    if kept.len() < 2 {
+        return Ok(plan.to_vec());
+    }
+    if kept.windows(2).any(|w| w[1].1 <= w[0].1) {
+        return Err(LapsifyError::message(
+            "Retiming needs strictly increasing capture times",
+        ));
+    }
+
+    let step_ms = match retime.seconds_per_frame {
+        Some(seconds) => seconds as f64 * 1000.0,
+        None => {
+            let mut intervals: Vec<i64> = kept.windows(2).map(|w| w[1].1 - w[0].1).collect();
+            intervals.sort_unstable();
+            intervals[intervals.len() / 2] as f64
+        }
+    };
+    let step_ms = step_ms / rate_factor;
+    let (first, last) = (kept[0].1, kept[kept.len() - 1].1);
+    let count = ((last - first) as f64 / step_ms).floor() + 1.0;
+    // As slow as the slowest speed ramp and no slower, so a tiny step
+    // can't ask for millions of frames.
+    let max_count = kept.len() as f64 / MIN_SPEED as f64;
+    if count > max_count {
+        return Err(LapsifyError::InvalidParam {
+            field: "retime.seconds_per_frame",
+            reason: format!(
+                "a step of {:.3} s makes {count:.0} frames from {} source frames, over the limit of {max_count:.0}; use a longer step",
+                step_ms / 1000.0,
+                kept.len()
+            ),
+        });
+    }
+    let count = count as usize;
+
+    let positions: Vec<(usize, f64)> = kept.iter().map(|&(f, t)| (f, t as f64)).collect();
+    let mut cursor = Cursor::new(&positions);
+    Ok((0..count)
+        .map(|k| cursor.frame_at(first as f64 + k as f64 * step_ms, retime.blend))
+        .collect())
+}
+
+/// Resample a plan along a speed ramp: `speed(frame)` is how many source
+/// frames one output frame advances at that source frame (0.25 is four
+/// times slower, 4 four times faster). Output frames fall on fractional
+/// source positions, which blend the two source frames around them. Only
+/// source frames take part; a position inside excluded frames blends the
+/// kept frames around the gap.
+pub fn speed_ramp_frames(plan: &[PlannedFrame], speed: impl Fn(u32) -> f32) -> Vec<PlannedFrame> {
+    let positions: Vec<(usize, f64)> = plan
+        .iter()
+        .filter_map(|planned| match *planned {
+            PlannedFrame::Source(frame) => Some((frame, frame as f64)),
+            PlannedFrame::Blend { .. } | PlannedFrame::Stack { .. } => None,
+        })
+        .collect();
+    if positions.len() < 2 {
+        return plan.to_vec();
+    }
+
+    let last = positions[positions.len() - 1].1;
+    let mut cursor = Cursor::new(&positions);
+    let mut ramped = Vec::new();
+    let mut position = positions[0].1;
+    // Float steps shouldn't lose the last frame to rounding.
+    while position <= last + 1e-6 {
+        ramped.push(cursor.frame_at(position, true));
+        let below = position.floor();
+        let t = (position - below) as f32;
+        let here = speed(below as u32);
+        let step = here + (speed(below as u32 + 1) - here) * t;
+        position += step.max(MIN_SPEED) as f64;
+    }
+    ramped
+}
+
+/// Turn a plan into stacks of `stack.frames` source frames (within the
+/// source frames `start..=end` it covers): consecutive groups, each shown
+/// once, or a window centered on every output frame.
+pub fn stack_frames(
+    plan: &[PlannedFrame],
+    stack: Stack,
+    (start, end): (usize, usize),
+) -> Vec<PlannedFrame> {
+    let n = stack.frames.max(1) as usize;
+    match stack.mode {
+        StackMode::Groups => plan
+            .chunks(n)
+            .map(|group| {
+                let frames = group.iter().map(PlannedFrame::frame);
+                PlannedFrame::Stack {
+                    frame: group[group.len() / 2].frame(),
+                    first: frames.clone().min().expect("chunks are non-empty"),
+                    last: frames.max().expect("chunks are non-empty"),
+                }
+            })
+            .collect(),
+        StackMode::Sliding => plan
+            .iter()
+            .map(|planned| {
+                let frame = planned.frame();
+                let first = frame.saturating_sub((n - 1) / 2).max(start);
+                let last = (first + n - 1).min(end);
+                PlannedFrame::Stack {
+                    frame,
+                    first: last.saturating_sub(n - 1).clamp(start, first),
+                    last,
+                }
+            })
+            .collect(),
+    }
+}
+
+/// Slowest speed a ramp advances at, so a bad curve can't stall a render.
+const MIN_SPEED: f32 = 0.01;
+
+/// Walks increasing positions over source frames placed on an axis (frame
+/// index or capture time), in order.
+struct Cursor<'a> {
+    positions: &'a [(usize, f64)],
+    segment: usize,
+}
+
+impl<'a> Cursor<'a> {
+    /// `positions` holds at least two frames, strictly increasing.
+    fn new(positions: &'a [(usize, f64)]) -> Self {
+        Self {
+            positions,
+            segment: 0,
+        }
+    }
+
+    /// The output frame at `x`: the nearest source frame, or with `blend`
+    /// a mix of the two frames around it. Positions must not decrease
+    /// between calls.
+    fn frame_at(&mut self, x: f64, blend: bool) -> PlannedFrame {
+        let positions = self.positions;
+        while self.segment + 2 < positions.len() && positions[self.segment + 1].1 <= x {
+            self.segment += 1;
+        }
+        let ((a, xa), (b, xb)) = (positions[self.segment], positions[self.segment + 1]);
+        let t = ((x - xa) / (xb - xa)).clamp(0.0, 1.0) as f32;
+        // Positions reached by float steps land a hair off a frame.
+        let t = if t < 1e-4 {
+            0.0
+        } else if t > 1.0 - 1e-4 {
+            1.0
+        } else {
+            t
+        };
+        let nearest = if t < 0.5 { a } else { b };
+        if blend && t > 0.0 && t < 1.0 {
+            PlannedFrame::Blend {
+                frame: nearest,
+                from: a,
+                to: b,
+                t,
+            }
+        } else {
+            PlannedFrame::Source(nearest)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::project::StackAlign;
 
     #[test]
     fn recognizes_supported_extensions() {
@@ -181,4 +503,154 @@ mod tests {
 
         assert!(select_frame_range(files, Some(10), None).is_err());
     }
+
+    #[test]
+    fn excluded_frames_are_dropped_or_filled() {
+        let dropped = plan_frames(0, 5, 6, &[2, 3], false).unwrap();
+        assert_eq!(
+            dropped.iter().map(PlannedFrame::frame).collect::<Vec<_>>(),
+            vec![0, 1, 4, 5]
+        );
+
+        let filled = plan_frames(0, 5, 6, &[2, 3], true).unwrap();
+        assert_eq!(filled.len(), 6);
+        assert_eq!(
+            filled[3],
+            PlannedFrame::Blend {
+                frame: 3,
+                from: 1,
+                to: 4,
+                t: 2.0 / 3.0
+            }
+        );
+        // At the end of the sequence the last kept frame is held.
+        let held = plan_frames(4, 5, 6, &[5], true).unwrap();
+        assert!(matches!(
+            held[1],
+            PlannedFrame::Blend { from: 4, to: 4, .. }
+        ));
+
+        assert!(plan_frames(2, 3, 6, &[2, 3], false).is_err());
+    }
+
+    #[test]
+    fn retiming_paces_output_by_capture_time() {
+        // A bulb ramp: 5 s intervals, then 20 s.
+        let times = [0, 5_000, 10_000, 30_000, 50_000];
+        let plan: Vec<PlannedFrame> = (0..5).map(PlannedFrame::Source).collect();
+        let retime = |seconds: f32, blend: bool| Retime {
+            seconds_per_frame: Some(seconds),
+            blend,
+        };
+
+        // Every 10 s: the 5 s stretch drops frames, the 20 s one duplicates.
+        let nearest = retime_frames(&plan, &times, retime(10.0, false), 1.0).unwrap();
+        assert_eq!(
+            nearest.iter().map(PlannedFrame::frame).collect::<Vec<_>>(),
+            vec![0, 2, 3, 3, 4, 4]
+        );
+
+        let blended = retime_frames(&plan, &times, retime(10.0, true), 1.0).unwrap();
+        assert_eq!(blended[1], PlannedFrame::Source(2));
+        assert_eq!(
+            blended[2],
+            PlannedFrame::Blend {
+                frame: 3,
+                from: 2,
+                to: 3,
+                t: 0.5
+            }
+        );
+
+        // The median interval (of 5, 5, 20 and 20 s) is the default pace.
+        let auto = retime_frames(&plan, &times, Retime::default(), 1.0).unwrap();
+        assert_eq!(auto.len(), 3);
+
+        let backwards = [0, 5_000, 4_000, 9_000, 12_000];
+        assert!(retime_frames(&plan, &backwards, Retime::default(), 1.0).is_err());
+
+        // At most 100 output frames per source frame.
+        assert_eq!(
+            retime_frames(&plan, &times, retime(0.25, false), 1.0)
+                .unwrap()
+                .len(),
+            201
+        );
+        assert!(retime_frames(&plan, &times, retime(0.09, false), 1.0).is_err());
+        assert!(retime_frames(&plan, &times, retime(1e-9, false), 1.0).is_err());
+    }
+
+    #[test]
+    fn stacks_group_or_slide_over_the_plan() {
+        let plan: Vec<PlannedFrame> = (0..7).map(PlannedFrame::Source).collect();
+        let stack = |mode| Stack {
+            frames: 3,
+            mode,
+            align: StackAlign::None,
+        };
+        let ranges = |stacked: Vec<PlannedFrame>| -> Vec<(usize, usize, usize)> {
+            stacked
+                .into_iter()
+                .map(|planned| match planned {
+                    PlannedFrame::Stack { frame, first, last } => (frame, first, last),
+                    other => panic!("not a stack: {other:?}"),
+                })
+                .collect()
+        };
+
+        assert_eq!(
+            ranges(stack_frames(&plan, stack(StackMode::Groups), (0, 6))),
+            vec![(1, 0, 2), (4, 3, 5), (6, 6, 6)]
+        );
+        let sliding = ranges(stack_frames(&plan, stack(StackMode::Sliding), (0, 6)));
+        assert_eq!(sliding.len(), 7);
+        assert_eq!(sliding[0], (0, 0, 2));
+        assert_eq!(sliding[3], (3, 2, 4));
+        assert_eq!(sliding[6], (6, 4, 6));
+
+        // Windows stay inside the frame range.
+        let ranged: Vec<PlannedFrame> = (2..5).map(PlannedFrame::Source).collect();
+        let sliding = ranges(stack_frames(&ranged, stack(StackMode::Sliding), (2, 4)));
+        assert_eq!(sliding, vec![(2, 2, 4), (3, 2, 4), (4, 2, 4)]);
+    }
+
+    #[test]
+    fn speed_ramp_slows_and_speeds_through_the_sequence() {
+        let plan: Vec<PlannedFrame> = (0..9).map(PlannedFrame::Source).collect();
+
+        let slow = speed_ramp_frames(&plan, |_| 0.5);
+        assert_eq!(slow.len(), 17);
+        assert_eq!(
+            slow[1],
+            PlannedFrame::Blend {
+                frame: 1,
+                from: 0,
+                to: 1,
+                t: 0.5
+            }
+        );
+        assert_eq!(slow[2], PlannedFrame::Source(1));
+
+        let fast = speed_ramp_frames(&plan, |_| 2.0);
+        assert_eq!(fast, [0, 2, 4, 6, 8].map(PlannedFrame::Source));
+
+        // Half speed up to frame 3, ramping to double speed from frame 4.
+        let ramped = speed_ramp_frames(&plan, |frame| if frame < 4 { 0.5 } else { 2.0 });
+        assert_eq!(ramped.len(), 10);
+        assert_eq!(ramped[7].frame(), 4);
+
+        // A dropped frame leaves a gap the ramp blends across.
+        let gapped = [0, 1, 3].map(PlannedFrame::Source);
+        let ramped = speed_ramp_frames(&gapped, |_| 1.0);
+        assert_eq!(
+            ramped[2],
+            PlannedFrame::Blend {
+                frame: 3,
+                from: 1,
+                to: 3,
+                t: 0.5
+            }
+        );
+        assert_eq!(ramped.len(), 4);
+    }
 }
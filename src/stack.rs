@@ -0,0 +1,237 @@
+//! Frame stacking: averaging aligned frames to bring night noise down.
+//!
+//! Every frame of a stack is registered to a reference frame (the kept
+//! frame nearest the stack's own) with a rigid transform — a shift, or a
+//! shift and a rotation for a sky turning around the pole. The transform
+//! chains the motions between neighboring kept frames, each fitted to the
+//! block motion between their thumbnails, so overlapping stacks share them.
+//! The frames are then averaged in linear light at full resolution, before
+//! any grading, so the color pipeline sees one cleaner frame.
+
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, OnceLock};
+
+use image::{imageops, DynamicImage, ImageBuffer, Rgb};
+use rayon::prelude::*;
+
+use crate::analysis::luminance::load_thumbnail;
+use crate::color::transfer::{linear_to_srgb, srgb_to_linear};
+use crate::error::Result;
+use crate::flow::{global_motion, GlobalMotion, MotionModel};
+use crate::project::{Project, StackAlign, StackMode};
+use crate::source::SourceFrames;
+
+/// Long edge of the thumbnails alignment is estimated on.
+const ALIGN_DIM: u32 = 512;
+
+type Frame16 = ImageBuffer<Rgb<u16>, Vec<u16>>;
+
+/// What the stacks of one render share. A sliding stack holds all but one
+/// frame of the next, so recently decoded frames are kept for reuse, and
+/// the motion between two neighboring kept frames is fitted once.
+#[derive(Default)]
+pub struct StackCache {
+    frames: Mutex<VecDeque<(usize, Arc<Frame16>)>>,
+    steps: Mutex<HashMap<(usize, usize), GlobalMotion>>,
+}
+
+impl StackCache {
+    /// Source frame `index` at 16 bits, keeping the `capacity` most
+    /// recently used.
+    fn frame(&self, sources: &SourceFrames, index: usize, capacity: usize) -> Result<Arc<Frame16>> {
+        {
+            let mut frames = self.frames.lock().unwrap();
+            if let Some(at) = frames.iter().position(|(i, _)| *i == index) {
+                let entry = frames.remove(at).expect("position is in range");
+                let img = entry.1.clone();
+                frames.push_back(entry);
+                return Ok(img);
+            }
+        }
+        let img = Arc::new(sources.load(index)?.into_rgb16());
+        let mut frames = self.frames.lock().unwrap();
+        if capacity > 0 && !frames.iter().any(|(i, _)| *i == index) {
+            frames.push_back((index, img.clone()));
+            while frames.len() > capacity {
+                frames.pop_front();
+            }
+        }
+        Ok(img)
+    }
+
+    /// The motion from kept frame `from` to kept frame `to`, in pixels of a
+    /// frame `width` wide.
+    fn step(
+        &self,
+        files: &[PathBuf],
+        (from, to): (usize, usize),
+        model: MotionModel,
+        width: u32,
+        cache_dir: Option<&Path>,
+    ) -> Result<GlobalMotion> {
+        if let Some(&motion) = self.steps.lock().unwrap().get(&(from, to)) {
+            return Ok(motion);
+        }
+        let base = load_thumbnail(&files[from], ALIGN_DIM, cache_dir)?;
+        let mut thumb = load_thumbnail(&files[to], ALIGN_DIM, cache_dir)?;
+        if thumb.dimensions() != base.dimensions() {
+            thumb = imageops::resize(
+                &thumb,
+                base.width(),
+                base.height(),
+                imageops::FilterType::Triangle,
+            );
+        }
+        let scale = width as f32 / base.width() as f32;
+        let motion = global_motion(&base, &thumb, model).scaled(scale);
+        self.steps.lock().unwrap().insert((from, to), motion);
+        Ok(motion)
+    }
+}
+
+/// Stack source frames `first..=last` of `sources`, skipping excluded
+/// ones, onto the kept frame nearest `frame`. The result is still ungraded.
+pub fn stack_frames(
+    sources: &SourceFrames,
+    project: &Project,
+    frame: usize,
+    (first, last): (usize, usize),
+    cache: &StackCache,
+) -> Result<DynamicImage> {
+    let files = sources.files();
+    let excluded = &project.excluded_frames;
+    let mut kept: Vec<usize> = (first..=last.min(files.len() - 1))
+        .filter(|&i| excluded.binary_search(&(i as u32)).is_err())
+        .collect();
+    if kept.is_empty() {
+        kept.push(frame);
+    }
+    let reference = kept
+        .iter()
+        .enumerate()
+        .min_by_key(|(_, &i)| i.abs_diff(frame))
+        .map(|(at, _)| at)
+        .expect("kept holds at least one frame");
+    let stack = project.export.stack;
+    // Every worker's window at once, when windows overlap.
+    let capacity = match stack.map(|stack| stack.mode) {
+        Some(StackMode::Sliding) => rayon::current_num_threads() + kept.len() + 2,
+        _ => 0,
+    };
+    let reference_img = cache.frame(sources, kept[reference], capacity)?;
+    if kept.len() == 1 {
+        return Ok(DynamicImage::ImageRgb16((*reference_img).clone()));
+    }
+    let (width, height) = reference_img.dimensions();
+
+    let align = stack.map_or(StackAlign::None, |stack| stack.align);
+    let transforms: Vec<GlobalMotion> = match align {
+        StackAlign::None => vec![GlobalMotion::IDENTITY; kept.len()],
+        StackAlign::Translation | StackAlign::Rotation => {
+            let cache_dir = project.input.join(".lapsify").join("thumbs");
+            let cache_dir = std::fs::create_dir_all(&cache_dir)
+                .is_ok()
+                .then_some(cache_dir.as_path());
+            let model = MotionModel {
+                rotation: align == StackAlign::Rotation,
+                scale: false,
+            };
+            let steps: Vec<GlobalMotion> = kept
+                .par_windows(2)
+                .map(|pair| cache.step(files, (pair[0], pair[1]), model, width, cache_dir))
+                .collect::<Result<_>>()?;
+            // From the reference out to either end of the stack.
+            let mut transforms = vec![GlobalMotion::IDENTITY; kept.len()];
+            for j in reference + 1..kept.len() {
+                transforms[j] = transforms[j - 1].then(steps[j - 1]);
+            }
+            for j in (0..reference).rev() {
+                transforms[j] = transforms[j + 1].then(steps[j].inverse());
+            }
+            transforms
+        }
+    };
+
+    let decode = decode_table();
+    let pixels = width as usize * height as usize;
+    let mut sum = vec![0.0f32; pixels * 3];
+    let mut count = vec![0u8; pixels];
+    for (at, (&i, transform)) in kept.iter().zip(&transforms).enumerate() {
+        let loaded;
+        let img = if at == reference {
+            &*reference_img
+        } else {
+            let img = cache.frame(sources, i, capacity)?;
+            loaded = if img.dimensions() != (width, height) {
+                Arc::new(imageops::resize(
+                    &*img,
+                    width,
+                    height,
+                    imageops::FilterType::Triangle,
+                ))
+            } else {
+                img
+            };
+            &*loaded
+        };
+        sum.par_chunks_mut(width as usize * 3)
+            .zip(count.par_chunks_mut(width as usize))
+            .enumerate()
+            .for_each(|(y, (sum_row, count_row))| {
+                for x in 0..width as usize {
+                    let Some(rgb) =
+                        sample_linear(img, transform.apply((x as f32, y as f32)), decode)
+                    else {
+                        continue;
+                    };
+                    for c in 0..3 {
+                        sum_row[x * 3 + c] += rgb[c];
+                    }
+                    count_row[x] += 1;
+                }
+            });
+    }
+
+    let data: Vec<u16> = sum
+        .par_chunks(3)
+        .zip(count.par_iter())
+        .flat_map_iter(|(rgb, &n)| {
+            let n = n.max(1) as f32;
+            rgb.iter()
+                .map(move |&v| (linear_to_srgb((v / n).clamp(0.0, 1.0)) * 65535.0).round() as u16)
+        })
+        .collect();
+    let stacked: Frame16 =
+        ImageBuffer::from_raw(width, height, data).expect("buffer matches the frame size");
+    Ok(DynamicImage::ImageRgb16(stacked))
+}
+
+/// Bilinear sample in linear light, or None outside the frame.
+fn sample_linear(img: &Frame16, (x, y): (f32, f32), decode: &[f32]) -> Option<[f32; 3]> {
+    let (width, height) = img.dimensions();
+    if x < 0.0 || y < 0.0 || x > (width - 1) as f32 || y > (height - 1) as f32 {
+        return None;
+    }
+    let (x0, y0) = (x.floor() as u32, y.floor() as u32);
+    let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+    let (tx, ty) = (x - x0 as f32, y - y0 as f32);
+    let mut out = [0.0f32; 3];
+    for (c, v) in out.iter_mut().enumerate() {
+        let p = |px: u32, py: u32| decode[img.get_pixel(px, py)[c] as usize];
+        let top = p(x0, y0) + (p(x1, y0) - p(x0, y0)) * tx;
+        let bottom = p(x0, y1) + (p(x1, y1) - p(x0, y1)) * tx;
+        *v = top + (bottom - top) * ty;
+    }
+    Some(out)
+}
+
+/// Linear light of every 16-bit sRGB code.
+fn decode_table() -> &'static [f32] {
+    static TABLE: OnceLock<Vec<f32>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        (0..=u16::MAX)
+            .map(|i| srgb_to_linear(i as f32 / 65535.0))
+            .collect()
+    })
+}
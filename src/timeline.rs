@@ -5,26 +5,56 @@
 //! intervals. Geometry (the crop track) intentionally stays in frame space:
 //! motion is perceived in playback time, where frames are equally spaced.
 
+use crate::expr::Vars;
 use crate::project::{InterpolationMode, Project};
 
 pub struct Timeline<'a> {
     times: Option<&'a [i64]>,
+    capture_times: Option<&'a [i64]>,
+    frames: usize,
 }
 
 impl<'a> Timeline<'a> {
-    /// The timeline a project's color curves should sample with. Falls back
-    /// to frame indices when time interpolation is off, timestamps are
-    /// missing, or timestamps are not strictly increasing.
-    pub fn of(project: &'a Project) -> Self {
+    /// The timeline a project's color curves should sample with, for a
+    /// sequence of `frames` source frames. Falls back to frame indices when
+    /// time interpolation is off, timestamps are missing, or timestamps are
+    /// not strictly increasing.
+    pub fn of(project: &'a Project, frames: usize) -> Self {
+        let capture_times = project
+            .analysis
+            .as_ref()
+            .and_then(|a| a.capture_times_ms.as_deref())
+            .filter(|t| t.len() >= 2 && t.windows(2).all(|w| w[1] > w[0]));
         let times = match project.interpolation {
             InterpolationMode::Frame => None,
-            InterpolationMode::Time => project
-                .analysis
-                .as_ref()
-                .and_then(|a| a.capture_times_ms.as_deref())
-                .filter(|t| t.len() >= 2 && t.windows(2).all(|w| w[1] > w[0])),
+            InterpolationMode::Time => capture_times,
         };
-        Self { times }
+        Self {
+            times,
+            capture_times,
+            frames,
+        }
+    }
+
+    /// Plain frame indices over `frames` source frames, without capture
+    /// times.
+    pub fn indices(frames: usize) -> Self {
+        Self {
+            times: None,
+            capture_times: None,
+            frames,
+        }
+    }
+
+    /// Source frames in the sequence.
+    pub fn frames(&self) -> usize {
+        self.frames
+    }
+
+    /// Capture times in milliseconds, when the analysis has strictly
+    /// increasing ones (whatever the interpolation mode).
+    pub fn capture_times(&self) -> Option<&'a [i64]> {
+        self.capture_times
     }
 
     /// Whether capture times are actually driving interpolation.
@@ -42,6 +72,29 @@ impl<'a> Timeline<'a> {
             None => frame as f32,
         }
     }
+
+    /// Expression variables at a frame: `t` runs 0..1 over the sequence and
+    /// `time` is capture time in seconds (whatever the interpolation mode),
+    /// or the frame index without timestamps.
+    pub fn vars(&self, frame: u32) -> Vars {
+        let t = if self.frames > 1 {
+            frame as f32 / (self.frames - 1) as f32
+        } else {
+            0.0
+        };
+        let time = match self.capture_times {
+            Some(times) => {
+                let i = (frame as usize).min(times.len() - 1);
+                (times[i] - times[0]) as f32 / 1000.0
+            }
+            None => frame as f32,
+        };
+        Vars {
+            frame: frame as f32,
+            t,
+            time,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -58,10 +111,13 @@ mod tests {
             version: PROJECT_VERSION,
             input: PathBuf::from("frames"),
             frame_range: None,
+            excluded_frames: Vec::new(),
             interpolation: mode,
             color: ColorGrade::default(),
+            perspective: None,
             crop: None,
             export: ExportSettings::new(PathBuf::from("out")),
+            outputs: Vec::new(),
             analysis: times.map(|capture_times_ms| Analysis {
                 capture_times_ms: Some(capture_times_ms),
                 ..Analysis::default()
@@ -72,7 +128,7 @@ mod tests {
     #[test]
     fn frame_mode_ignores_times() {
         let project = project_with_times(Some(vec![0, 1000, 9000]), InterpolationMode::Frame);
-        let timeline = Timeline::of(&project);
+        let timeline = Timeline::of(&project, 3);
         assert!(!timeline.is_time_based());
         assert_relative_eq!(timeline.x(1), 1.0);
     }
@@ -81,7 +137,7 @@ mod tests {
     fn time_mode_uses_capture_spacing() {
         // Frames at 0s, 1s and 9s: frame 1 sits at 1/9 of the clip, not 1/2.
         let project = project_with_times(Some(vec![0, 1000, 9000]), InterpolationMode::Time);
-        let timeline = Timeline::of(&project);
+        let timeline = Timeline::of(&project, 3);
         assert!(timeline.is_time_based());
 
         let curve = Curve::Keyframed(vec![Keyframe::new(0, 0.0), Keyframe::new(2, 9.0)]);
@@ -92,13 +148,27 @@ mod tests {
         assert!(time_based < 2.0, "expected early value, got {time_based}");
     }
 
+    #[test]
+    fn expression_vars_span_the_clip() {
+        let project = project_with_times(Some(vec![0, 1000, 9000]), InterpolationMode::Frame);
+        let timeline = Timeline::of(&project, 3);
+        let vars = timeline.vars(1);
+        assert_relative_eq!(vars.t, 0.5);
+        // Capture time is available to expressions in frame mode too.
+        assert_relative_eq!(vars.time, 1.0);
+        assert_relative_eq!(timeline.vars(2).time, 9.0);
+
+        let untimed = project_with_times(None, InterpolationMode::Frame);
+        assert_relative_eq!(Timeline::of(&untimed, 3).vars(2).time, 2.0);
+    }
+
     #[test]
     fn degrades_on_bad_timestamps() {
         // Not strictly increasing -> frame fallback.
         let project = project_with_times(Some(vec![0, 5000, 5000]), InterpolationMode::Time);
-        assert!(!Timeline::of(&project).is_time_based());
+        assert!(!Timeline::of(&project, 3).is_time_based());
 
         let project = project_with_times(None, InterpolationMode::Time);
-        assert!(!Timeline::of(&project).is_time_based());
+        assert!(!Timeline::of(&project, 3).is_time_based());
     }
 }
@@ -2,10 +2,16 @@ use std::path::Path;
 
 use lapsify::analysis::deflicker::{run_deflicker, DeflickerOptions};
 use lapsify::analysis::holygrail::{compute_holy_grail, HolyGrailOptions};
-use lapsify::analysis::keyframes::{suggest_keyframes, SuggestOptions};
+use lapsify::analysis::intervals::{capture_interval_report, IntervalOptions};
+use lapsify::analysis::keyframes::{
+    insert_handles, suggest_color_keyframes, suggest_keyframes, SuggestOptions, SuggestParam,
+};
 use lapsify::analysis::luminance::{measure_luminance, LuminanceOptions};
+use lapsify::analysis::outliers::{detect_outliers, OutlierOptions};
+use lapsify::analysis::scenes::{detect_scenes, SceneOptions};
+use lapsify::analysis::stabilize::{stabilize, StabilizeOptions};
+use lapsify::analysis::whitebalance::{compute_white_balance, WhiteBalanceOptions};
 use lapsify::analysis::Analysis;
-use lapsify::curve::Keyframe;
 use lapsify::progress::ProgressEvent;
 use lapsify::Curve;
 
@@ -28,12 +34,15 @@ pub struct StudioApp {
     // Job progress
     pub progress: Option<(usize, usize)>, // done, total
     pub deflicker_note: Option<String>,
+    /// Result line of the running job, shown when it finishes.
+    pub job_summary: Option<String>,
     pub status: String,
     pub error: Option<String>,
 
     // Curve panel state
     pub selected_param: ParamId,
     pub dragging_keyframe: Option<usize>,
+    pub dragging_handle: Option<(usize, panels::timeline::HandleSide)>,
     pub show_source_luma: bool,
     pub show_developed_luma: bool,
     pub show_compensation: bool,
@@ -74,10 +83,12 @@ impl StudioApp {
             preview_dirty: false,
             progress: None,
             deflicker_note: None,
+            job_summary: None,
             status: "Open a frames folder or a project.json to start".to_string(),
             error: None,
             selected_param: ParamId::Exposure,
             dragging_keyframe: None,
+            dragging_handle: None,
             show_source_luma: true,
             show_developed_luma: false,
             show_compensation: false,
@@ -167,6 +178,37 @@ impl StudioApp {
                     ProgressEvent::KeyframeSuggestion { frames } => {
                         self.status = format!("Suggested keyframes: {frames:?}");
                     }
+                    ProgressEvent::Outliers { flags } => {
+                        self.job_summary = Some(format!("{} frame(s) flagged", flags.len()));
+                    }
+                    ProgressEvent::Intervals { report } => {
+                        self.job_summary = Some(match report.nominal_ms {
+                            None => "No capture times found".to_string(),
+                            Some(nominal) => format!(
+                                "Interval {:.1} s: {} gap(s), {} duplicate(s), {} clock jump(s)",
+                                nominal as f64 / 1000.0,
+                                report.gaps.len(),
+                                report.duplicates.len(),
+                                report.clock_jumps.len()
+                            ),
+                        });
+                    }
+                    ProgressEvent::Scenes { scenes } => {
+                        self.job_summary = Some(format!("{} scene(s)", scenes.len()));
+                    }
+                    ProgressEvent::Stabilized { zoom, .. } => {
+                        self.job_summary = Some(format!("window zoomed to {:.1}%", zoom * 100.0));
+                    }
+                    ProgressEvent::HolyGrailFit {
+                        rotate,
+                        stretch,
+                        rms_ev,
+                        ..
+                    } => {
+                        self.status = format!(
+                            "Luminance fit: rotate {rotate:.2} EV, stretch {stretch:.2}, RMS {rms_ev:.3} EV"
+                        );
+                    }
                 },
                 UiEvent::JobFinished { name, result } => {
                     self.worker.job_running = None;
@@ -178,9 +220,15 @@ impl StudioApp {
                                 doc.dirty = true;
                                 self.preview_dirty = true;
                             }
-                            self.status = format!("{name} finished");
+                            self.status = match self.job_summary.take() {
+                                Some(summary) => format!("{name} finished: {summary}"),
+                                None => format!("{name} finished"),
+                            };
+                        }
+                        Err(e) => {
+                            self.job_summary = None;
+                            self.error = Some(format!("{name}: {e}"));
                         }
-                        Err(e) => self.error = Some(format!("{name}: {e}")),
                     }
                 }
             }
@@ -207,6 +255,7 @@ impl StudioApp {
                 measure_luminance(&project, &frames, &opts, reporter).map_err(|e| e.to_string())?;
             let mut project = project;
             let analysis = project.analysis.get_or_insert_with(Analysis::default);
+            analysis.record_frames(&frames).map_err(|e| e.to_string())?;
             if developed {
                 analysis.developed_luminance = Some(series);
             } else {
@@ -222,22 +271,50 @@ impl StudioApp {
         self.worker
             .run_job("exposure compensation", move |reporter| {
                 let frames = frames_of(&project)?;
+                // Manual EV events are user input: carry them over.
                 let opts = HolyGrailOptions {
-                    rotate: None,
-                    stretch: None,
+                    events: project
+                        .analysis
+                        .as_ref()
+                        .and_then(|a| a.holy_grail.as_ref())
+                        .map(|hg| hg.ev_events.clone())
+                        .unwrap_or_default(),
+                    ..HolyGrailOptions::default()
                 };
                 let (layer, times) =
                     compute_holy_grail(&frames, &opts, reporter).map_err(|e| e.to_string())?;
                 let mut project = project;
                 let analysis = project.analysis.get_or_insert_with(Analysis::default);
+                analysis.record_frames(&frames).map_err(|e| e.to_string())?;
                 analysis.holy_grail = Some(layer);
                 if times.is_some() {
                     analysis.capture_times_ms = times;
+                    project.resolve_anchors();
                 }
                 Ok(Some(project))
             });
     }
 
+    pub fn job_white_balance(&mut self) {
+        let Some(doc) = &self.doc else { return };
+        let project = doc.project.clone();
+        self.worker
+            .run_job("white balance compensation", move |reporter| {
+                let frames = frames_of(&project)?;
+                let opts = WhiteBalanceOptions {
+                    excluded_frames: project.excluded_frames.clone(),
+                    ..WhiteBalanceOptions::default()
+                };
+                let layer =
+                    compute_white_balance(&frames, &opts, reporter).map_err(|e| e.to_string())?;
+                let mut project = project;
+                let analysis = project.analysis.get_or_insert_with(Analysis::default);
+                analysis.record_frames(&frames).map_err(|e| e.to_string())?;
+                analysis.white_balance = Some(layer);
+                Ok(Some(project))
+            });
+    }
+
     pub fn job_deflicker(&mut self) {
         let Some(doc) = &self.doc else { return };
         let project = doc.project.clone();
@@ -248,24 +325,115 @@ impl StudioApp {
                 .map_err(|e| e.to_string())?;
             let mut project = project;
             let analysis = project.analysis.get_or_insert_with(Analysis::default);
+            analysis.record_frames(&frames).map_err(|e| e.to_string())?;
             analysis.deflicker = Some(layer);
             Ok(Some(project))
         });
     }
 
+    /// Flag bad frames and exclude them from analysis and export.
+    pub fn job_outliers(&mut self) {
+        let Some(doc) = &self.doc else { return };
+        let project = doc.project.clone();
+        self.worker.run_job("bad frame detection", move |reporter| {
+            let frames = frames_of(&project)?;
+            let layer = detect_outliers(&project, &frames, &OutlierOptions::default(), reporter)
+                .map_err(|e| e.to_string())?;
+            reporter.report(ProgressEvent::Outliers {
+                flags: layer.flags.clone(),
+            });
+            let mut project = project;
+            project.exclude_frames(&layer.frames());
+            let analysis = project.analysis.get_or_insert_with(Analysis::default);
+            analysis.record_frames(&frames).map_err(|e| e.to_string())?;
+            analysis.outliers = Some(layer);
+            Ok(Some(project))
+        });
+    }
+
+    /// Check the capture intervals; the summary lands in the status bar.
+    pub fn job_intervals(&mut self) {
+        let Some(doc) = &self.doc else { return };
+        let project = doc.project.clone();
+        self.worker.run_job("interval check", move |reporter| {
+            let frames = frames_of(&project)?;
+            let report = capture_interval_report(&frames, &IntervalOptions::default(), reporter);
+            reporter.report(ProgressEvent::Intervals { report });
+            Ok(None)
+        });
+    }
+
+    /// Find scene boundaries; deflicker then treats each scene on its own.
+    pub fn job_scenes(&mut self) {
+        let Some(doc) = &self.doc else { return };
+        let project = doc.project.clone();
+        self.worker.run_job("scene detection", move |reporter| {
+            let frames = frames_of(&project)?;
+            let layer = detect_scenes(&project, &frames, &SceneOptions::default(), reporter)
+                .map_err(|e| e.to_string())?;
+            reporter.report(ProgressEvent::Scenes {
+                scenes: layer.scenes.clone(),
+            });
+            let mut project = project;
+            let analysis = project.analysis.get_or_insert_with(Analysis::default);
+            analysis.record_frames(&frames).map_err(|e| e.to_string())?;
+            analysis.scenes = Some(layer);
+            Ok(Some(project))
+        });
+    }
+
+    /// Smooth the camera path and write the corrections into the crop.
+    pub fn job_stabilize(&mut self) {
+        let Some(doc) = &self.doc else { return };
+        let project = doc.project.clone();
+        self.worker.run_job("stabilization", move |reporter| {
+            let frames = frames_of(&project)?;
+            let layer = stabilize(&project, &frames, &StabilizeOptions::default(), reporter)
+                .map_err(|e| e.to_string())?;
+            reporter.report(ProgressEvent::Stabilized {
+                zoom: layer.zoom,
+                max_shift: layer.max_shift(),
+            });
+            let mut project = project;
+            project.crop = Some(layer.crop_track());
+            let analysis = project.analysis.get_or_insert_with(Analysis::default);
+            analysis.record_frames(&frames).map_err(|e| e.to_string())?;
+            analysis.stabilize = Some(layer);
+            Ok(Some(project))
+        });
+    }
+
+    /// Suggest keyframes for the selected curve when it is temperature or
+    /// tint (from color drift), otherwise for exposure.
     pub fn job_suggest_keyframes(&mut self) {
         let Some(doc) = &self.doc else { return };
         let project = doc.project.clone();
+        let param = match self.selected_param {
+            ParamId::Temperature => SuggestParam::Temperature,
+            ParamId::Tint => SuggestParam::Tint,
+            _ => SuggestParam::Exposure,
+        };
         self.worker.run_job("keyframe suggestion", move |reporter| {
             let analysis = project.analysis.as_ref();
             let luma = analysis
                 .and_then(|a| a.source_luminance.as_ref())
                 .ok_or("Run luminance analysis first")?;
-            let frames = suggest_keyframes(
-                &luma.values,
-                analysis.and_then(|a| a.holy_grail.as_ref()),
-                &SuggestOptions::default(),
-            )
+            let frames = match param {
+                SuggestParam::Exposure => suggest_keyframes(
+                    &luma.values,
+                    analysis.and_then(|a| a.holy_grail.as_ref()),
+                    &SuggestOptions::default(),
+                ),
+                _ if luma.chromaticity.is_empty() => {
+                    return Err("Re-run luminance analysis to measure color".to_string())
+                }
+                _ => suggest_color_keyframes(
+                    &luma.chromaticity,
+                    analysis.and_then(|a| a.white_balance.as_ref()),
+                    param,
+                    &SuggestOptions::default(),
+                ),
+            }
             .map_err(|e| e.to_string())?;
             reporter.report(ProgressEvent::KeyframeSuggestion {
                 frames: frames.clone(),
@@ -273,18 +441,8 @@ impl StudioApp {
 
             // Insert no-op handles at the suggested frames.
             let mut project = project;
-            let mut keyframes: Vec<Keyframe> = match &project.color.exposure {
-                Curve::Keyframed(kfs) => kfs.clone(),
-                Curve::Constant(_) => Vec::new(),
-            };
-            for &frame in &frames {
-                if keyframes.iter().any(|k| k.frame.abs_diff(frame) <= 2) {
-                    continue;
-                }
-                keyframes.push(Keyframe::new(frame, project.color.exposure.sample(frame)));
-            }
-            keyframes.sort_by_key(|k| k.frame);
-            project.color.exposure = Curve::Keyframed(keyframes);
+            insert_handles(param.curve_mut(&mut project.color), &frames)
+                .map_err(|e| e.to_string())?;
             Ok(Some(project))
         });
     }
@@ -294,13 +452,8 @@ impl StudioApp {
         let project = doc.project.clone();
         self.worker.run_job("export", move |reporter| {
             let start = std::time::Instant::now();
-            if project.is_video_output() {
-                lapsify::export::video::render_to_video(&project, reporter, start)
-                    .map_err(|e| e.to_string())?;
-            } else {
-                lapsify::export::images::render_to_images(&project, reporter, start)
-                    .map_err(|e| e.to_string())?;
-            }
+            lapsify::export::render_project(&project, reporter, start)
+                .map_err(|e| e.to_string())?;
             Ok(None)
         });
     }
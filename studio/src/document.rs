@@ -7,6 +7,7 @@ use lapsify::curve::{Easing, Keyframe};
 use lapsify::error::Result;
 use lapsify::project::{ExportSettings, Project, PROJECT_VERSION};
 use lapsify::source::list_images;
+use lapsify::timeline::Timeline;
 use lapsify::Curve;
 
 /// Identifies one keyframable color parameter.
@@ -130,10 +131,13 @@ impl Document {
             version: PROJECT_VERSION,
             input: dir.to_path_buf(),
             frame_range: None,
+            excluded_frames: Vec::new(),
             interpolation: Default::default(),
             color: Default::default(),
+            perspective: None,
             crop: None,
             export: ExportSettings::new(output),
+            outputs: Vec::new(),
             analysis: None,
         };
         Ok(Self {
@@ -202,7 +206,8 @@ impl Document {
 
     /// The parameter value at a frame.
     pub fn value_at(&self, param: ParamId, frame: u32) -> f32 {
-        self.curve(param).sample(frame)
+        let timeline = Timeline::of(&self.project, self.frame_count());
+        self.curve(param).sample_on(frame, &timeline)
     }
 
     /// Whether the curve has a keyframe exactly at this frame.
@@ -211,7 +216,8 @@ impl Document {
     }
 
     /// Keyframe-aware edit: a constant curve stays constant (global change);
-    /// a keyframed curve gets a keyframe written/updated at the frame.
+    /// a keyframed curve gets a keyframe written/updated at the frame. An
+    /// expression curve is read-only here: edit its source in the project.
     pub fn set_value(&mut self, param: ParamId, frame: u32, value: f32) {
         let curve = self.curve_mut(param);
         match curve {
@@ -220,6 +226,7 @@ impl Document {
                 Ok(i) => kfs[i].value = value,
                 Err(i) => kfs.insert(i, Keyframe::new(frame, value)),
             },
+            Curve::Expression(_) => return,
         }
         self.dirty = true;
     }
@@ -227,15 +234,15 @@ impl Document {
     /// Toggle a keyframe at the frame: converts a constant curve to a
     /// keyframed one, removes an existing keyframe (falling back to constant
     /// when it was the last), or adds a keyframe at the sampled value.
+    /// Expression curves are left alone.
     pub fn toggle_keyframe(&mut self, param: ParamId, frame: u32) {
         let current = self.value_at(param, frame);
         let curve = self.curve_mut(param);
         match curve {
             Curve::Constant(_) => {
                 *curve = Curve::Keyframed(vec![Keyframe {
-                    frame,
-                    value: current,
                     easing: Easing::Smooth,
+                    ..Keyframe::new(frame, current)
                 }]);
             }
             Curve::Keyframed(kfs) => match kfs.binary_search_by_key(&frame, |k| k.frame) {
@@ -247,6 +254,7 @@ impl Document {
                 }
                 Err(i) => kfs.insert(i, Keyframe::new(frame, current)),
             },
+            Curve::Expression(_) => return,
         }
         self.dirty = true;
     }
@@ -27,7 +27,12 @@ mod imp {
         luminance: MenuItem,
         developed: MenuItem,
         holygrail: MenuItem,
+        white_balance: MenuItem,
         deflicker: MenuItem,
+        outliers: MenuItem,
+        scenes: MenuItem,
+        stabilize: MenuItem,
+        intervals: MenuItem,
         keyframes: MenuItem,
         render: MenuItem,
         view_source: CheckMenuItem,
@@ -116,7 +121,12 @@ mod imp {
             );
             let developed = MenuItem::new("Measure Developed Luminance", true, None);
             let holygrail = MenuItem::new("Compensate EXIF Exposure", true, None);
+            let white_balance = MenuItem::new("Compensate RAW White Balance", true, None);
             let deflicker = MenuItem::new("Deflicker", true, accel(Modifiers::META, Code::KeyD));
+            let outliers = MenuItem::new("Find Bad Frames", true, None);
+            let scenes = MenuItem::new("Find Scenes", true, None);
+            let stabilize = MenuItem::new("Stabilize", true, None);
+            let intervals = MenuItem::new("Check Capture Intervals", true, None);
             let keyframes = MenuItem::new(
                 "Suggest Keyframes",
                 true,
@@ -129,7 +139,12 @@ mod imp {
                     &luminance,
                     &developed,
                     &holygrail,
+                    &white_balance,
                     &deflicker,
+                    &outliers,
+                    &scenes,
+                    &stabilize,
+                    &intervals,
                     &keyframes,
                     &PredefinedMenuItem::separator(),
                     &render,
@@ -186,7 +201,12 @@ mod imp {
                 luminance,
                 developed,
                 holygrail,
+                white_balance,
                 deflicker,
+                outliers,
+                scenes,
+                stabilize,
+                intervals,
                 keyframes,
                 render,
                 view_source,
@@ -234,7 +254,12 @@ mod imp {
                 id if id == self.luminance.id() => app.job_luminance(false),
                 id if id == self.developed.id() => app.job_luminance(true),
                 id if id == self.holygrail.id() => app.job_holygrail(),
+                id if id == self.white_balance.id() => app.job_white_balance(),
                 id if id == self.deflicker.id() => app.job_deflicker(),
+                id if id == self.outliers.id() => app.job_outliers(),
+                id if id == self.scenes.id() => app.job_scenes(),
+                id if id == self.stabilize.id() => app.job_stabilize(),
+                id if id == self.intervals.id() => app.job_intervals(),
                 id if id == self.keyframes.id() => app.job_suggest_keyframes(),
                 id if id == self.render.id() => app.job_render(),
                 id if id == self.view_source.id() => {
@@ -267,7 +292,12 @@ mod imp {
                 &self.luminance,
                 &self.developed,
                 &self.holygrail,
+                &self.white_balance,
                 &self.deflicker,
+                &self.outliers,
+                &self.scenes,
+                &self.stabilize,
+                &self.intervals,
                 &self.keyframes,
                 &self.render,
             ] {
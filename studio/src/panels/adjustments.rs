@@ -1,4 +1,7 @@
-use lapsify::project::Codec;
+use lapsify::project::{
+    Accumulate, AccumulateMode, BlurUnit, BlurWeighting, Codec, FrameInterpolation, MotionBlur,
+    Retime, Stack, StackAlign, StackMode,
+};
 use lapsify::Curve;
 
 use crate::app::StudioApp;
@@ -154,6 +157,40 @@ pub fn show(app: &mut StudioApp, ctx: &egui::Context) {
                         }
                         ui.end_row();
 
+                        ui.label("Output FPS");
+                        let mut output_fps = export.output_rate();
+                        if ui
+                            .add(egui::DragValue::new(&mut output_fps).range(1..=120))
+                            .on_hover_text("Frame rates above FPS add in-between frames")
+                            .changed()
+                        {
+                            export.output_fps = (output_fps != export.fps).then_some(output_fps);
+                            changed = true;
+                        }
+                        ui.end_row();
+
+                        ui.label("In-between frames");
+                        egui::ComboBox::from_id_salt("frame_interpolation")
+                            .selected_text(match export.frame_interpolation {
+                                FrameInterpolation::Blend => "Blend",
+                                FrameInterpolation::Flow => "Optical flow",
+                            })
+                            .show_ui(ui, |ui| {
+                                for (mode, label) in [
+                                    (FrameInterpolation::Blend, "Blend"),
+                                    (FrameInterpolation::Flow, "Optical flow"),
+                                ] {
+                                    if ui
+                                        .selectable_label(export.frame_interpolation == mode, label)
+                                        .clicked()
+                                    {
+                                        export.frame_interpolation = mode;
+                                        changed = true;
+                                    }
+                                }
+                            });
+                        ui.end_row();
+
                         ui.label("Quality (CRF)");
                         let mut quality = export.quality;
                         if ui
@@ -166,20 +203,248 @@ pub fn show(app: &mut StudioApp, ctx: &egui::Context) {
                         ui.end_row();
 
                         ui.label("Motion blur");
-                        let mut blur = export.motion_blur.unwrap_or(1);
-                        if ui
-                            .add(
-                                egui::DragValue::new(&mut blur)
-                                    .range(1..=128)
-                                    .suffix(" frames"),
-                            )
-                            .changed()
-                        {
-                            export.motion_blur = if blur <= 1 { None } else { Some(blur) };
-                            changed = true;
+                        ui.horizontal(|ui| {
+                            let mut enabled = export.motion_blur.is_some();
+                            if ui.checkbox(&mut enabled, "").changed() {
+                                export.motion_blur = enabled.then(|| MotionBlur::frames(3.0));
+                                changed = true;
+                            }
+                            let Some(blur) = export.motion_blur.as_mut() else {
+                                return;
+                            };
+                            match blur.window {
+                                Curve::Constant(ref mut window) => {
+                                    let (max, suffix) = match blur.unit {
+                                        BlurUnit::Frames => (128.0, " frames"),
+                                        BlurUnit::ShutterAngle => (3600.0, "°"),
+                                    };
+                                    changed |= ui
+                                        .add(
+                                            egui::DragValue::new(window)
+                                                .range(0.0..=max)
+                                                .speed(0.1)
+                                                .suffix(suffix),
+                                        )
+                                        .changed();
+                                }
+                                _ => {
+                                    ui.label(egui::RichText::new("Keyframed").weak());
+                                }
+                            }
+                            egui::ComboBox::from_id_salt("blur_unit")
+                                .selected_text(match blur.unit {
+                                    BlurUnit::Frames => "Frames",
+                                    BlurUnit::ShutterAngle => "Shutter",
+                                })
+                                .show_ui(ui, |ui| {
+                                    for (unit, label) in [
+                                        (BlurUnit::Frames, "Frames"),
+                                        (BlurUnit::ShutterAngle, "Shutter"),
+                                    ] {
+                                        if ui.selectable_label(blur.unit == unit, label).clicked() {
+                                            blur.unit = unit;
+                                            changed = true;
+                                        }
+                                    }
+                                });
+                            egui::ComboBox::from_id_salt("blur_weighting")
+                                .selected_text(format!("{:?}", blur.weighting))
+                                .show_ui(ui, |ui| {
+                                    for weighting in [
+                                        BlurWeighting::Box,
+                                        BlurWeighting::Triangle,
+                                        BlurWeighting::Gaussian,
+                                    ] {
+                                        if ui
+                                            .selectable_label(
+                                                blur.weighting == weighting,
+                                                format!("{weighting:?}"),
+                                            )
+                                            .clicked()
+                                        {
+                                            blur.weighting = weighting;
+                                            changed = true;
+                                        }
+                                    }
+                                });
+                        });
+                        ui.end_row();
+
+                        ui.label("Accumulate");
+                        ui.horizontal(|ui| {
+                            let label = |mode: Option<AccumulateMode>| match mode {
+                                None => "Off",
+                                Some(AccumulateMode::Lighten) => "Lighten (trails)",
+                                Some(AccumulateMode::Darken) => "Darken",
+                                Some(AccumulateMode::Average) => "Average",
+                                Some(AccumulateMode::Median) => "Median",
+                            };
+                            let current = export.accumulate.map(|a| a.mode);
+                            egui::ComboBox::from_id_salt("accumulate")
+                                .selected_text(label(current))
+                                .show_ui(ui, |ui| {
+                                    for mode in [
+                                        None,
+                                        Some(AccumulateMode::Lighten),
+                                        Some(AccumulateMode::Darken),
+                                        Some(AccumulateMode::Average),
+                                        Some(AccumulateMode::Median),
+                                    ] {
+                                        if ui.selectable_label(current == mode, label(mode)).clicked()
+                                        {
+                                            export.accumulate = mode.map(|mode| Accumulate {
+                                                mode,
+                                                // The median needs a window.
+                                                window: (mode == AccumulateMode::Median)
+                                                    .then_some(30),
+                                                decay: 0.0,
+                                            });
+                                            changed = true;
+                                        }
+                                    }
+                                });
+                            let Some(accumulate) = export.accumulate.as_mut() else {
+                                return;
+                            };
+                            let mut window = accumulate.window.unwrap_or(0);
+                            if ui
+                                .add(
+                                    egui::DragValue::new(&mut window)
+                                        .range(0..=256)
+                                        .custom_formatter(|v, _| {
+                                            if v < 2.0 {
+                                                "all frames".to_string()
+                                            } else {
+                                                format!("{v} frames")
+                                            }
+                                        }),
+                                )
+                                .changed()
+                            {
+                                accumulate.window = (window >= 2).then_some(window);
+                                changed = true;
+                            }
+                            changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut accumulate.decay)
+                                        .range(0.0..=0.99)
+                                        .speed(0.005)
+                                        .prefix("decay "),
+                                )
+                                .changed();
+                        });
+                        ui.end_row();
+
+                        ui.label("Stack");
+                        ui.horizontal(|ui| {
+                            let mut enabled = export.stack.is_some();
+                            if ui.checkbox(&mut enabled, "").changed() {
+                                export.stack = enabled.then_some(Stack {
+                                    frames: 4,
+                                    mode: StackMode::default(),
+                                    align: StackAlign::default(),
+                                });
+                                changed = true;
+                            }
+                            let Some(stack) = export.stack.as_mut() else {
+                                return;
+                            };
+                            changed |= ui
+                                .add(
+                                    egui::DragValue::new(&mut stack.frames)
+                                        .range(2..=64)
+                                        .suffix(" frames"),
+                                )
+                                .changed();
+                            let mut sliding = stack.mode == StackMode::Sliding;
+                            if ui.checkbox(&mut sliding, "sliding").changed() {
+                                stack.mode = if sliding {
+                                    StackMode::Sliding
+                                } else {
+                                    StackMode::Groups
+                                };
+                                changed = true;
+                            }
+                            egui::ComboBox::from_id_salt("stack_align")
+                                .selected_text(format!("{:?}", stack.align))
+                                .show_ui(ui, |ui| {
+                                    for align in [
+                                        StackAlign::None,
+                                        StackAlign::Translation,
+                                        StackAlign::Rotation,
+                                    ] {
+                                        if ui
+                                            .selectable_label(
+                                                stack.align == align,
+                                                format!("{align:?}"),
+                                            )
+                                            .clicked()
+                                        {
+                                            stack.align = align;
+                                            changed = true;
+                                        }
+                                    }
+                                });
+                        });
+                        ui.end_row();
+
+                        ui.label("Speed");
+                        match export.speed {
+                            None | Some(Curve::Constant(_)) => {
+                                let mut speed = match export.speed {
+                                    Some(Curve::Constant(v)) => v,
+                                    _ => 1.0,
+                                };
+                                if ui
+                                    .add(
+                                        egui::DragValue::new(&mut speed)
+                                            .range(0.01..=100.0)
+                                            .speed(0.01)
+                                            .suffix("×"),
+                                    )
+                                    .on_hover_text(
+                                        "Source frames per output frame; keyframe a ramp in the project file",
+                                    )
+                                    .changed()
+                                {
+                                    export.speed =
+                                        (speed != 1.0).then_some(Curve::Constant(speed));
+                                    changed = true;
+                                }
+                            }
+                            Some(_) => {
+                                ui.label(egui::RichText::new("Ramp (project file)").weak());
+                            }
                         }
                         ui.end_row();
 
+                        ui.label("Real-time pacing");
+                        ui.horizontal(|ui| {
+                            let mut enabled = export.retime.is_some();
+                            if ui.checkbox(&mut enabled, "").changed() {
+                                export.retime = enabled.then(Retime::default);
+                                changed = true;
+                            }
+                            if let Some(retime) = export.retime.as_mut() {
+                                let mut auto = retime.seconds_per_frame.is_none();
+                                if ui.checkbox(&mut auto, "Median interval").changed() {
+                                    retime.seconds_per_frame = if auto { None } else { Some(10.0) };
+                                    changed = true;
+                                }
+                                if let Some(seconds) = retime.seconds_per_frame.as_mut() {
+                                    changed |= ui
+                                        .add(
+                                            egui::DragValue::new(seconds)
+                                                .range(0.1..=3600.0)
+                                                .suffix(" s/frame"),
+                                        )
+                                        .changed();
+                                }
+                                changed |= ui.checkbox(&mut retime.blend, "Blend").changed();
+                            }
+                        });
+                        ui.end_row();
+
                         ui.label("Output");
                         ui.add(
                             egui::Label::new(
@@ -125,6 +125,13 @@ pub fn show(app: &mut StudioApp, ctx: &egui::Context) {
                     ui.close();
                     app.job_holygrail();
                 }
+                if ui
+                    .add_enabled(idle, egui::Button::new("Compensate RAW white balance"))
+                    .clicked()
+                {
+                    ui.close();
+                    app.job_white_balance();
+                }
                 if ui
                     .add_enabled(idle, egui::Button::new("Deflicker"))
                     .clicked()
@@ -132,6 +139,34 @@ pub fn show(app: &mut StudioApp, ctx: &egui::Context) {
                     ui.close();
                     app.job_deflicker();
                 }
+                if ui
+                    .add_enabled(idle, egui::Button::new("Find bad frames"))
+                    .clicked()
+                {
+                    ui.close();
+                    app.job_outliers();
+                }
+                if ui
+                    .add_enabled(idle, egui::Button::new("Find scenes"))
+                    .clicked()
+                {
+                    ui.close();
+                    app.job_scenes();
+                }
+                if ui
+                    .add_enabled(idle, egui::Button::new("Stabilize"))
+                    .clicked()
+                {
+                    ui.close();
+                    app.job_stabilize();
+                }
+                if ui
+                    .add_enabled(idle, egui::Button::new("Check capture intervals"))
+                    .clicked()
+                {
+                    ui.close();
+                    app.job_intervals();
+                }
                 if ui
                     .add_enabled(idle, egui::Button::new("Suggest keyframes"))
                     .clicked()
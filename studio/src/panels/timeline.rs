@@ -1,5 +1,6 @@
 //! Bottom panel: frame scrubber plus the layer curve graph with editable
-//! keyframes for the selected parameter.
+//! keyframes for the selected parameter. Shift-click a keyframe to give
+//! its outgoing segment Bezier handles; drag the handles to shape it.
 
 use egui_plot::{Line, Plot, PlotPoint, PlotPoints, Points, VLine};
 
@@ -7,7 +8,10 @@ const SOURCE_COLOR: egui::Color32 = egui::Color32::from_rgb(90, 160, 255);
 const DEVELOPED_COLOR: egui::Color32 = egui::Color32::from_rgb(240, 120, 200);
 const COMPENSATION_COLOR: egui::Color32 = egui::Color32::from_rgb(255, 165, 60);
 const DEFLICKER_COLOR: egui::Color32 = egui::Color32::from_rgb(90, 200, 120);
-use lapsify::Curve;
+use lapsify::curve::{bezier_handles, make_bezier};
+use lapsify::exif::format_capture_time;
+use lapsify::timeline::Timeline;
+use lapsify::{Curve, Easing, Handle};
 
 use crate::app::StudioApp;
 use crate::document::ParamId;
@@ -66,6 +70,7 @@ pub fn show(app: &mut StudioApp, ctx: &egui::Context) {
                             {
                                 app.selected_param = param;
                                 app.dragging_keyframe = None;
+                                app.dragging_handle = None;
                             }
                         }
                     });
@@ -192,8 +197,9 @@ pub fn show(app: &mut StudioApp, ctx: &egui::Context) {
                 }
 
                 // The selected parameter's curve, sampled per frame.
+                let timeline = Timeline::of(&doc.project, frame_count);
                 let sampled: Vec<[f64; 2]> = (0..=last_frame)
-                    .map(|f| [f as f64, param_curve.sample(f) as f64])
+                    .map(|f| [f as f64, param_curve.sample_on(f, &timeline) as f64])
                     .collect();
                 plot_ui.line(
                     Line::new(param.label(), PlotPoints::from(sampled))
@@ -211,9 +217,49 @@ pub fn show(app: &mut StudioApp, ctx: &egui::Context) {
                 // ---- keyframe editing ---------------------------------
                 let keyframes: Vec<(u32, f32)> = match &param_curve {
                     Curve::Keyframed(kfs) => kfs.iter().map(|k| (k.frame, k.value)).collect(),
-                    Curve::Constant(_) => Vec::new(),
+                    Curve::Constant(_) | Curve::Expression(_) => Vec::new(),
                 };
 
+                // Bezier handles as absolute plot points, with their owner.
+                let handles: Vec<(usize, HandleSide, [f64; 2])> = match &param_curve {
+                    Curve::Keyframed(kfs) => (0..kfs.len())
+                        .flat_map(|i| {
+                            let (incoming, outgoing) = bezier_handles(kfs, i);
+                            let at = |h: Handle| {
+                                [
+                                    kfs[i].frame as f64 + h.frames as f64,
+                                    (kfs[i].value + h.value) as f64,
+                                ]
+                            };
+                            [
+                                incoming.map(|h| (i, HandleSide::In, at(h))),
+                                outgoing.map(|h| (i, HandleSide::Out, at(h))),
+                            ]
+                        })
+                        .flatten()
+                        .collect(),
+                    Curve::Constant(_) | Curve::Expression(_) => Vec::new(),
+                };
+                for (i, _, point) in &handles {
+                    let (f, v) = keyframes[*i];
+                    plot_ui.line(
+                        Line::new(
+                            "handle",
+                            PlotPoints::from(vec![[f as f64, v as f64], *point]),
+                        )
+                        .color(crate::theme::TEXT_WEAK)
+                        .width(1.0),
+                    );
+                }
+                if !handles.is_empty() {
+                    let points: Vec<[f64; 2]> = handles.iter().map(|(_, _, p)| *p).collect();
+                    plot_ui.points(
+                        Points::new("handles", PlotPoints::from(points))
+                            .radius(3.5)
+                            .color(crate::theme::TEXT_WEAK),
+                    );
+                }
+
                 if !keyframes.is_empty() {
                     let marker_points: Vec<[f64; 2]> = keyframes
                         .iter()
@@ -245,11 +291,46 @@ pub fn show(app: &mut StudioApp, ctx: &egui::Context) {
                         .map(|(i, _)| i)
                 });
 
+                // Nearest handle, checked first: handles sit close to their
+                // keyframe and would otherwise be unreachable.
+                let nearest_handle = pointer.and_then(|p| {
+                    let cursor = plot_ui.screen_from_plot(p);
+                    handles
+                        .iter()
+                        .map(|(i, side, [x, y])| {
+                            let screen = plot_ui.screen_from_plot(PlotPoint::new(*x, *y));
+                            ((*i, *side), cursor.distance(screen))
+                        })
+                        .filter(|(_, d)| *d < 8.0)
+                        .min_by(|a, b| a.1.total_cmp(&b.1))
+                        .map(|(h, _)| h)
+                });
+
                 if response.drag_started() {
-                    app.dragging_keyframe = nearest;
+                    app.dragging_handle = nearest_handle;
+                    if nearest_handle.is_none() {
+                        app.dragging_keyframe = nearest;
+                    }
                 }
                 if response.dragged() {
-                    if let (Some(index), Some(p)) = (app.dragging_keyframe, pointer) {
+                    if let (Some((index, side)), Some(p)) = (app.dragging_handle, pointer) {
+                        let (f, v) = keyframes[index];
+                        // Handles stay on their side of the keyframe and
+                        // inside the parameter range (they bound the curve).
+                        let frames = (p.x - f as f64) as f32;
+                        let frames = match side {
+                            HandleSide::In => frames.min(0.0),
+                            HandleSide::Out => frames.max(0.0),
+                        };
+                        edited = Some(EditAction::Handle {
+                            index,
+                            side,
+                            handle: Handle {
+                                frames,
+                                value: (p.y as f32).clamp(min_v, max_v) - v,
+                            },
+                        });
+                    } else if let (Some(index), Some(p)) = (app.dragging_keyframe, pointer) {
                         edited = Some(EditAction::Drag {
                             index,
                             frame: (p.x.round() as i64).clamp(0, last_frame as i64) as u32,
@@ -259,7 +340,9 @@ pub fn show(app: &mut StudioApp, ctx: &egui::Context) {
                 }
                 if response.drag_stopped() {
                     app.dragging_keyframe = None;
+                    app.dragging_handle = None;
                 }
+                let shift = plot_ui.ctx().input(|i| i.modifiers.shift);
                 if response.double_clicked() {
                     if let Some(p) = pointer {
                         edited = Some(EditAction::Add {
@@ -271,6 +354,8 @@ pub fn show(app: &mut StudioApp, ctx: &egui::Context) {
                     if let Some(index) = nearest {
                         edited = Some(EditAction::Delete { index });
                     }
+                } else if let (true, true, Some(index)) = (response.clicked(), shift, nearest) {
+                    edited = Some(EditAction::ToggleBezier { index });
                 } else if response.clicked() && nearest.is_none() {
                     // Plain click scrubs the playhead.
                     if let Some(p) = pointer {
@@ -287,6 +372,12 @@ pub fn show(app: &mut StudioApp, ctx: &egui::Context) {
         });
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandleSide {
+    In,
+    Out,
+}
+
 enum EditAction {
     Drag {
         index: usize,
@@ -300,9 +391,36 @@ enum EditAction {
     Delete {
         index: usize,
     },
+    Handle {
+        index: usize,
+        side: HandleSide,
+        handle: Handle,
+    },
+    ToggleBezier {
+        index: usize,
+    },
 }
 
 fn apply_edit(doc: &mut crate::document::Document, param: ParamId, action: EditAction) {
+    // The axis the curve is sampled on, for Bezier handles that keep its
+    // shape.
+    let axis: Vec<f32> = {
+        let frames = doc.frame_count();
+        let timeline = Timeline::of(&doc.project, frames);
+        (0..frames as u32).map(|f| timeline.x(f)).collect()
+    };
+    let x_of = |f: u32| {
+        axis.get(f as usize)
+            .or(axis.last())
+            .copied()
+            .unwrap_or(f as f32)
+    };
+    let capture_times = doc
+        .project
+        .analysis
+        .as_ref()
+        .and_then(|a| a.capture_times_ms.clone())
+        .unwrap_or_default();
     let curve = doc.curve_mut(param);
     match action {
         EditAction::Add { frame, value } => match curve {
@@ -313,6 +431,8 @@ fn apply_edit(doc: &mut crate::document::Document, param: ParamId, action: EditA
                 Ok(i) => kfs[i].value = value,
                 Err(i) => kfs.insert(i, lapsify::Keyframe::new(frame, value)),
             },
+            // Expressions are edited as text in the project, not on the plot.
+            Curve::Expression(_) => {}
         },
         EditAction::Delete { index } => {
             if let Curve::Keyframed(kfs) = curve {
@@ -343,6 +463,39 @@ fn apply_edit(doc: &mut crate::document::Document, param: ParamId, action: EditA
                         .unwrap_or(u32::MAX);
                     kfs[index].frame = frame.clamp(lo, hi.max(lo));
                     kfs[index].value = value;
+                    // An anchored keyframe follows the drag to its new moment.
+                    if kfs[index].at.is_some() {
+                        kfs[index].at = capture_times
+                            .get(kfs[index].frame as usize)
+                            .map(|&ms| format_capture_time(ms));
+                    }
+                }
+            }
+        }
+        EditAction::Handle {
+            index,
+            side,
+            handle,
+        } => {
+            if let Curve::Keyframed(kfs) = curve {
+                if let Some(k) = kfs.get_mut(index) {
+                    match side {
+                        HandleSide::In => k.in_handle = Some(handle),
+                        HandleSide::Out => k.out_handle = Some(handle),
+                    }
+                }
+            }
+        }
+        EditAction::ToggleBezier { index } => {
+            if let Curve::Keyframed(kfs) = curve {
+                if index + 1 < kfs.len() {
+                    if kfs[index].easing == Easing::Bezier {
+                        kfs[index].easing = Easing::Smooth;
+                        kfs[index].out_handle = None;
+                        kfs[index + 1].in_handle = None;
+                    } else {
+                        make_bezier(kfs, index, x_of);
+                    }
                 }
             }
         }
@@ -36,7 +36,7 @@ pub fn section(ui: &mut egui::Ui, doc: &Document, idle: bool) -> Option<Workflow
     let has_deflicker = analysis.is_some_and(|a| a.deflicker.is_some());
     let keyframes = match &doc.project.color.exposure {
         Curve::Keyframed(kfs) => kfs.len(),
-        Curve::Constant(_) => 0,
+        Curve::Constant(_) | Curve::Expression(_) => 0,
     };
 
     ui.add_space(6.0);
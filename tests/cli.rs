@@ -4,6 +4,7 @@ use std::process::Command;
 
 use assert_cmd::prelude::*;
 use image::{ImageBuffer, Rgb};
+use lapsify::color::transfer::{linear_to_srgb, srgb_to_linear};
 use predicates::prelude::*;
 
 fn write_frames(dir: &Path, count: u32) {
@@ -407,3 +408,670 @@ fn keyframed_crop_video_encodes_fixed_size() {
 
     assert!(output.join("timelapse.mp4").exists());
 }
+
+#[test]
+fn curves_bake_moves_a_layer_into_keyframes() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    write_frames(&input, 10);
+
+    // A deflicker layer that ramps up and back down: 3 keyframes reproduce it.
+    let offsets = [0.0, 0.1, 0.2, 0.3, 0.4, 0.5, 0.4, 0.3, 0.2, 0.1];
+    let project_path = tmp.path().join("project.json");
+    let project = serde_json::json!({
+        "version": 1,
+        "input": input.to_str().unwrap(),
+        "color": { "exposure": 0.25 },
+        "export": { "output": tmp.path().join("out").to_str().unwrap(), "format": "jpg" },
+        "analysis": { "deflicker": {
+            "target": vec![0.18; 10], "offsets": offsets, "smoothing_frames": 4,
+            "threshold_ev": 0.02, "passes_run": 1, "converged": true,
+            "computed_at_unix": 0, "source_fingerprint": ""
+        } }
+    });
+    fs::write(&project_path, project.to_string()).unwrap();
+
+    lapsify()
+        .args(["curves", "bake", "-p", project_path.to_str().unwrap()])
+        .args(["--layer", "deflicker", "--clear"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("max_error"));
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&project_path).unwrap()).unwrap();
+    assert!(saved["analysis"].get("deflicker").is_none());
+    let keyframes = saved["color"]["exposure"].as_array().unwrap();
+    assert_eq!(keyframes.len(), 3);
+    assert_eq!(keyframes[1]["frame"], 5);
+    assert!((keyframes[1]["value"].as_f64().unwrap() - 0.75).abs() < 1e-4);
+}
+
+#[test]
+fn project_rebase_follows_a_deleted_frame() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    write_frames(&input, 6);
+
+    let project_path = tmp.path().join("project.json");
+    let project = serde_json::json!({
+        "version": 1,
+        "input": input.to_str().unwrap(),
+        "color": { "exposure": [
+            { "frame": 0, "value": 0.0 },
+            { "frame": 4, "value": 1.0 }
+        ] },
+        "export": { "output": tmp.path().join("out").to_str().unwrap(), "format": "jpg" }
+    });
+    fs::write(&project_path, project.to_string()).unwrap();
+
+    // The first run records the folder as the baseline.
+    lapsify()
+        .args(["project", "rebase", "-p", project_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("recorded 6 frame(s)"));
+
+    fs::remove_file(input.join("frame_001.png")).unwrap();
+    lapsify()
+        .args(["project", "rebase", "-p", project_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("1 deleted"));
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&project_path).unwrap()).unwrap();
+    assert_eq!(saved["color"]["exposure"][1]["frame"], 3);
+    assert_eq!(saved["analysis"]["frames"].as_array().unwrap().len(), 5);
+}
+
+#[test]
+fn excluded_frames_are_skipped_or_filled() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    write_frames(&input, 5);
+
+    let render = |out: &Path, fill: bool| {
+        let mut cmd = lapsify();
+        cmd.args(["-i", input.to_str().unwrap()])
+            .args(["-o", out.to_str().unwrap()])
+            .args(["-f", "png", "--exclude", "1,3-3"]);
+        if fill {
+            cmd.arg("--fill-excluded");
+        }
+        cmd.assert().success();
+        let mut names: Vec<String> = fs::read_dir(out)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect();
+        names.sort();
+        names
+    };
+
+    let skipped = render(&tmp.path().join("skipped"), false);
+    assert_eq!(
+        skipped,
+        [
+            "frame_000_processed.png",
+            "frame_002_processed.png",
+            "frame_004_processed.png"
+        ]
+    );
+
+    // A filled frame is the even blend of its neighbors.
+    let filled_dir = tmp.path().join("filled");
+    assert_eq!(render(&filled_dir, true).len(), 5);
+    let load = |name: &str| image::open(filled_dir.join(name)).unwrap().to_rgb8();
+    let (a, mid, b) = (
+        load("frame_000_processed.png"),
+        load("frame_001_processed.png"),
+        load("frame_002_processed.png"),
+    );
+    for ((pa, pm), pb) in a.pixels().zip(mid.pixels()).zip(b.pixels()) {
+        for c in 0..3 {
+            let expected = (pa[c] as f32 + pb[c] as f32) / 2.0;
+            assert!((pm[c] as f32 - expected).abs() <= 1.0);
+        }
+    }
+}
+
+#[test]
+fn retime_paces_output_by_capture_time() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    write_frames(&input, 4);
+    let out = tmp.path().join("out");
+
+    // Without capture times there is nothing to pace by.
+    lapsify()
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", out.to_str().unwrap(), "-f", "png", "--retime", "10"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("capture times"));
+
+    // A 40 s pause after frame 1 is filled by repeating frames 1 and 2.
+    let project_path = tmp.path().join("project.json");
+    let project = serde_json::json!({
+        "version": 1,
+        "input": input.to_str().unwrap(),
+        "export": { "output": out.to_str().unwrap(), "format": "png" },
+        "analysis": { "capture_times_ms": [0, 10_000, 50_000, 60_000] }
+    });
+    fs::write(&project_path, project.to_string()).unwrap();
+    lapsify()
+        .args([
+            "render",
+            "-p",
+            project_path.to_str().unwrap(),
+            "--retime",
+            "10",
+        ])
+        .assert()
+        .success();
+    assert_eq!(fs::read_dir(&out).unwrap().count(), 7);
+    let load = |i: u32| {
+        image::open(out.join(format!("retimed_{i:06}.png")))
+            .unwrap()
+            .to_rgb8()
+    };
+    assert_eq!(load(1), load(2));
+    assert_ne!(load(5), load(6));
+
+    // Capture times recorded for other frames are stale.
+    let mut stale = project.clone();
+    let key = serde_json::json!({ "name": "other.jpg", "size": 0, "modified_unix": 0 });
+    stale["analysis"]["frames"] = vec![key; 4].into();
+    fs::write(&project_path, stale.to_string()).unwrap();
+    lapsify()
+        .args([
+            "render",
+            "-p",
+            project_path.to_str().unwrap(),
+            "--retime",
+            "10",
+        ])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("current source frames"));
+}
+
+#[test]
+fn speed_ramp_blends_slowed_frames() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    write_frames(&input, 3);
+    let out = tmp.path().join("out");
+
+    lapsify()
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", out.to_str().unwrap(), "-f", "png", "--speed", "0.5"])
+        .assert()
+        .success();
+    assert_eq!(fs::read_dir(&out).unwrap().count(), 5);
+
+    // Output frame 1 sits halfway between source frames 0 and 1.
+    let load = |i: u32| {
+        image::open(out.join(format!("retimed_{i:06}.png")))
+            .unwrap()
+            .to_rgb8()
+    };
+    let (a, mid, b) = (load(0), load(1), load(2));
+    for ((pa, pm), pb) in a.pixels().zip(mid.pixels()).zip(b.pixels()) {
+        for c in 0..3 {
+            let expected = (pa[c] as f32 + pb[c] as f32) / 2.0;
+            assert!((pm[c] as f32 - expected).abs() <= 1.0);
+        }
+    }
+
+    lapsify()
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", out.to_str().unwrap(), "-f", "png"])
+        .args(["--speed", "0.5", "--retime", "auto"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("speed"));
+}
+
+#[test]
+fn output_fps_adds_flow_interpolated_frames() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    write_frames(&input, 3);
+    let out = tmp.path().join("out");
+
+    lapsify()
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", out.to_str().unwrap(), "-f", "png"])
+        .args(["--fps", "24", "--output-fps", "48"])
+        .args(["--frame-interpolation", "flow"])
+        .assert()
+        .success();
+    assert_eq!(fs::read_dir(&out).unwrap().count(), 5);
+}
+
+#[test]
+fn motion_blur_averages_image_sequence_frames() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    write_frames(&input, 3);
+    let sharp = tmp.path().join("sharp");
+    let blurred = tmp.path().join("blurred");
+
+    for (out, blur) in [(&sharp, None), (&blurred, Some("3"))] {
+        let mut cmd = lapsify();
+        cmd.args(["-i", input.to_str().unwrap()])
+            .args(["-o", out.to_str().unwrap(), "-f", "png"]);
+        if let Some(frames) = blur {
+            cmd.args(["--motion-blur", frames]);
+        }
+        cmd.assert().success();
+    }
+
+    let load = |dir: &Path, i: u32| {
+        image::open(dir.join(format!("frame_{i:03}_processed.png")))
+            .unwrap()
+            .to_rgb8()
+    };
+    let frames: Vec<_> = (0..3).map(|i| load(&sharp, i)).collect();
+    let middle = load(&blurred, 1);
+    for (i, pixel) in middle.pixels().enumerate() {
+        for c in 0..3 {
+            let mean: f32 = frames
+                .iter()
+                .map(|f| f.as_raw()[i * 3 + c] as f32)
+                .sum::<f32>()
+                / 3.0;
+            assert!((pixel[c] as f32 - mean).abs() <= 1.0);
+        }
+    }
+
+    lapsify()
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", blurred.to_str().unwrap(), "-f", "png"])
+        .args(["--blur-weighting", "gaussian"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("needs motion blur"));
+}
+
+#[test]
+fn lighten_accumulation_builds_trails_and_a_still() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    write_frames(&input, 3);
+    let sharp = tmp.path().join("sharp");
+    let trails = tmp.path().join("trails");
+
+    lapsify()
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", sharp.to_str().unwrap(), "-f", "png"])
+        .assert()
+        .success();
+    lapsify()
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", trails.to_str().unwrap(), "-f", "png"])
+        .args(["--accumulate", "lighten"])
+        .assert()
+        .success();
+
+    let load = |path: std::path::PathBuf| image::open(path).unwrap().to_rgb8();
+    let frames: Vec<_> = (0..3)
+        .map(|i| load(sharp.join(format!("frame_{i:03}_processed.png"))))
+        .collect();
+    let last = load(trails.join("frame_002_processed.png"));
+    let still = load(trails.join("accumulated.png"));
+    assert_eq!(last, still);
+    for (i, pixel) in still.pixels().enumerate() {
+        for c in 0..3 {
+            let max = frames.iter().map(|f| f.as_raw()[i * 3 + c]).max().unwrap();
+            assert_eq!(pixel[c], max);
+        }
+    }
+
+    lapsify()
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", trails.to_str().unwrap(), "-f", "png"])
+        .args(["--accumulate", "median"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("needs a window"));
+}
+
+#[test]
+fn stacking_averages_groups_in_linear_light() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    write_frames(&input, 4);
+    let output = tmp.path().join("out");
+
+    lapsify()
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", output.to_str().unwrap(), "-f", "png"])
+        .args(["--stack", "2", "--stack-align", "none"])
+        .assert()
+        .success();
+
+    let mut written: Vec<String> = fs::read_dir(&output)
+        .unwrap()
+        .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+        .collect();
+    written.sort();
+    assert_eq!(
+        written,
+        vec!["frame_001_processed.png", "frame_003_processed.png"]
+    );
+
+    let load = |path: std::path::PathBuf| image::open(path).unwrap().to_rgb8();
+    let a = load(input.join("frame_000.png"));
+    let b = load(input.join("frame_001.png"));
+    let stacked = load(output.join("frame_001_processed.png"));
+    let linear = |v: u8| srgb_to_linear(v as f32 / 255.0);
+    for ((&va, &vb), &out) in a.iter().zip(b.iter()).zip(stacked.iter()) {
+        let expected = linear_to_srgb((linear(va) + linear(vb)) / 2.0) * 255.0;
+        assert!((out as f32 - expected).abs() <= 1.0, "{out} vs {expected}");
+    }
+
+    lapsify()
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", output.to_str().unwrap()])
+        .args(["--stack-sliding"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("needs --stack"));
+}
+
+#[test]
+fn outliers_apply_excludes_a_black_frame() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    fs::create_dir_all(&input).unwrap();
+    // A steady scene that brightens slowly, with the lens cap on at frame 5.
+    for i in 0..8u32 {
+        let img = ImageBuffer::from_fn(64, 48, |x, y| {
+            let level = if i == 5 {
+                0
+            } else {
+                60 + (x * 2 + y) % 40 + (x / 8 + y / 8) % 2 * 60 + i * 3
+            };
+            Rgb([level as u8; 3])
+        });
+        img.save(input.join(format!("frame_{i:03}.png"))).unwrap();
+    }
+
+    let project_path = tmp.path().join("project.json");
+    let project = serde_json::json!({
+        "version": 1,
+        "input": input.to_str().unwrap(),
+        "export": { "output": tmp.path().join("out").to_str().unwrap(), "format": "jpg" }
+    });
+    fs::write(&project_path, project.to_string()).unwrap();
+
+    lapsify()
+        .args(["analyze", "outliers", "--apply"])
+        .args(["-p", project_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("frame 5: near black"));
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&project_path).unwrap()).unwrap();
+    assert_eq!(saved["excluded_frames"], serde_json::json!([5]));
+    assert_eq!(
+        saved["analysis"]["outliers"]["flags"][0]["reason"],
+        "near_black"
+    );
+}
+
+#[test]
+fn scenes_split_into_folders_and_projects() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    fs::create_dir_all(&input).unwrap();
+    // Two shoots on one card: a checkerboard, then a horizontal gradient.
+    for i in 0..10u32 {
+        let img = ImageBuffer::from_fn(64, 48, |x, y| {
+            let level = if i < 5 {
+                40 + (x / 8 + y / 8) % 2 * 150 + i
+            } else {
+                20 + x * 3 + i
+            };
+            Rgb([level as u8; 3])
+        });
+        img.save(input.join(format!("frame_{i:03}.png"))).unwrap();
+    }
+
+    let project_path = tmp.path().join("shoot.json");
+    let project = serde_json::json!({
+        "version": 1,
+        "input": input.to_str().unwrap(),
+        "color": { "exposure": [
+            { "frame": 0, "value": 0.0 },
+            { "frame": 9, "value": 0.9 }
+        ] },
+        "export": { "output": tmp.path().join("out.mp4").to_str().unwrap(), "format": "mp4" }
+    });
+    fs::write(&project_path, project.to_string()).unwrap();
+
+    lapsify()
+        .args(["analyze", "scenes", "-p", project_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("scene 2: frames 5-9"));
+
+    lapsify()
+        .args(["project", "split", "-p", project_path.to_str().unwrap()])
+        .assert()
+        .success();
+
+    assert!(input.join("scene-01/frame_004.png").exists());
+    assert!(input.join("scene-02/frame_005.png").exists());
+    assert!(!input.join("frame_000.png").exists());
+
+    let second: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(tmp.path().join("shoot-scene-02.json")).unwrap())
+            .unwrap();
+    assert!(second["input"].as_str().unwrap().ends_with("scene-02"));
+    assert!(second["export"]["output"]
+        .as_str()
+        .unwrap()
+        .ends_with("out-scene-02.mp4"));
+    // The exposure ramp continues where the first scene left off.
+    assert_eq!(second["color"]["exposure"][0]["frame"], 0);
+    let start = second["color"]["exposure"][0]["value"].as_f64().unwrap();
+    assert!((start - 0.5).abs() < 1e-6, "{start}");
+    assert_eq!(second["analysis"]["frames"].as_array().unwrap().len(), 5);
+}
+
+#[test]
+fn stabilize_writes_a_crop_that_follows_the_shake() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    fs::create_dir_all(&input).unwrap();
+    // A textured scene that jumps 6 px right on odd frames.
+    for i in 0..8u32 {
+        let dx = if i % 2 == 1 { 6.0 } else { 0.0 };
+        let img = ImageBuffer::from_fn(160, 120, |x, y| {
+            let (u, v) = (x as f32 - dx, y as f32);
+            let level = 128.0
+                + 50.0 * (u * 0.21).sin() * (v * 0.17).cos()
+                + 30.0 * (u * 0.05 + v * 0.08).sin();
+            Rgb([level as u8; 3])
+        });
+        img.save(input.join(format!("frame_{i:03}.png"))).unwrap();
+    }
+
+    let project_path = tmp.path().join("project.json");
+    let project = serde_json::json!({
+        "version": 1,
+        "input": input.to_str().unwrap(),
+        "export": { "output": tmp.path().join("out").to_str().unwrap(), "format": "png" }
+    });
+    fs::write(&project_path, project.to_string()).unwrap();
+
+    lapsify()
+        .args(["analyze", "stabilize", "--smoothing", "8"])
+        .args(["-p", project_path.to_str().unwrap()])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Stabilized"));
+
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&project_path).unwrap()).unwrap();
+    let x = saved["crop"]["x"].as_array().unwrap();
+    assert_eq!(x.len(), 8);
+    // The window moves with the scene: about 6 px further right on odd frames.
+    let at = |i: usize| x[i]["value"].as_f64().unwrap() * 160.0;
+    let jump = at(3) - (at(2) + at(4)) / 2.0;
+    assert!((jump - 6.0).abs() < 1.0, "{jump}");
+    assert!(saved["crop"]["width"].as_f64().unwrap() < 1.0);
+
+    lapsify()
+        .args(["analyze", "stabilize", "--reset"])
+        .args(["-p", project_path.to_str().unwrap()])
+        .assert()
+        .success();
+    let saved: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(&project_path).unwrap()).unwrap();
+    assert!(saved.get("crop").is_none());
+    assert!(saved["analysis"].get("stabilize").is_none());
+}
+
+#[test]
+fn keystone_warps_before_a_crop_that_must_stay_inside() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    let output = tmp.path().join("out");
+    fs::create_dir_all(&input).unwrap();
+    ImageBuffer::from_pixel(64, 48, Rgb([200u8, 200, 200]))
+        .save(input.join("frame_000.png"))
+        .unwrap();
+
+    // Top corners pulled together: the top corners of the frame lose their
+    // source pixels, which only a crop may leave out.
+    lapsify()
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", output.to_str().unwrap()])
+        .args(["-f", "png", "--keystone", "-0.25"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("empty edges"));
+    lapsify()
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", output.to_str().unwrap()])
+        .args(["-f", "png", "--keystone", "-0.25", "--crop", "32:24:16:24"])
+        .assert()
+        .success();
+    let out = image::open(output.join("frame_000_processed.png"))
+        .unwrap()
+        .to_rgb8();
+    assert_eq!(out.dimensions(), (32, 24));
+    assert!(out.pixels().all(|p| p.0 == [200, 200, 200]));
+
+    // A full-frame crop would show those wedges.
+    lapsify()
+        .args(["-i", input.to_str().unwrap()])
+        .args(["-o", output.to_str().unwrap()])
+        .args(["-f", "png", "--keystone", "-0.25", "--crop", "64:48:0:0"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("perspective-corrected"));
+}
+
+#[test]
+fn aspect_and_framing_reshape_without_stretching() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    let output = tmp.path().join("out");
+    fs::create_dir_all(&input).unwrap();
+    ImageBuffer::from_pixel(160, 90, Rgb([200u8, 200, 200]))
+        .save(input.join("frame_000.png"))
+        .unwrap();
+    let render = |args: &[&str]| {
+        lapsify()
+            .args(["-i", input.to_str().unwrap()])
+            .args(["-o", output.to_str().unwrap()])
+            .args(["-f", "png"])
+            .args(args)
+            .assert()
+            .success();
+        image::open(output.join("frame_000_processed.png"))
+            .unwrap()
+            .to_rgb8()
+    };
+
+    // A vertical cut of the landscape frame, trimmed at the sides.
+    let vertical = render(&["--aspect", "9:16", "--framing", "fill"]);
+    assert_eq!(vertical.dimensions(), (51, 90));
+    assert!(vertical.pixels().all(|p| p.0 == [200, 200, 200]));
+
+    // The whole frame in a square, with bars above and below.
+    let square = render(&["--resolution", "90x90", "--framing", "letterbox"]);
+    assert_eq!(square.dimensions(), (90, 90));
+    assert_eq!(square.get_pixel(45, 5).0, [0, 0, 0]);
+    assert_eq!(square.get_pixel(45, 45).0, [200, 200, 200]);
+
+    // An aspect-locked crop ignores its height.
+    let locked = render(&["--crop", "80:10:0:0", "--crop-aspect", "1:1"]);
+    assert_eq!(locked.dimensions(), (80, 80));
+}
+
+#[test]
+fn outputs_render_reframes_in_one_run() {
+    let tmp = tempfile::tempdir().unwrap();
+    let input = tmp.path().join("frames");
+    write_frames(&input, 3);
+    let folder = |name: &str| tmp.path().join(name);
+
+    let project = serde_json::json!({
+        "version": 1,
+        "input": input.to_str().unwrap(),
+        "color": { "exposure": 0.5, "contrast": 1.2 },
+        "export": { "output": folder("landscape").to_str().unwrap(), "format": "png" },
+        "outputs": [
+            {
+                "name": "square",
+                "crop": { "x": 0.125, "y": 0.0, "width": 0.75, "height": 1.0 },
+                "export": { "output": folder("square").to_str().unwrap(), "format": "png" }
+            },
+            {
+                "name": "vertical",
+                "export": {
+                    "output": folder("vertical").to_str().unwrap(),
+                    "format": "png",
+                    "aspect": "9:16",
+                    "framing": "fill"
+                }
+            },
+            {
+                "name": "slow",
+                "export": {
+                    "output": folder("slow").to_str().unwrap(),
+                    "format": "png",
+                    "speed": 0.5
+                }
+            }
+        ]
+    });
+    let project_path = tmp.path().join("project.json");
+    fs::write(&project_path, project.to_string()).unwrap();
+
+    lapsify()
+        .args(["render", "--project", project_path.to_str().unwrap()])
+        .assert()
+        .success();
+
+    let open = |dir: &str, name: &str| image::open(folder(dir).join(name)).unwrap().to_rgb8();
+    for i in 0..3 {
+        let name = format!("frame_{i:03}_processed.png");
+        let landscape = open("landscape", &name);
+        let square = open("square", &name);
+        assert_eq!(landscape.dimensions(), (64, 48));
+        assert_eq!(square.dimensions(), (48, 48));
+        // One grade: the square is the middle of the landscape frame.
+        assert_eq!(square.get_pixel(0, 10), landscape.get_pixel(8, 10));
+        assert_eq!(open("vertical", &name).dimensions(), (27, 48));
+    }
+    // Its own timing renders in a pass of its own.
+    assert_eq!(fs::read_dir(folder("slow")).unwrap().count(), 5);
+}
@@ -59,6 +59,7 @@ fn test_project() -> Project {
         version: PROJECT_VERSION,
         input: PathBuf::from("unused"),
         frame_range: None,
+        excluded_frames: Vec::new(),
         interpolation: Default::default(),
         color: ColorGrade {
             exposure: Curve::Keyframed(vec![Keyframe::new(0, -0.5), Keyframe::new(4, 0.8)]),
@@ -67,6 +68,7 @@ fn test_project() -> Project {
             saturation: Curve::Constant(1.3),
             ..ColorGrade::default()
         },
+        perspective: None,
         crop: Some(CropTrack::from_rect(CropRect {
             x: 0.125,
             y: 0.125,
@@ -78,13 +80,14 @@ fn test_project() -> Project {
             export.format = "png".to_string();
             export
         },
+        outputs: Vec::new(),
         analysis: None,
     }
 }
 
 fn check_golden(name: &str, img: DynamicImage, frame: u32) {
     let project = test_project();
-    let rendered = render_frame(img, &project, frame).unwrap().to_rgb8();
+    let rendered = render_frame(img, &project, frame, 5).unwrap().to_rgb8();
 
     let expected_path = fixtures_dir().join(format!("{name}_frame{frame}.png"));
 